@@ -0,0 +1,43 @@
+//! Compares `verify_full` on public transactions, which take the fast path, with
+//! the full shielded pipeline run over the same number of shielded transactions
+//!
+//! Run with `cargo bench --bench verification`.
+
+use namada_shielded_demo::{wallet::ADDRESS_PREFIX, ShieldedTransaction, Wallet};
+use std::time::{Duration, Instant};
+
+const TRANSACTIONS: usize = 2_000;
+
+fn address(byte: char) -> String {
+    format!("{}{}", ADDRESS_PREFIX, byte.to_string().repeat(40))
+}
+
+fn time_verification(transactions: &[ShieldedTransaction]) -> Duration {
+    let start = Instant::now();
+    for transaction in transactions {
+        assert!(transaction.verify_full().unwrap().is_valid());
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let sender = Wallet::new("alice").unwrap();
+    let build = |shielded: bool| -> Vec<ShieldedTransaction> {
+        (1..=TRANSACTIONS as u64)
+            .map(|amount| {
+                if shielded {
+                    ShieldedTransaction::create_shielded(&sender, &address('b'), amount * 1_000)
+                } else {
+                    ShieldedTransaction::create_public(&sender, &address('b'), amount * 1_000)
+                }
+                .unwrap()
+            })
+            .collect()
+    };
+    
+    let public = time_verification(&build(false));
+    let shielded = time_verification(&build(true));
+    println!("public (fast path):  {:?} for {} transactions", public, TRANSACTIONS);
+    println!("shielded (full):     {:?} for {} transactions", shielded, TRANSACTIONS);
+    println!("speedup: {:.1}x", shielded.as_secs_f64() / public.as_secs_f64().max(f64::EPSILON));
+}
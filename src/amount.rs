@@ -0,0 +1,152 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Decimal places used when none are configured (1 NAM = 1_000_000 micro-units)
+pub const DEFAULT_DECIMALS: u8 = 6;
+
+/// Largest supported number of decimal places, so `10^decimals` fits in a `u64`
+pub const MAX_DECIMALS: u8 = 18;
+
+/// Token symbol shown after rendered amounts
+pub const DENOMINATION: &str = "NAM";
+
+/// A raw `u64` amount in micro-units, paired with how many decimals to render
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Amount {
+    pub value: u64,
+    pub decimals: u8,
+}
+
+impl Amount {
+    /// Wrap a raw amount using the default number of decimals
+    pub fn new(value: u64) -> Self {
+        Self {
+            value,
+            decimals: DEFAULT_DECIMALS,
+        }
+    }
+    
+    /// Wrap a raw amount with a specific number of decimals
+    pub fn with_decimals(value: u64, decimals: u8) -> Result<Self> {
+        Self::check_decimals(decimals)?;
+        Ok(Self { value, decimals })
+    }
+    
+    /// Parse a decimal string such as `"1.5"` into raw units
+    ///
+    /// An optional trailing denomination is accepted. More fractional digits than
+    /// `decimals` are rejected rather than silently truncated.
+    pub fn parse(input: &str, decimals: u8) -> Result<Self> {
+        Self::check_decimals(decimals)?;
+        let invalid = |reason: &str| crate::error::ShieldedError::InvalidAmount(
+            format!("Cannot parse '{}': {}", input, reason)
+        );
+        
+        let trimmed = input.trim();
+        let number = trimmed
+            .strip_suffix(DENOMINATION)
+            .map(str::trim_end)
+            .unwrap_or(trimmed);
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid("no digits"));
+        }
+        if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(invalid("expected digits with an optional decimal point"));
+        }
+        if fraction.len() > decimals as usize {
+            return Err(invalid(&format!("more than {} fractional digits", decimals)));
+        }
+        
+        let scale = 10u64.pow(decimals as u32);
+        let whole_units = if whole.is_empty() {
+            0
+        } else {
+            whole.parse::<u64>().map_err(|_| invalid("too large"))?
+        };
+        let fraction_units = if fraction.is_empty() {
+            0
+        } else {
+            format!("{:0<width$}", fraction, width = decimals as usize)
+                .parse::<u64>()
+                .map_err(|_| invalid("too large"))?
+        };
+        
+        let value = whole_units
+            .checked_mul(scale)
+            .and_then(|units| units.checked_add(fraction_units))
+            .ok_or_else(|| invalid("too large"))?;
+        
+        Ok(Self { value, decimals })
+    }
+    
+    fn check_decimals(decimals: u8) -> Result<()> {
+        if decimals > MAX_DECIMALS {
+            return Err(crate::error::ShieldedError::InvalidAmount(
+                format!("At most {} decimals are supported, got {}", MAX_DECIMALS, decimals)
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Amount {
+    type Err = crate::error::ShieldedError;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s, DEFAULT_DECIMALS)
+    }
+}
+
+impl std::fmt::Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.decimals == 0 {
+            return write!(f, "{} {}", self.value, DENOMINATION);
+        }
+        
+        let scale = 10u64.pow(self.decimals as u32);
+        write!(
+            f,
+            "{}.{:0width$} {}",
+            self.value / scale,
+            self.value % scale,
+            DENOMINATION,
+            width = self.decimals as usize
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_decimal_strings_into_micro_units() {
+        assert_eq!("1.5".parse::<Amount>().unwrap().value, 1_500_000);
+        assert_eq!("0.000001".parse::<Amount>().unwrap().value, 1);
+        assert_eq!("2 NAM".parse::<Amount>().unwrap().value, 2_000_000);
+    }
+    
+    #[test]
+    fn rejects_more_fractional_digits_than_configured() {
+        assert!(matches!(
+            "1.1234567".parse::<Amount>(),
+            Err(crate::error::ShieldedError::InvalidAmount(_))
+        ));
+        assert!(Amount::parse("1.123", 2).is_err());
+        assert_eq!(Amount::parse("1.12", 2).unwrap().value, 112);
+    }
+    
+    #[test]
+    fn display_round_trips_through_parse() {
+        let amount = Amount::new(1_500_000);
+        assert_eq!(amount.to_string(), "1.500000 NAM");
+        assert_eq!(amount.to_string().parse::<Amount>().unwrap(), amount);
+        
+        let whole = Amount::with_decimals(42, 0).unwrap();
+        assert_eq!(whole.to_string(), "42 NAM");
+        assert_eq!(Amount::parse(&whole.to_string(), 0).unwrap(), whole);
+    }
+}
@@ -0,0 +1,53 @@
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, ristretto::RistrettoPoint};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha512, Digest};
+
+/// Asset of transactions and notes stored before assets were recorded
+pub const NATIVE_ASSET: &str = "NAM";
+
+/// Identifier of the token a transfer or note moves
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct AssetId(pub String);
+
+impl Default for AssetId {
+    fn default() -> Self {
+        Self::native()
+    }
+}
+
+impl AssetId {
+    pub fn new(id: &str) -> Self {
+        Self(id.to_string())
+    }
+    
+    /// The native token, `NATIVE_ASSET`
+    pub fn native() -> Self {
+        Self::new(NATIVE_ASSET)
+    }
+    
+    pub fn is_native(&self) -> bool {
+        self.0 == NATIVE_ASSET
+    }
+    
+    /// Value generator of this asset's Pedersen commitments
+    ///
+    /// The native asset keeps the basepoint; every other asset hashes its id to the
+    /// curve, so no combination of one asset's values can cancel another's.
+    pub fn value_generator(&self) -> RistrettoPoint {
+        if self.is_native() {
+            return RISTRETTO_BASEPOINT_POINT;
+        }
+        let mut hasher = Sha512::new();
+        hasher.update(b"namada_shielded_demo:asset_generator:");
+        hasher.update(self.0.as_bytes());
+        RistrettoPoint::from_uniform_bytes(&hasher.finalize().into())
+    }
+}
+
+impl std::fmt::Display for AssetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    error::Result,
+    commitment::CommitmentScheme,
+    crypto::{ct_eq, derive_opening},
+    shielded_transaction::{ShieldedTransaction, TransactionType},
+};
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+use hex;
+
+/// The opening of one commitment: its value and the randomness hiding it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Opening {
+    pub amount: u64,
+    /// Nonce of the note commitment
+    pub nonce: String,
+    /// Blinding of the Pedersen value commitment
+    pub blinding: String,
+}
+
+/// Every commitment opening of a shielded transaction, handed to an auditor so
+/// they can check the transaction without trusting the sender
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditBundle {
+    pub transaction_id: String,
+    pub inputs: Vec<Opening>,
+    /// Outputs in transaction order; the first pays the recipient, the rest are change
+    pub outputs: Vec<Opening>,
+}
+
+impl ShieldedTransaction {
+    /// Re-derive every commitment opening from the sender's spending key
+    ///
+    /// Only transactions built with the spending key (`create_shielded_to` and
+    /// friends) can be exported, and a wrong key yields a `CommitmentError`.
+    pub fn export_audit_bundle(&self, spend_key: &str) -> Result<AuditBundle> {
+        if let TransactionType::Public = self.transaction_type {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "Public transactions have no commitments to open".to_string()
+            ));
+        }
+        
+        let input_amount = self.amount.checked_add(self.fee).ok_or_else(|| {
+            crate::error::ShieldedError::InvalidAmount("Amount plus fee overflows".to_string())
+        })?;
+        let output_amounts: Vec<u64> = (0..self.output_commitments.len())
+            .map(|index| if index == 0 { self.amount } else { 0 })
+            .collect();
+        
+        let nonce = |role: &str, index: usize| derive_opening(spend_key, &self.id, role, index);
+        let (input_blindings, output_blindings) = Self::balanced_blindings(
+            self.input_commitments.len(),
+            output_amounts.len(),
+            |role, index| Scalar::from_bytes_mod_order(nonce(role, index)),
+        );
+        
+        let inputs = input_blindings
+            .iter()
+            .enumerate()
+            .map(|(index, blinding)| Opening {
+                amount: input_amount,
+                nonce: hex::encode(nonce("input", index)),
+                blinding: hex::encode(blinding.as_bytes()),
+            })
+            .collect();
+        let outputs = output_amounts
+            .iter()
+            .zip(&output_blindings)
+            .enumerate()
+            .map(|(index, (amount, blinding))| Opening {
+                amount: *amount,
+                nonce: hex::encode(nonce("output", index)),
+                blinding: hex::encode(blinding.as_bytes()),
+            })
+            .collect();
+        
+        let bundle = AuditBundle {
+            transaction_id: self.id.clone(),
+            inputs,
+            outputs,
+        };
+        if !bundle.verify(self)? {
+            return Err(crate::error::ShieldedError::CommitmentError(
+                "Spending key does not open this transaction's commitments".to_string()
+            ));
+        }
+        
+        Ok(bundle)
+    }
+}
+
+impl AuditBundle {
+    /// Recompute every commitment from its opening and check the transaction balances
+    ///
+    /// Returns `false` if any commitment, the recipient amount or the balance
+    /// doesn't match; errors only on a malformed bundle.
+    pub fn verify(&self, transaction: &ShieldedTransaction) -> Result<bool> {
+        if self.transaction_id != transaction.id
+            || self.inputs.len() != transaction.input_commitments.len()
+            || self.inputs.len() != transaction.input_value_commitments.len()
+            || self.outputs.len() != transaction.output_commitments.len()
+            || self.outputs.len() != transaction.output_value_commitments.len()
+        {
+            return Ok(false);
+        }
+        
+        let openings_match = |openings: &[Opening], commitments: &[String], value_commitments: &[String]| -> Result<bool> {
+            for ((opening, commitment), value_commitment) in openings.iter().zip(commitments).zip(value_commitments) {
+                let note_commitment = CommitmentScheme::create_commitment(opening.amount, &Self::decode(&opening.nonce)?)?;
+                let blinding = Scalar::from_canonical_bytes(Self::decode(&opening.blinding)?)
+                    .into_option()
+                    .ok_or_else(|| crate::error::ShieldedError::CommitmentError(
+                        format!("Non-canonical blinding: {}", opening.blinding)
+                    ))?;
+                if !ct_eq(&note_commitment.commitment_hash, commitment)
+                    || !ct_eq(&CommitmentScheme::commit_pedersen(opening.amount, &blinding), value_commitment)
+                {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        };
+        
+        if !openings_match(&self.inputs, &transaction.input_commitments, &transaction.input_value_commitments)?
+            || !openings_match(&self.outputs, &transaction.output_commitments, &transaction.output_value_commitments)?
+        {
+            return Ok(false);
+        }
+        
+        // The first output pays the recipient, and inputs cover the outputs plus the fee
+        let input_total = self.inputs.iter().map(|opening| opening.amount as u128).sum::<u128>();
+        let output_total = self.outputs.iter().map(|opening| opening.amount as u128).sum::<u128>();
+        Ok(self.outputs.first().map(|opening| opening.amount) == Some(transaction.amount)
+            && input_total == output_total + transaction.fee as u128)
+    }
+    
+    fn decode(value: &str) -> Result<[u8; 32]> {
+        hex::decode(value)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crate::error::ShieldedError::CommitmentError(
+                format!("Opening value must be 32 hex-encoded bytes: {}", value)
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::Wallet;
+    
+    fn audited_transaction() -> (Wallet, ShieldedTransaction) {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let transaction = ShieldedTransaction::create_shielded_to(&sender, &recipient.address, &recipient.viewing_public_key, 1_000).unwrap();
+        (sender, transaction)
+    }
+    
+    #[test]
+    fn bundle_verifies_against_its_transaction() {
+        let (sender, transaction) = audited_transaction();
+        let bundle = transaction.export_audit_bundle(sender.spending_key().unwrap()).unwrap();
+        assert!(bundle.verify(&transaction).unwrap());
+        assert_eq!(bundle.outputs[0].amount, 1_000);
+        
+        let stranger = Wallet::new("mallory").unwrap();
+        assert!(transaction.export_audit_bundle(stranger.spending_key().unwrap()).is_err());
+    }
+    
+    #[test]
+    fn bundle_detects_a_tampered_amount() {
+        let (sender, mut transaction) = audited_transaction();
+        let bundle = transaction.export_audit_bundle(sender.spending_key().unwrap()).unwrap();
+        
+        transaction.amount += 1;
+        assert!(!bundle.verify(&transaction).unwrap());
+    }
+}
@@ -0,0 +1,36 @@
+use borsh::io::{Error, ErrorKind, Read, Result, Write};
+use borsh::{BorshDeserialize, BorshSerialize};
+use chrono::{DateTime, Utc};
+
+/// Borsh encoding of a timestamp, which chrono does not provide: the Unix seconds
+/// as an `i64` followed by the sub-second nanoseconds as a `u32`
+pub(crate) fn serialize<W: Write>(timestamp: &DateTime<Utc>, writer: &mut W) -> Result<()> {
+    timestamp.timestamp().serialize(writer)?;
+    timestamp.timestamp_subsec_nanos().serialize(writer)
+}
+
+pub(crate) fn deserialize<R: Read>(reader: &mut R) -> Result<DateTime<Utc>> {
+    let seconds = i64::deserialize_reader(reader)?;
+    let nanos = u32::deserialize_reader(reader)?;
+    DateTime::from_timestamp(seconds, nanos)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Timestamp out of range"))
+}
+
+/// Like `serialize`, with a one-byte tag for `None` or `Some` as for any Borsh `Option`
+pub(crate) fn serialize_option<W: Write>(timestamp: &Option<DateTime<Utc>>, writer: &mut W) -> Result<()> {
+    match timestamp {
+        Some(timestamp) => {
+            1u8.serialize(writer)?;
+            serialize(timestamp, writer)
+        }
+        None => 0u8.serialize(writer),
+    }
+}
+
+pub(crate) fn deserialize_option<R: Read>(reader: &mut R) -> Result<Option<DateTime<Utc>>> {
+    match u8::deserialize_reader(reader)? {
+        0 => Ok(None),
+        1 => deserialize(reader).map(Some),
+        tag => Err(Error::new(ErrorKind::InvalidData, format!("Invalid option tag {}", tag))),
+    }
+}
@@ -1,15 +1,60 @@
-use crate::{error::Result, crypto::{hash, generate_nonce}};
+use crate::{error::Result, asset::AssetId, crypto::{ct_eq, generate_nonce, generate_nonces, HashAlgo}};
+#[cfg(feature = "bulletproofs")]
+use bulletproofs::{BulletproofGens, PedersenGens, ProofError, RangeProof};
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use sha2::{Sha256, Digest};
+use sha2::{Sha256, Sha512, Digest};
 use hex;
+#[cfg(feature = "bulletproofs")]
+use merlin::Transcript;
+
+/// Blinding generator for Pedersen commitments, hashed to the curve so its
+/// discrete log relative to the value generator is unknown
+static PEDERSEN_H: Lazy<RistrettoPoint> = Lazy::new(|| {
+    let mut hasher = Sha512::new();
+    hasher.update(b"namada_shielded_demo:pedersen_h");
+    RistrettoPoint::from_uniform_bytes(&hasher.finalize().into())
+});
+
+/// Bulletproof generators for one value of up to 64 bits
+#[cfg(feature = "bulletproofs")]
+static BULLETPROOF_GENS: Lazy<BulletproofGens> = Lazy::new(|| BulletproofGens::new(64, 1));
+
+/// Transcript label shared by range provers and verifiers
+#[cfg(feature = "bulletproofs")]
+const RANGE_PROOF_LABEL: &[u8] = b"namada_shielded_demo:range_proof";
+
+/// Hex encoding of the identity point, i.e. a Pedersen commitment to zero with zero blinding
+pub const IDENTITY_COMMITMENT: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Sentinel meaning "no value", which must never appear in a transaction
+///
+/// Zero-value outputs such as empty change are committed with a fresh nonce like
+/// any other output, so observers can't tell them apart; only the owner learns the
+/// value by decrypting the note. A transaction carrying this sentinel (or, for
+/// Pedersen commitments, the unblinded identity point it equals) is rejected.
+pub const EMPTY_COMMITMENT: &str = IDENTITY_COMMITMENT;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct Commitment {
     pub commitment_hash: String,
     pub nonce: String,
     pub amount: Option<u64>, // None for hiding the amount
+    /// Hash function the commitment was made with
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
 }
 
+/// Opening of a hash commitment, bound to it by `proof_hash`
+///
+/// Produced by `CommitmentScheme::prove_knowledge_of` and checked by
+/// `verify_knowledge_proof`. It reveals the amount and nonce to the verifier.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KnowledgeProof {
     pub proof_hash: String,
@@ -22,15 +67,59 @@ pub struct CommitmentScheme;
 
 impl CommitmentScheme {
     /// Create a commitment to an amount without revealing it
-    pub fn commit(amount: u64) -> Result<String> {
+    ///
+    /// The returned `Commitment` keeps the nonce and amount for the committer, who
+    /// needs both to open it later; publish only `commitment_hash`.
+    pub fn commit(amount: u64) -> Result<Commitment> {
+        Self::commit_with(HashAlgo::default(), amount)
+    }
+    
+    /// Like `commit`, hashing with the given algorithm
+    pub fn commit_with(algo: HashAlgo, amount: u64) -> Result<Commitment> {
         let nonce = generate_nonce();
-        let commitment = Self::create_commitment(amount, &nonce)?;
-        Ok(commitment.commitment_hash)
+        let commitment = Self::create_commitment_with(algo, amount, &nonce)?;
+        Ok(Commitment {
+            amount: Some(amount),
+            ..commitment
+        })
+    }
+    
+    /// Commit to each of `amounts`, in order
+    ///
+    /// Like calling `commit` per amount, but every nonce comes from one draw on the
+    /// RNG. Each `Commitment` keeps its amount and nonce for opening later.
+    pub fn commit_batch(amounts: &[u64]) -> Result<Vec<Commitment>> {
+        amounts
+            .iter()
+            .zip(generate_nonces(amounts.len()))
+            .map(|(&amount, nonce)| {
+                let commitment = Self::create_commitment(amount, &nonce)?;
+                Ok(Commitment {
+                    amount: Some(amount),
+                    ..commitment
+                })
+            })
+            .collect()
+    }
+    
+    /// Create a commitment to an amount, returning only the hash and discarding the nonce
+    pub fn commit_hash_only(amount: u64) -> Result<String> {
+        Ok(Self::commit(amount)?.commitment_hash)
+    }
+    
+    /// Check whether a commitment is the `EMPTY_COMMITMENT` sentinel
+    pub fn is_empty_sentinel(commitment: &str) -> bool {
+        commitment.eq_ignore_ascii_case(EMPTY_COMMITMENT)
     }
     
     /// Create a commitment with a specific nonce
     pub fn create_commitment(amount: u64, nonce: &[u8; 32]) -> Result<Commitment> {
-        let mut hasher = Sha256::new();
+        Self::create_commitment_with(HashAlgo::default(), amount, nonce)
+    }
+    
+    /// Create a commitment with a specific nonce and hash algorithm
+    pub fn create_commitment_with(algo: HashAlgo, amount: u64, nonce: &[u8; 32]) -> Result<Commitment> {
+        let mut hasher = algo.hasher();
         hasher.update(amount.to_le_bytes());
         hasher.update(nonce);
         let commitment_hash = hex::encode(hasher.finalize());
@@ -39,18 +128,145 @@ impl CommitmentScheme {
             commitment_hash,
             nonce: hex::encode(nonce),
             amount: None, // Hide the amount
+            hash_algo: algo,
+        })
+    }
+    
+    /// Hash commitment to an amount of a given asset
+    ///
+    /// Native amounts commit exactly as `create_commitment`; other assets also hash
+    /// the asset id, so a note can't be relabelled as another asset.
+    pub fn create_asset_commitment(asset: &AssetId, amount: u64, nonce: &[u8; 32]) -> Result<Commitment> {
+        Self::create_asset_commitment_with(HashAlgo::default(), asset, amount, nonce)
+    }
+    
+    /// Like `create_asset_commitment`, hashing with the given algorithm
+    pub fn create_asset_commitment_with(algo: HashAlgo, asset: &AssetId, amount: u64, nonce: &[u8; 32]) -> Result<Commitment> {
+        if asset.is_native() {
+            return Self::create_commitment_with(algo, amount, nonce);
+        }
+        let mut hasher = algo.hasher();
+        hasher.update(b"asset_commitment");
+        hasher.update((asset.0.len() as u64).to_le_bytes());
+        hasher.update(asset.0.as_bytes());
+        hasher.update(amount.to_le_bytes());
+        hasher.update(nonce);
+        
+        Ok(Commitment {
+            commitment_hash: hex::encode(hasher.finalize()),
+            nonce: hex::encode(nonce),
+            amount: None,
+            hash_algo: algo,
         })
     }
     
+    /// Create a Pedersen commitment `amount * G + blinding * H` as a compressed point
+    ///
+    /// Unlike the hash commitment, Pedersen commitments add up: the sum of two
+    /// commitments commits to the sum of their amounts and blindings.
+    pub fn commit_pedersen(amount: u64, blinding: &Scalar) -> String {
+        let point = Scalar::from(amount) * RISTRETTO_BASEPOINT_POINT + blinding * *PEDERSEN_H;
+        Self::encode_pedersen(&point)
+    }
+    
+    /// Pedersen commitment to an amount of a given asset, on that asset's value generator
+    ///
+    /// The native asset gives the same commitment as `commit_pedersen`.
+    pub fn commit_pedersen_asset(asset: &AssetId, amount: u64, blinding: &Scalar) -> String {
+        let point = Scalar::from(amount) * asset.value_generator() + blinding * *PEDERSEN_H;
+        Self::encode_pedersen(&point)
+    }
+    
+    /// Encode a Pedersen commitment point as a hex compressed point
+    pub fn encode_pedersen(point: &RistrettoPoint) -> String {
+        hex::encode(point.compress().as_bytes())
+    }
+    
+    /// Decode a compressed Pedersen commitment
+    pub fn decode_pedersen(commitment: &str) -> Result<RistrettoPoint> {
+        hex::decode(commitment)
+            .ok()
+            .and_then(|bytes| CompressedRistretto::from_slice(&bytes).ok())
+            .and_then(|compressed| compressed.decompress())
+            .ok_or_else(|| crate::error::ShieldedError::CommitmentError(
+                format!("Invalid Pedersen commitment: {}", commitment)
+            ))
+    }
+    
+    /// Add two Pedersen commitments, committing to the sum of their amounts and blindings
+    pub fn add(c1: &str, c2: &str) -> Result<String> {
+        let sum = Self::decode_pedersen(c1)? + Self::decode_pedersen(c2)?;
+        Ok(Self::encode_pedersen(&sum))
+    }
+    
+    /// Subtract one Pedersen commitment from another, committing to the differences
+    pub fn sub(c1: &str, c2: &str) -> Result<String> {
+        let difference = Self::decode_pedersen(c1)? - Self::decode_pedersen(c2)?;
+        Ok(Self::encode_pedersen(&difference))
+    }
+    
+    /// Prove that `commit_pedersen(amount, blinding)` hides a value in `[0, 2^bits)`
+    ///
+    /// `bits` must be 8, 16, 32 or 64. An amount outside the range still yields a
+    /// proof, but one that fails `verify_range`.
+    #[cfg(feature = "bulletproofs")]
+    pub fn prove_range(amount: u64, blinding: &Scalar, bits: usize) -> Result<String> {
+        let mut transcript = Transcript::new(RANGE_PROOF_LABEL);
+        let (proof, _) = RangeProof::prove_single(
+            &BULLETPROOF_GENS,
+            &Self::pedersen_gens(),
+            &mut transcript,
+            amount,
+            blinding,
+            bits,
+        )
+        .map_err(|e| crate::error::ShieldedError::ZKProofError(format!("Range proof failed: {}", e)))?;
+        
+        Ok(hex::encode(proof.to_bytes()))
+    }
+    
+    /// Verify a bulletproof that a Pedersen commitment hides a value in `[0, 2^bits)`
+    ///
+    /// Returns `false` for a proof that doesn't hold; errors on malformed input or
+    /// an unsupported bit size.
+    #[cfg(feature = "bulletproofs")]
+    pub fn verify_range(proof: &str, commitment: &str, bits: usize) -> Result<bool> {
+        let proof = hex::decode(proof)
+            .ok()
+            .and_then(|bytes| RangeProof::from_bytes(&bytes).ok())
+            .ok_or_else(|| crate::error::ShieldedError::ZKProofError("Malformed range proof".to_string()))?;
+        let commitment = Self::decode_pedersen(commitment)?.compress();
+        
+        let mut transcript = Transcript::new(RANGE_PROOF_LABEL);
+        match proof.verify_single(&BULLETPROOF_GENS, &Self::pedersen_gens(), &mut transcript, &commitment, bits) {
+            Ok(()) => Ok(true),
+            Err(ProofError::VerificationError) => Ok(false),
+            Err(e) => Err(crate::error::ShieldedError::ZKProofError(format!("Range proof failed: {}", e))),
+        }
+    }
+    
+    /// The generators of `commit_pedersen`, as bulletproofs expects them
+    #[cfg(feature = "bulletproofs")]
+    fn pedersen_gens() -> PedersenGens {
+        PedersenGens {
+            B: RISTRETTO_BASEPOINT_POINT,
+            B_blinding: *PEDERSEN_H,
+        }
+    }
+    
+    /// Draw a random blinding factor for a Pedersen commitment
+    pub fn random_blinding() -> Scalar {
+        Scalar::from_bytes_mod_order(generate_nonce())
+    }
+    
     /// Prove knowledge of the amount without revealing it
     pub fn prove_knowledge(amount: u64) -> Result<String> {
         let nonce = generate_nonce();
-        let commitment = Self::create_commitment(amount, &nonce)?;
         
         // Create a proof that demonstrates knowledge of the amount
         let mut hasher = Sha256::new();
         hasher.update(amount.to_le_bytes());
-        hasher.update(&nonce);
+        hasher.update(nonce);
         hasher.update(b"knowledge_proof");
         let proof_hash = hex::encode(hasher.finalize());
         
@@ -58,19 +274,68 @@ impl CommitmentScheme {
     }
     
     /// Verify that a proof demonstrates knowledge of the committed amount
+    ///
+    /// Only checks that both are 64 characters; `verify_knowledge_proof` checks
+    /// a `KnowledgeProof` against its commitment.
     pub fn verify_knowledge(commitment_hash: &str, proof: &str) -> Result<bool> {
         // In a real implementation, this would verify the zero-knowledge proof
         // For this demo, we'll simulate verification
         Ok(commitment_hash.len() == 64 && proof.len() == 64)
     }
     
-    /// Open a commitment to reveal the amount
-    pub fn open_commitment(commitment: &Commitment, amount: u64, nonce: &str) -> Result<bool> {
-        let nonce_bytes = hex::decode(nonce)
-            .map_err(|_| crate::error::ShieldedError::CryptoError("Invalid nonce".to_string()))?;
+    /// Prove knowledge of a commitment's amount and nonce
+    ///
+    /// Errors if they don't open the commitment.
+    pub fn prove_knowledge_of(commitment: &Commitment, amount: u64, nonce: &str) -> Result<KnowledgeProof> {
+        if !Self::open_commitment(commitment, amount, nonce)? {
+            return Err(crate::error::ShieldedError::CommitmentError(
+                "Amount and nonce do not open the commitment".to_string()
+            ));
+        }
         
-        let expected_commitment = Self::create_commitment(amount, &nonce_bytes.try_into().unwrap())?;
-        Ok(commitment.commitment_hash == expected_commitment.commitment_hash)
+        Ok(KnowledgeProof {
+            proof_hash: Self::knowledge_proof_hash(amount, nonce)?,
+            commitment_hash: commitment.commitment_hash.clone(),
+            amount,
+            nonce: nonce.to_string(),
+        })
+    }
+    
+    /// Check a knowledge proof against a commitment
+    ///
+    /// Holds only if the proof names this commitment, its amount and nonce open
+    /// it, and `proof_hash` is `SHA256(amount || nonce || "knowledge_proof")`.
+    /// Errors on a malformed nonce.
+    pub fn verify_knowledge_proof(commitment: &Commitment, proof: &KnowledgeProof) -> Result<bool> {
+        Ok(ct_eq(&proof.commitment_hash, &commitment.commitment_hash)
+            && Self::open_commitment(commitment, proof.amount, &proof.nonce)?
+            && ct_eq(&proof.proof_hash, &Self::knowledge_proof_hash(proof.amount, &proof.nonce)?))
+    }
+    
+    fn knowledge_proof_hash(amount: u64, nonce: &str) -> Result<String> {
+        let nonce_bytes = Self::decode_nonce(nonce)?;
+        let mut hasher = Sha256::new();
+        hasher.update(amount.to_le_bytes());
+        hasher.update(nonce_bytes);
+        hasher.update(b"knowledge_proof");
+        Ok(hex::encode(hasher.finalize()))
+    }
+    
+    /// Open a commitment to reveal the amount, rehashing with its `hash_algo`
+    ///
+    /// A nonce that isn't 32 hex-encoded bytes is a `CryptoError`, not a failed opening.
+    pub fn open_commitment(commitment: &Commitment, amount: u64, nonce: &str) -> Result<bool> {
+        let nonce_bytes = Self::decode_nonce(nonce)?;
+        let expected_commitment = Self::create_commitment_with(commitment.hash_algo, amount, &nonce_bytes)?;
+        Ok(ct_eq(&commitment.commitment_hash, &expected_commitment.commitment_hash))
+    }
+    
+    fn decode_nonce(nonce: &str) -> Result<[u8; 32]> {
+        let bytes = hex::decode(nonce)
+            .map_err(|e| crate::error::ShieldedError::CryptoError(format!("Nonce is not valid hex: {}", e)))?;
+        <[u8; 32]>::try_from(bytes).map_err(|bytes| crate::error::ShieldedError::CryptoError(
+            format!("Nonce must be 32 bytes, got {}", bytes.len())
+        ))
     }
     
     /// Create a range proof (simplified version)
@@ -91,9 +356,165 @@ impl CommitmentScheme {
     }
     
     /// Verify a range proof
-    pub fn verify_range_proof(proof: &str, commitment_hash: &str, min: u64, max: u64) -> Result<bool> {
+    pub fn verify_range_proof(proof: &str, commitment_hash: &str, _min: u64, _max: u64) -> Result<bool> {
         // In a real implementation, this would verify the range proof
         // For this demo, we'll simulate verification
         Ok(proof.len() == 64 && commitment_hash.len() == 64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Published commitment test vectors as `(amount, nonce, commitment_hash)`.
+    ///
+    /// A SHA-256 commitment is `SHA256(amount.to_le_bytes() || nonce)` with no domain tag,
+    /// where the nonce is exactly 32 bytes. Other implementations can use these
+    /// constants to check they follow the same convention.
+    const COMMITMENT_TEST_VECTORS: &[(u64, &str, &str)] = &[
+        (
+            0,
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "2c34ce1df23b838c5abf2a7f6437cca3d3067ed509ff25f11df6b11b582b51eb",
+        ),
+        (
+            1000,
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "7aaa5487201e5cd1ac0b794519e4a8b00b1f0acd2668836a6c790a09c1e9365f",
+        ),
+        (
+            1_000_000,
+            "abababababababababababababababababababababababababababababababab",
+            "e7e9dd5c54a5db9a8eec9a2940a5fbe8e3c8218cb3b406d1433051d1fd5af650",
+        ),
+        (
+            u64::MAX,
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "6ecd0f0bd7cf53c56d2129820911a26f815949eee418ca46b4f3d7a80cd969a7",
+        ),
+    ];
+    
+    #[test]
+    fn create_commitment_reproduces_published_vectors() {
+        for (amount, nonce, expected) in COMMITMENT_TEST_VECTORS {
+            let nonce: [u8; 32] = hex::decode(nonce).unwrap().try_into().unwrap();
+            let commitment = CommitmentScheme::create_commitment(*amount, &nonce).unwrap();
+            assert_eq!(commitment.commitment_hash, *expected, "amount {}", amount);
+        }
+    }
+    
+    #[test]
+    fn asset_commitment_hashes_with_the_given_algorithm() {
+        let asset = AssetId("ATOM".to_string());
+        let nonce = [7u8; 32];
+        let sha256 = CommitmentScheme::create_asset_commitment(&asset, 1_000, &nonce).unwrap();
+        let blake2b = CommitmentScheme::create_asset_commitment_with(HashAlgo::Blake2b, &asset, 1_000, &nonce).unwrap();
+        
+        assert_eq!(sha256.hash_algo, HashAlgo::Sha256);
+        assert_eq!(blake2b.hash_algo, HashAlgo::Blake2b);
+        assert_ne!(sha256.commitment_hash, blake2b.commitment_hash);
+        
+        let native = CommitmentScheme::create_asset_commitment_with(HashAlgo::Blake2b, &AssetId::native(), 1_000, &nonce).unwrap();
+        assert_eq!(native.commitment_hash, CommitmentScheme::create_commitment_with(HashAlgo::Blake2b, 1_000, &nonce).unwrap().commitment_hash);
+    }
+    
+    #[test]
+    fn added_commitments_open_to_the_summed_value() {
+        let (first, second) = (Scalar::from(11u64), Scalar::from(31u64));
+        let sum = CommitmentScheme::add(
+            &CommitmentScheme::commit_pedersen(400, &first),
+            &CommitmentScheme::commit_pedersen(600, &second),
+        ).unwrap();
+        
+        assert_eq!(sum, CommitmentScheme::commit_pedersen(1_000, &(first + second)));
+        assert_ne!(sum, CommitmentScheme::commit_pedersen(999, &(first + second)));
+        let difference = CommitmentScheme::sub(&sum, &CommitmentScheme::commit_pedersen(600, &second)).unwrap();
+        assert_eq!(difference, CommitmentScheme::commit_pedersen(400, &first));
+    }
+    
+    #[test]
+    fn commitment_opens_with_its_nonce_and_not_another() {
+        let commitment = CommitmentScheme::commit(1_000).unwrap();
+        assert_eq!(commitment.amount, Some(1_000));
+        assert!(CommitmentScheme::open_commitment(&commitment, 1_000, &commitment.nonce).unwrap());
+        assert!(!CommitmentScheme::open_commitment(&commitment, 999, &commitment.nonce).unwrap());
+        
+        let wrong_nonce = hex::encode([9u8; 32]);
+        assert!(!CommitmentScheme::open_commitment(&commitment, 1_000, &wrong_nonce).unwrap());
+        assert!(CommitmentScheme::open_commitment(&commitment, 1_000, "not hex").is_err());
+    }
+    
+    #[cfg(feature = "bulletproofs")]
+    #[test]
+    fn range_proof_fails_for_an_out_of_range_value() {
+        let blinding = CommitmentScheme::random_blinding();
+        let in_range = CommitmentScheme::commit_pedersen(200, &blinding);
+        let proof = CommitmentScheme::prove_range(200, &blinding, 8).unwrap();
+        assert!(CommitmentScheme::verify_range(&proof, &in_range, 8).unwrap());
+        assert!(!CommitmentScheme::verify_range(&proof, &CommitmentScheme::commit_pedersen(201, &blinding), 8).unwrap());
+        
+        let out_of_range = CommitmentScheme::commit_pedersen(256, &blinding);
+        let proof = CommitmentScheme::prove_range(256, &blinding, 8).unwrap();
+        assert!(!CommitmentScheme::verify_range(&proof, &out_of_range, 8).unwrap());
+    }
+    
+    #[test]
+    fn blake2b_commitment_opens_only_under_blake2b() {
+        let nonce = [3u8; 32];
+        let blake2b = CommitmentScheme::create_commitment_with(HashAlgo::Blake2b, 1_000, &nonce).unwrap();
+        let sha256 = CommitmentScheme::create_commitment(1_000, &nonce).unwrap();
+        assert_ne!(blake2b.commitment_hash, sha256.commitment_hash);
+        assert!(CommitmentScheme::open_commitment(&blake2b, 1_000, &blake2b.nonce).unwrap());
+        
+        // Opening rehashes with the recorded algorithm, so relabelling it breaks the opening
+        let relabelled = Commitment { hash_algo: HashAlgo::Sha256, ..blake2b };
+        assert!(!CommitmentScheme::open_commitment(&relabelled, 1_000, &relabelled.nonce).unwrap());
+    }
+    
+    #[test]
+    fn knowledge_proof_holds_only_for_its_amount_and_nonce() {
+        let commitment = CommitmentScheme::commit(1_000).unwrap();
+        let proof = CommitmentScheme::prove_knowledge_of(&commitment, 1_000, &commitment.nonce).unwrap();
+        assert!(CommitmentScheme::verify_knowledge_proof(&commitment, &proof).unwrap());
+        
+        let wrong_amount = KnowledgeProof { amount: 999, ..proof.clone() };
+        assert!(!CommitmentScheme::verify_knowledge_proof(&commitment, &wrong_amount).unwrap());
+        let wrong_nonce = KnowledgeProof { nonce: hex::encode([9u8; 32]), ..proof.clone() };
+        assert!(!CommitmentScheme::verify_knowledge_proof(&commitment, &wrong_nonce).unwrap());
+        // A proof hash of the right length is not enough
+        let garbage = KnowledgeProof { proof_hash: "0".repeat(64), ..proof.clone() };
+        assert!(!CommitmentScheme::verify_knowledge_proof(&commitment, &garbage).unwrap());
+        
+        assert!(CommitmentScheme::prove_knowledge_of(&commitment, 999, &commitment.nonce).is_err());
+        let other = CommitmentScheme::commit(1_000).unwrap();
+        assert!(!CommitmentScheme::verify_knowledge_proof(&other, &proof).unwrap());
+    }
+    
+    #[test]
+    fn nonce_of_the_wrong_length_is_an_error_not_a_panic() {
+        let commitment = CommitmentScheme::commit(1_000).unwrap();
+        for nonce in [hex::encode([1u8; 16]), hex::encode([1u8; 64])] {
+            match CommitmentScheme::open_commitment(&commitment, 1_000, &nonce) {
+                Err(crate::error::ShieldedError::CryptoError(message)) => assert!(message.contains("32 bytes"), "{}", message),
+                other => panic!("expected a CryptoError, got {:?}", other),
+            }
+        }
+    }
+    
+    #[test]
+    fn batch_of_a_hundred_gives_distinct_openable_commitments() {
+        // Equal amounts too, so distinctness has to come from the nonces
+        let amounts: Vec<u64> = (0..100).map(|i| i % 10).collect();
+        let commitments = CommitmentScheme::commit_batch(&amounts).unwrap();
+        assert_eq!(commitments.len(), 100);
+        
+        let hashes: std::collections::HashSet<&str> = commitments.iter().map(|commitment| commitment.commitment_hash.as_str()).collect();
+        assert_eq!(hashes.len(), 100);
+        for (commitment, &amount) in commitments.iter().zip(&amounts) {
+            assert_eq!(commitment.amount, Some(amount));
+            assert!(CommitmentScheme::open_commitment(commitment, amount, &commitment.nonce).unwrap());
+        }
+        assert!(CommitmentScheme::commit_batch(&[]).unwrap().is_empty());
+    }
+}
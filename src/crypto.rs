@@ -1,14 +1,205 @@
 use crate::error::Result;
-use rand::Rng;
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, ristretto::CompressedRistretto, scalar::Scalar};
+use ed25519_dalek::{Signer, Verifier};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use hex;
+use std::cell::RefCell;
+use std::str::FromStr;
+use subtle::ConstantTimeEq;
+#[cfg(feature = "time")]
+use uuid::Uuid;
 
-pub fn generate_keypair() -> Result<(String, String)> {
-    let mut rng = rand::thread_rng();
-    let private_key: [u8; 32] = rng.gen();
-    let public_key = derive_public_key(&private_key)?;
+/// Signature scheme of a key pair, and of the signatures it produces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[cfg_attr(feature = "borsh", borsh(use_discriminant = true))]
+pub enum KeyScheme {
+    /// Demo scheme where the signature is `SHA256(message || public key)`; forgeable,
+    /// kept only to verify old wallets and transactions
+    Legacy = 0,
+    #[default]
+    Ed25519 = 1,
+    /// ECDSA over secp256k1 with SHA-256, compressed SEC1 public keys
+    Secp256k1 = 2,
+}
+
+impl FromStr for KeyScheme {
+    type Err = crate::error::ShieldedError;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "ed25519" => Ok(KeyScheme::Ed25519),
+            "secp256k1" => Ok(KeyScheme::Secp256k1),
+            "legacy" => Err(crate::error::ShieldedError::CryptoError(
+                "The legacy scheme is forgeable and can't make new keys; use ed25519 or secp256k1".to_string()
+            )),
+            other => Err(crate::error::ShieldedError::CryptoError(
+                format!("Unknown key scheme '{}', expected ed25519 or secp256k1", other)
+            )),
+        }
+    }
+}
+
+impl KeyScheme {
+    /// Scheme of keys and signatures stored before the scheme was recorded
+    pub fn legacy() -> Self {
+        KeyScheme::Legacy
+    }
+    
+    /// Refuse the legacy scheme for new keys and signatures, which anyone could forge
+    pub(crate) fn ensure_secure(self) -> Result<()> {
+        match self {
+            KeyScheme::Legacy => Err(crate::error::ShieldedError::CryptoError(
+                "The legacy scheme is forgeable; only old signatures can be checked with it".to_string()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyScheme::Legacy => write!(f, "legacy"),
+            KeyScheme::Ed25519 => write!(f, "ed25519"),
+            KeyScheme::Secp256k1 => write!(f, "secp256k1"),
+        }
+    }
+}
+
+thread_local! {
+    /// RNG installed by `with_seed`, used in place of `thread_rng` while set
+    static SEEDED_RNG: RefCell<Option<ChaCha20Rng>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with keys, nonces and ids on this thread drawn from a ChaCha20 RNG
+/// seeded with `seed`, so the wallets and transactions it creates are reproducible
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<ChaCha20Rng>);
     
-    Ok((hex::encode(public_key), hex::encode(private_key)))
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SEEDED_RNG.with(|rng| *rng.borrow_mut() = previous);
+        }
+    }
+    
+    let _restore = Restore(SEEDED_RNG.with(|rng| rng.replace(Some(ChaCha20Rng::seed_from_u64(seed)))));
+    f()
+}
+
+/// Fill `bytes` from the seeded RNG if one is installed, otherwise from `thread_rng`
+fn fill_random(bytes: &mut [u8]) {
+    SEEDED_RNG.with(|seeded| match seeded.borrow_mut().as_mut() {
+        Some(rng) => rng.fill_bytes(bytes),
+        None => rand::thread_rng().fill_bytes(bytes),
+    })
+}
+
+/// Generate a `(public, private)` hex Ed25519 keypair
+pub fn generate_keypair() -> Result<(String, String)> {
+    generate_keypair_for(KeyScheme::Ed25519)
+}
+
+/// Generate a `(public, private)` hex keypair for a signature scheme
+///
+/// The legacy scheme is refused.
+pub fn generate_keypair_for(scheme: KeyScheme) -> Result<(String, String)> {
+    scheme.ensure_secure()?;
+    loop {
+        let mut private_key = [0u8; 32];
+        fill_random(&mut private_key);
+        // Only secp256k1 rejects some keys (zero or not below the group order), and rarely
+        if let Ok(public_key) = derive_public_key_for(scheme, &private_key) {
+            return Ok((public_key, hex::encode(private_key)));
+        }
+    }
+}
+
+/// Derive the hex public key for a 32-byte private key under a signature scheme
+pub fn derive_public_key_for(scheme: KeyScheme, private_key: &[u8; 32]) -> Result<String> {
+    match scheme {
+        KeyScheme::Legacy => Ok(hex::encode(derive_public_key(private_key)?)),
+        KeyScheme::Ed25519 => {
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(private_key);
+            Ok(hex::encode(signing_key.verifying_key().to_bytes()))
+        }
+        KeyScheme::Secp256k1 => {
+            let signing_key = secp256k1_signing_key(private_key)?;
+            Ok(hex::encode(signing_key.verifying_key().to_sec1_bytes()))
+        }
+    }
+}
+
+/// Sign a message under a signature scheme, returning the hex signature
+///
+/// The legacy scheme is refused.
+pub fn sign_with(scheme: KeyScheme, message: &[u8], private_key: &str) -> Result<String> {
+    match scheme {
+        KeyScheme::Legacy => Err(crate::error::ShieldedError::CryptoError(
+            "The legacy scheme is forgeable and no longer signs".to_string()
+        )),
+        KeyScheme::Ed25519 => {
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(&decode_private_key(private_key)?);
+            Ok(hex::encode(signing_key.sign(message).to_bytes()))
+        }
+        KeyScheme::Secp256k1 => {
+            let signing_key = secp256k1_signing_key(&decode_private_key(private_key)?)?;
+            let signature: k256::ecdsa::Signature = signing_key.sign(message);
+            Ok(hex::encode(signature.to_bytes()))
+        }
+    }
+}
+
+/// Verify a hex signature under a signature scheme
+///
+/// Malformed keys or signatures, including ones from a different scheme, verify as false.
+/// So does every legacy signature, since anyone can forge one; old data that needs
+/// them goes through `verify_legacy_signature` explicitly.
+pub fn verify_signature_with(scheme: KeyScheme, message: &[u8], signature: &str, public_key: &str) -> Result<bool> {
+    match scheme {
+        KeyScheme::Legacy => Ok(false),
+        KeyScheme::Ed25519 => {
+            let verifying_key = hex::decode(public_key)
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .and_then(|bytes| ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok());
+            let signature = hex::decode(signature)
+                .ok()
+                .and_then(|bytes| ed25519_dalek::Signature::from_slice(&bytes).ok());
+            Ok(match (verifying_key, signature) {
+                (Some(key), Some(signature)) => key.verify(message, &signature).is_ok(),
+                _ => false,
+            })
+        }
+        KeyScheme::Secp256k1 => {
+            let verifying_key = hex::decode(public_key)
+                .ok()
+                .and_then(|bytes| k256::ecdsa::VerifyingKey::from_sec1_bytes(&bytes).ok());
+            let signature = hex::decode(signature)
+                .ok()
+                .and_then(|bytes| k256::ecdsa::Signature::from_slice(&bytes).ok());
+            Ok(match (verifying_key, signature) {
+                (Some(key), Some(signature)) => key.verify(message, &signature).is_ok(),
+                _ => false,
+            })
+        }
+    }
+}
+
+fn decode_private_key(private_key: &str) -> Result<[u8; 32]> {
+    hex::decode(private_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| crate::error::ShieldedError::CryptoError("Private key must be 32 bytes".to_string()))
+}
+
+fn secp256k1_signing_key(private_key: &[u8; 32]) -> Result<k256::ecdsa::SigningKey> {
+    k256::ecdsa::SigningKey::from_slice(private_key)
+        .map_err(|e| crate::error::ShieldedError::CryptoError(format!("Invalid secp256k1 key: {}", e)))
 }
 
 pub fn derive_public_key(private_key: &[u8; 32]) -> Result<[u8; 32]> {
@@ -22,6 +213,18 @@ pub fn derive_public_key(private_key: &[u8; 32]) -> Result<[u8; 32]> {
     Ok(public_key)
 }
 
+/// Compare two hex digests or signatures in constant time
+///
+/// Valid hex is compared as decoded bytes, so letter case doesn't matter;
+/// anything else is compared byte for byte. Only the lengths, which are public,
+/// affect the timing.
+pub fn ct_eq(a: &str, b: &str) -> bool {
+    match (hex::decode(a), hex::decode(b)) {
+        (Ok(a), Ok(b)) => a.ct_eq(&b).into(),
+        _ => a.as_bytes().ct_eq(b.as_bytes()).into(),
+    }
+}
+
 pub fn hash(data: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(data);
@@ -32,26 +235,333 @@ pub fn hash(data: &[u8]) -> [u8; 32] {
     hash
 }
 
+/// Hash function behind commitments and Merkle trees
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[cfg_attr(feature = "borsh", borsh(use_discriminant = true))]
+pub enum HashAlgo {
+    #[default]
+    Sha256 = 0,
+    /// BLAKE2b with a 256-bit output, as used in parts of the Namada stack
+    Blake2b = 1,
+}
+
+impl FromStr for HashAlgo {
+    type Err = crate::error::ShieldedError;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha256" => Ok(HashAlgo::Sha256),
+            "blake2b" => Ok(HashAlgo::Blake2b),
+            other => Err(crate::error::ShieldedError::CryptoError(
+                format!("Unknown hash algorithm '{}', expected sha256 or blake2b", other)
+            )),
+        }
+    }
+}
+
+impl HashAlgo {
+    /// Start an incremental hash under this algorithm
+    pub fn hasher(self) -> Hasher {
+        match self {
+            HashAlgo::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgo::Blake2b => Hasher::Blake2b(Blake2b256::new()),
+        }
+    }
+}
+
+type Blake2b256 = blake2::Blake2b<blake2::digest::consts::U32>;
+
+/// Incremental hash state for a `HashAlgo`; every algorithm gives 32 bytes
+#[derive(Clone)]
+pub enum Hasher {
+    Sha256(Sha256),
+    Blake2b(Blake2b256),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake2b(hasher) => hasher.update(data),
+        }
+    }
+    
+    pub fn finalize(self) -> [u8; 32] {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().into(),
+            Hasher::Blake2b(hasher) => hasher.finalize().into(),
+        }
+    }
+}
+
+/// Hash data under the given algorithm; `hash` is `hash_with(HashAlgo::Sha256, ..)`
+pub fn hash_with(algo: HashAlgo, data: &[u8]) -> [u8; 32] {
+    let mut hasher = algo.hasher();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Number of leading zero bits in a byte string
+pub fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Derive the nullifier revealed when the note behind `commitment` is spent
+///
+/// Only the holder of the spending key can compute it, and it is the same every
+/// time, so a second spend of one note shows up as a repeated nullifier.
+pub fn derive_nullifier(spending_key: &str, commitment: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"nullifier");
+    hasher.update(spending_key.as_bytes());
+    hasher.update(commitment.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Derive the nonce or blinding for one commitment opening of a transaction
+///
+/// `role` names the commitment kind (e.g. `"input"`) and `index` its position,
+/// so the sender can recompute every opening from the spending key alone.
+pub fn derive_opening(spending_key: &str, transaction_id: &str, role: &str, index: usize) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"opening");
+    for field in [spending_key, transaction_id, role] {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.update((index as u64).to_le_bytes());
+    hasher.finalize().into()
+}
+
 pub fn generate_random_bytes(length: usize) -> Vec<u8> {
-    let mut rng = rand::thread_rng();
-    (0..length).map(|_| rng.gen()).collect()
+    let mut bytes = vec![0u8; length];
+    fill_random(&mut bytes);
+    bytes
 }
 
 pub fn generate_nonce() -> [u8; 32] {
-    let mut rng = rand::thread_rng();
     let mut nonce = [0u8; 32];
-    rng.fill(&mut nonce);
+    fill_random(&mut nonce);
     nonce
 }
 
+/// Generate `count` random 32-byte nonces from a single draw, honouring `with_seed`
+pub fn generate_nonces(count: usize) -> Vec<[u8; 32]> {
+    let mut bytes = vec![0u8; count * 32];
+    fill_random(&mut bytes);
+    bytes
+        .chunks_exact(32)
+        .map(|chunk| chunk.try_into().expect("chunks are 32 bytes"))
+        .collect()
+}
+
+/// Generate a random (v4) UUID, honouring `with_seed`
+#[cfg(feature = "time")]
+pub fn generate_uuid() -> Uuid {
+    let mut bytes = [0u8; 16];
+    fill_random(&mut bytes);
+    uuid::Builder::from_random_bytes(bytes).into_uuid()
+}
+
+/// Verify a detached hex Ed25519 signature against a hex public key
+///
+/// A public key that isn't 32 hex-encoded bytes is a `CryptoError`, not a failed match.
 pub fn verify_signature(message: &[u8], signature: &str, public_key: &str) -> Result<bool> {
-    // In a real implementation, this would verify the signature properly
+    decode_public_key(public_key)?;
+    verify_signature_with(KeyScheme::Ed25519, message, signature, public_key)
+}
+
+/// Check a `KeyScheme::Legacy` signature, which anyone knowing the public key can forge
+///
+/// Only for data stored before a secure scheme was required.
+pub fn verify_legacy_signature(message: &[u8], signature: &str, public_key: &str) -> Result<bool> {
+    let public_key = decode_public_key(public_key)?;
     let expected_signature = {
         let mut hasher = Sha256::new();
         hasher.update(message);
-        hasher.update(hex::decode(public_key).unwrap_or_default());
+        hasher.update(public_key);
         hex::encode(hasher.finalize())
     };
     
-    Ok(signature == expected_signature)
+    Ok(ct_eq(signature, &expected_signature))
+}
+
+/// Decode a 32-byte hex public key, as used by the legacy and Ed25519 schemes
+fn decode_public_key(public_key: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(public_key)
+        .map_err(|e| crate::error::ShieldedError::CryptoError(format!("Public key is not valid hex: {}", e)))?;
+    <[u8; 32]>::try_from(bytes).map_err(|bytes| crate::error::ShieldedError::CryptoError(
+        format!("Public key must be 32 bytes, got {}", bytes.len())
+    ))
+}
+
+/// Derive the viewing keypair `(public, secret)` from a spending key
+///
+/// The viewing key can decrypt notes sent to the wallet but cannot spend them.
+pub fn derive_viewing_keypair(private_key: &[u8; 32]) -> (String, String) {
+    let mut hasher = Sha256::new();
+    hasher.update(b"viewing_key");
+    hasher.update(private_key);
+    let secret = Scalar::from_bytes_mod_order(hasher.finalize().into());
+    let public = (secret * RISTRETTO_BASEPOINT_POINT).compress();
+    
+    (hex::encode(public.as_bytes()), hex::encode(secret.as_bytes()))
+}
+
+/// Encrypt to a viewing public key, returning `(ephemeral_public_key, ciphertext)`
+///
+/// Uses a Diffie-Hellman exchange over Ristretto to derive a one-time key, a
+/// SHA-256 keystream and an appended 32-byte authentication tag.
+pub fn encrypt_to_viewing_key(viewing_public_key: &str, plaintext: &[u8]) -> Result<(String, String)> {
+    let recipient = decode_point(viewing_public_key)
+        .ok_or_else(|| crate::error::ShieldedError::CryptoError("Invalid viewing public key".to_string()))?;
+    
+    let ephemeral_secret = Scalar::from_bytes_mod_order(generate_nonce());
+    let ephemeral_public = (ephemeral_secret * RISTRETTO_BASEPOINT_POINT).compress();
+    let key = shared_key(&(ephemeral_secret * recipient).compress());
+    
+    let mut ciphertext = apply_keystream(&key, plaintext);
+    let tag = ciphertext_tag(&key, &ciphertext);
+    ciphertext.extend_from_slice(&tag);
+    
+    Ok((hex::encode(ephemeral_public.as_bytes()), hex::encode(ciphertext)))
+}
+
+/// Decrypt a ciphertext with a viewing key, or `None` if it wasn't addressed to it
+pub fn decrypt_with_viewing_key(viewing_key: &str, ephemeral_public_key: &str, ciphertext: &str) -> Option<Vec<u8>> {
+    let secret_bytes: [u8; 32] = hex::decode(viewing_key).ok()?.try_into().ok()?;
+    let secret = Option::<Scalar>::from(Scalar::from_canonical_bytes(secret_bytes))?;
+    let ephemeral = decode_point(ephemeral_public_key)?;
+    let key = shared_key(&(secret * ephemeral).compress());
+    
+    let data = hex::decode(ciphertext).ok()?;
+    if data.len() < 32 {
+        return None;
+    }
+    let (body, tag) = data.split_at(data.len() - 32);
+    if !bool::from(ciphertext_tag(&key, body)[..].ct_eq(tag)) {
+        return None;
+    }
+    
+    Some(apply_keystream(&key, body))
+}
+
+fn decode_point(hex_point: &str) -> Option<curve25519_dalek::ristretto::RistrettoPoint> {
+    let bytes = hex::decode(hex_point).ok()?;
+    CompressedRistretto::from_slice(&bytes).ok()?.decompress()
+}
+
+fn shared_key(shared_point: &CompressedRistretto) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"note_key");
+    hasher.update(shared_point.as_bytes());
+    hasher.finalize().into()
+}
+
+fn apply_keystream(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    data.chunks(32)
+        .enumerate()
+        .flat_map(|(counter, chunk)| {
+            let mut hasher = Sha256::new();
+            hasher.update(key);
+            hasher.update((counter as u64).to_le_bytes());
+            let block = hasher.finalize();
+            chunk.iter().zip(block).map(|(byte, pad)| byte ^ pad).collect::<Vec<u8>>()
+        })
+        .collect()
+}
+
+fn ciphertext_tag(key: &[u8; 32], ciphertext: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"note_tag");
+    hasher.update(key);
+    hasher.update(ciphertext);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn malformed_public_keys_are_errors_not_mismatches() {
+        let (public_key, private_key) = generate_keypair().unwrap();
+        let signature = sign_with(KeyScheme::Ed25519, b"message", &private_key).unwrap();
+        assert!(verify_signature(b"message", &signature, &public_key).unwrap());
+        
+        let odd_length = &public_key[1..];
+        let wrong_length = &public_key[..62];
+        for public_key in ["", odd_length, wrong_length, "zz"] {
+            assert!(
+                matches!(verify_signature(b"message", &signature, public_key), Err(crate::error::ShieldedError::CryptoError(_))),
+                "{:?}",
+                public_key
+            );
+        }
+    }
+    
+    #[test]
+    fn each_hash_algo_gives_its_own_stable_digest() {
+        let sha256 = hash_with(HashAlgo::Sha256, b"abc");
+        let blake2b = hash_with(HashAlgo::Blake2b, b"abc");
+        assert_eq!(hex::encode(sha256), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(hex::encode(blake2b), "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319");
+        assert_eq!(sha256, hash(b"abc"));
+        assert_eq!(HashAlgo::default(), HashAlgo::Sha256);
+    }
+    
+    #[test]
+    fn ct_eq_compares_same_and_different_lengths() {
+        assert!(ct_eq("", ""));
+        assert!(ct_eq("00ff", "00ff"));
+        assert!(ct_eq("00ff", "00FF"));
+        assert!(!ct_eq("00ff", "00fe"));
+        assert!(!ct_eq("00ff", "00ff00"));
+        assert!(!ct_eq("00ff", ""));
+        
+        // Non-hex input is compared as raw bytes
+        assert!(ct_eq("not hex", "not hex"));
+        assert!(!ct_eq("not hex", "not hey"));
+        assert!(!ct_eq("not hex", "not hex!"));
+    }
+    
+    #[test]
+    fn tampered_tag_or_body_does_not_decrypt() {
+        let (public_key, secret) = derive_viewing_keypair(&[7u8; 32]);
+        let (ephemeral, ciphertext) = encrypt_to_viewing_key(&public_key, b"memo").unwrap();
+        assert_eq!(decrypt_with_viewing_key(&secret, &ephemeral, &ciphertext).unwrap(), b"memo");
+        
+        let mut bytes = hex::decode(&ciphertext).unwrap();
+        for index in [0, bytes.len() - 1] {
+            bytes[index] ^= 1;
+            assert!(decrypt_with_viewing_key(&secret, &ephemeral, &hex::encode(&bytes)).is_none());
+            bytes[index] ^= 1;
+        }
+        let (_, other) = derive_viewing_keypair(&[8u8; 32]);
+        assert!(decrypt_with_viewing_key(&other, &ephemeral, &ciphertext).is_none());
+    }
+    
+    #[test]
+    fn legacy_scheme_neither_signs_nor_verifies_without_opting_in() {
+        let (public_key, private_key) = generate_keypair().unwrap();
+        assert!(sign_with(KeyScheme::Legacy, b"message", &private_key).is_err());
+        assert!(generate_keypair_for(KeyScheme::Legacy).is_err());
+        assert!("legacy".parse::<KeyScheme>().is_err());
+        
+        // Anyone can compute this for any public key
+        let mut hasher = Sha256::new();
+        hasher.update(b"message");
+        hasher.update(hex::decode(&public_key).unwrap());
+        let forged = hex::encode(hasher.finalize());
+        assert!(!verify_signature_with(KeyScheme::Legacy, b"message", &forged, &public_key).unwrap());
+        assert!(verify_legacy_signature(b"message", &forged, &public_key).unwrap());
+    }
 }
@@ -40,6 +40,12 @@ pub enum ShieldedError {
 
     #[error("Storage error: {0}")]
     StorageError(String),
+
+    #[error("Sequence gap for {sender}: expected {expected}, got {found}")]
+    SequenceGap { sender: String, expected: u64, found: u64 },
+
+    #[error("Verification failed for {failed} of {checked} transactions")]
+    VerificationFailed { failed: usize, checked: usize },
 }
 
 pub type Result<T> = std::result::Result<T, ShieldedError>;
@@ -0,0 +1,120 @@
+use crate::{error::Result, storage::StorageData};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Read-only routes over the state stored in `data_dir`; nothing here can modify storage
+pub fn router(data_dir: PathBuf) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/stats", get(stats))
+        .route("/transactions/:id", get(transaction))
+        .route("/merkle/root", get(merkle_root))
+        .with_state(Arc::new(data_dir))
+}
+
+/// Serve the read-only endpoints until the process is stopped
+pub async fn serve(addr: &str, data_dir: PathBuf) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router(data_dir)).await?;
+    Ok(())
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn stats(State(data_dir): State<Arc<PathBuf>>) -> Response {
+    match load(data_dir).await {
+        Ok(storage) => Json(storage.stats()).into_response(),
+        Err(response) => response,
+    }
+}
+
+async fn transaction(State(data_dir): State<Arc<PathBuf>>, Path(id): Path<String>) -> Response {
+    let storage = match load(data_dir).await {
+        Ok(storage) => storage,
+        Err(response) => return response,
+    };
+    
+    match storage.get_transaction(&id) {
+        Some(transaction) => Json(transaction).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, &format!("Transaction not found: {}", id)),
+    }
+}
+
+async fn merkle_root(State(data_dir): State<Arc<PathBuf>>) -> Response {
+    match load(data_dir).await {
+        Ok(storage) => {
+            let tree = storage.merkle_tree();
+            Json(json!({
+                "root": tree.root(),
+                "height": tree.height(),
+                "leaf_count": tree.leaf_count(),
+            }))
+            .into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Load a fresh view of storage for each request so new transactions show up
+async fn load(data_dir: Arc<PathBuf>) -> std::result::Result<StorageData, Response> {
+    tokio::task::spawn_blocking(move || StorageData::load_from(&data_dir))
+        .await
+        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?
+        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shielded_transaction::ShieldedTransaction;
+    use crate::wallet::fixtures::{address, test_wallet};
+    use std::future::IntoFuture;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    /// Send a bare HTTP/1.1 GET and parse the JSON body of the response
+    async fn get(addr: std::net::SocketAddr, path: &str) -> serde_json::Value {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+        serde_json::from_str(body).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn health_and_stats_reflect_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let (from, to) = (test_wallet('a'), address('b'));
+        storage.add_transaction(ShieldedTransaction::create_public(&from, &to, 1_000).unwrap()).unwrap();
+        storage.add_transaction(ShieldedTransaction::create_shielded(&from, &to, 2_000).unwrap()).unwrap();
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router(dir.path().to_path_buf())).into_future());
+        
+        assert_eq!(get(addr, "/health").await, json!({ "status": "ok" }));
+        
+        let stats = get(addr, "/stats").await;
+        assert_eq!(stats, serde_json::to_value(storage.stats()).unwrap());
+        assert_eq!(stats["total_transactions"], 2);
+        assert_eq!(stats["shielded_transactions"], 1);
+    }
+}
@@ -1,16 +1,78 @@
 pub mod error;
+#[cfg(feature = "time")]
 pub mod wallet;
+#[cfg(feature = "storage")]
+pub mod wallet_store;
+#[cfg(feature = "time")]
 pub mod shielded_transaction;
+#[cfg(feature = "time")]
+pub mod multisig;
 pub mod commitment;
+#[cfg(feature = "time")]
 pub mod zk_proof;
+#[cfg(feature = "time")]
+pub mod proof_verifier;
+#[cfg(feature = "time")]
+pub mod proof_system;
 pub mod merkle_tree;
 pub mod crypto;
+#[cfg(feature = "storage")]
 pub mod storage;
+pub mod policy;
+pub mod note;
+pub mod amount;
+pub mod asset;
+#[cfg(feature = "time")]
+pub mod audit;
+#[cfg(feature = "storage")]
+pub mod stress;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_storage;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "borsh")]
+mod borsh_time;
 
 pub use error::ShieldedError;
+#[cfg(feature = "time")]
 pub use wallet::Wallet;
+#[cfg(feature = "storage")]
+pub use wallet_store::WalletStore;
+#[cfg(feature = "time")]
 pub use shielded_transaction::ShieldedTransaction;
 pub use commitment::CommitmentScheme;
+#[cfg(feature = "time")]
 pub use zk_proof::ZeroKnowledgeProof;
+#[cfg(feature = "time")]
+pub use proof_system::{MockProofSystem, ProofSystem};
 pub use merkle_tree::MerkleTree;
+#[cfg(feature = "storage")]
 pub use storage::StorageData;
+pub use policy::TransactionPolicy;
+pub use note::Note;
+pub use amount::Amount;
+pub use asset::AssetId;
+
+/// Runs only in a core-only build (`--no-default-features`), confirming the
+/// primitives work without the time, storage and CLI dependencies
+#[cfg(all(test, not(feature = "time")))]
+mod core_only_tests {
+    use super::*;
+    
+    #[test]
+    fn primitives_work_without_optional_features() {
+        let commitment = CommitmentScheme::commit(1_000).unwrap();
+        assert!(CommitmentScheme::open_commitment(&commitment, 1_000, &commitment.nonce).unwrap());
+        
+        let note = Note::new(1_000, "namada_owner").unwrap();
+        assert!(note.is_consistent());
+        
+        let leaves = vec![note.commitment.clone(), commitment.commitment_hash.clone()];
+        let tree = MerkleTree::from_leaves(&leaves).unwrap();
+        let proof = tree.generate_proof(0).unwrap();
+        assert!(tree.verify_proof(&leaves[0], &proof, 0).unwrap());
+        
+        assert_eq!("1.5".parse::<Amount>().unwrap().value, 1_500_000);
+        assert!(AssetId::native().is_native());
+    }
+}
@@ -1,29 +1,98 @@
 use clap::{Parser, Subcommand};
 use namada_shielded_demo::{
-    shielded_transaction::ShieldedTransaction,
-    merkle_tree::MerkleTree,
+    shielded_transaction::{ShieldedTransaction, TransactionStatus, TransactionType, VerifyOutcome},
     commitment::CommitmentScheme,
-    zk_proof::ZeroKnowledgeProof,
+    crypto::KeyScheme,
+    proof_system::{MockProofSystem, ProofSystem},
+    merkle_tree::MerkleTree,
     wallet::Wallet,
+    wallet_store::WalletStore,
     error::ShieldedError,
-    storage::StorageData,
+    storage::{CommitmentStatus, StorageData, TransactionFilter, VerifyMode},
+    policy::{FeePolicy, FeeRounding, TransactionPolicy},
+    amount::Amount,
+    stress,
 };
+use serde::Serialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::str::FromStr;
 use tracing::info;
 
 #[derive(Parser)]
 #[command(name = "namada-shielded-demo")]
 #[command(about = "A demonstration of shielded transactions and zero-knowledge proofs")]
 struct Cli {
+    /// When to verify stored transactions: none, eager (at load) or lazy (on first read)
+    #[arg(long, global = true, default_value = "none")]
+    verify_on_load: VerifyMode,
+    /// Accept forgeable legacy-scheme signatures when verifying, for stores written by old versions
+    #[arg(long, global = true)]
+    allow_legacy_signatures: bool,
+    /// Gzip the storage files when saving (either format is read back)
+    #[arg(long, global = true)]
+    compress: bool,
+    /// Directory holding the storage and wallet files [default: per-user data directory]
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+    /// Output format: text for people, or json for one JSON object per command
+    #[arg(long, global = true, default_value = "text")]
+    output: OutputFormat,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!("Unknown output format '{}', expected text or json", other)),
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Create a new wallet
     CreateWallet {
         #[arg(short, long)]
         name: String,
+        /// Signature scheme for the wallet keys: ed25519 or secp256k1
+        #[arg(long, default_value = "ed25519")]
+        key_scheme: KeyScheme,
+        /// Derive the keys from the wallet name for stable demo addresses (INSECURE)
+        #[arg(long)]
+        deterministic: bool,
+        /// Derive Ed25519 keys from a new 24-word mnemonic and print it for backup
+        #[arg(long, conflicts_with = "deterministic")]
+        mnemonic: bool,
+        /// Store the private key encrypted under this password instead of in plaintext
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Recreate a wallet from its BIP-39 mnemonic
+    RecoverWallet {
+        #[arg(short, long)]
+        name: String,
+        /// The 12 to 24 word phrase, quoted
+        #[arg(short, long)]
+        mnemonic: String,
+        /// BIP-39 passphrase the wallet was created with, if any
+        #[arg(long, default_value = "")]
+        passphrase: String,
+        /// Store the private key encrypted under this password instead of in plaintext
+        #[arg(long)]
+        password: Option<String>,
     },
     /// Create a shielded transaction
     CreateTransaction {
@@ -31,26 +100,104 @@ enum Commands {
         from: String,
         #[arg(short, long)]
         to: String,
+        /// Amount in NAM, e.g. `1.5`
         #[arg(short, long)]
-        amount: u64,
+        amount: Amount,
         #[arg(short, long)]
         shielded: bool,
+        /// Reject shielded outputs below this amount in NAM (0 disables the check)
+        #[arg(long, default_value = "0")]
+        dust_limit: Amount,
+        /// Rounding of the 0.1% shielded fee: floor, ceil or round
+        #[arg(long, default_value = "floor")]
+        fee_rounding: FeeRounding,
+        /// Stored wallet to receive shielded change instead of the sender
+        #[arg(long)]
+        change_address: Option<String>,
+        /// Reject a transaction above this amount in NAM
+        #[arg(long)]
+        max_amount: Option<Amount>,
+        /// Reject a transaction that takes the sender over this amount in NAM for the day
+        #[arg(long)]
+        daily_limit: Option<Amount>,
+        /// Solve a proof-of-work puzzle with this many leading zero bits before storing
+        #[arg(long, default_value_t = 0)]
+        pow_difficulty: u32,
+        /// Expire the transaction if it is still pending this many seconds after creation
+        #[arg(long)]
+        ttl: Option<u32>,
+        /// Also write the created transaction's JSON to this file
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Show the fee, debit and commitments without storing the transaction or moving balances
+        #[arg(long, conflicts_with = "out")]
+        dry_run: bool,
+        /// Password of an encrypted sender wallet, needed to sign
+        #[arg(long)]
+        password: Option<String>,
     },
     /// Verify a transaction
     VerifyTransaction {
         #[arg(short, long)]
         transaction_id: String,
     },
+    /// Verify every stored transaction
+    VerifyAll {
+        /// Stop at the first transaction that fails verification
+        #[arg(long)]
+        fail_fast: bool,
+        /// Run every check on every transaction and print each check's outcome
+        #[arg(long, conflicts_with = "fail_fast")]
+        detailed: bool,
+    },
     /// Generate a zero-knowledge proof
     GenerateProof {
         #[arg(short, long)]
         transaction_id: String,
     },
+    /// Decrypt and list the shielded notes received by a stored wallet
+    Notes {
+        #[arg(short, long)]
+        wallet: String,
+    },
+    /// Check whether a nullifier has been revealed by a stored transaction
+    CheckNullifier {
+        #[arg(short, long)]
+        value: String,
+    },
+    /// List the input commitments spent by stored shielded transactions
+    ShowSpent,
     /// Show wallet balance
     Balance {
         #[arg(short, long)]
         wallet: String,
     },
+    /// Credit a stored wallet from the demo faucet
+    Fund {
+        #[arg(short, long)]
+        wallet: String,
+        /// Amount in NAM, e.g. `1.5`
+        #[arg(short, long)]
+        amount: Amount,
+        /// Credit the shielded balance instead of the transparent one
+        #[arg(short, long)]
+        shielded: bool,
+    },
+    /// Derive the next receive address of a stored wallet
+    NewAddress {
+        #[arg(short, long)]
+        wallet: String,
+        /// Password of an encrypted wallet, needed to derive from its seed
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// List stored wallets
+    ListWallets,
+    /// Show a stored wallet's public details
+    ShowWallet {
+        #[arg(short, long)]
+        name: String,
+    },
     /// Demonstrate commitment scheme
     DemonstrateCommitment {
         #[arg(short, long)]
@@ -58,95 +205,690 @@ enum Commands {
     },
     /// Show Merkle tree state
     ShowMerkleTree,
+    /// Print a self-contained Merkle inclusion proof for a transaction as JSON
+    ProveInclusion {
+        #[arg(short, long)]
+        transaction_id: String,
+    },
+    /// Compute the Merkle root of a JSON array of leaves, without touching storage
+    MerkleRoot {
+        #[arg(short, long)]
+        leaves: PathBuf,
+    },
     /// List all stored transactions
-    ListTransactions,
+    ListTransactions {
+        /// Only transactions with this status: pending, confirmed or failed
+        #[arg(long)]
+        status: Option<TransactionStatus>,
+        /// Only transactions of this type: public or shielded
+        #[arg(long = "type")]
+        transaction_type: Option<TransactionType>,
+        /// Only transactions from this address
+        #[arg(long)]
+        from: Option<String>,
+        /// Show at most this many transactions
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Skip this many matching transactions first
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+    },
+    /// Hide a transaction from listings without deleting it
+    ArchiveTransaction {
+        #[arg(short, long)]
+        transaction_id: String,
+    },
+    /// Bring an archived transaction back into listings
+    RestoreTransaction {
+        #[arg(short, long)]
+        transaction_id: String,
+    },
+    /// List archived transactions
+    ListArchived,
+    /// Confirm a pending transaction
+    ConfirmTransaction {
+        #[arg(short, long)]
+        transaction_id: String,
+    },
+    /// Show statistics over stored transactions
+    Stats {
+        /// Count outputs below this amount in NAM as dust
+        #[arg(long, default_value = "0")]
+        dust_limit: Amount,
+    },
+    /// Generate synthetic transactions and report throughput
+    ///
+    /// Runs against a scratch store unless `--data-dir` is given, so the real store
+    /// is never filled with synthetic load.
+    Stress {
+        #[arg(short, long)]
+        count: usize,
+        #[arg(short, long)]
+        shielded: bool,
+        /// Seed for the deterministic RNG, so runs are reproducible
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+    /// Serve read-only stats, transactions and Merkle root over HTTP
+    #[cfg(feature = "http")]
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
     /// Clear all stored data
     ClearStorage,
 }
 
 #[tokio::main]
-async fn main() -> Result<(), ShieldedError> {
-    // Set up logging for the demo
-    tracing_subscriber::fmt::init();
+async fn main() {
+    // Set up logging for the demo, on stderr so `--output json` output stays clean
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
     
     info!("Starting Namada Shielded Transaction Demo");
     
-    // Load existing data from storage
-    let mut storage = StorageData::load()?;
-    info!("Loaded {} transactions from storage", storage.get_all_transactions().len());
-    
     let cli = Cli::parse();
+    let output = cli.output;
+    
+    if let Err(error) = run(cli).await {
+        if output == OutputFormat::Json {
+            let report = json!({
+                "error": error.to_string(),
+                "hint": suggestion(&error),
+                "exit_code": exit_code(&error),
+            });
+            eprintln!("{}", report);
+        } else {
+            eprintln!("{}", error_message(&error));
+        }
+        std::process::exit(exit_code(&error));
+    }
+}
+
+/// The error and any hint, as printed for text output
+fn error_message(error: &ShieldedError) -> String {
+    match suggestion(error) {
+        Some(suggestion) => format!("Error: {}\nHint: {}", error, suggestion),
+        None => format!("Error: {}", error),
+    }
+}
+
+/// Print a command's result as a single line of JSON
+fn print_json(value: &impl Serialize) -> Result<(), ShieldedError> {
+    let line = serde_json::to_string(value)?;
+    #[cfg(test)]
+    tests::PRINTED.with(|printed| printed.borrow_mut().push(line.clone()));
+    println!("{}", line);
+    Ok(())
+}
+
+/// Process exit code for a failed command, one per error variant
+///
+/// Codes start at 10 so they don't clash with clap's usage error (2).
+fn exit_code(error: &ShieldedError) -> i32 {
+    match error {
+        ShieldedError::InvalidTransaction(_) => 10,
+        ShieldedError::InsufficientFunds { .. } => 11,
+        ShieldedError::InvalidWalletAddress(_) => 12,
+        ShieldedError::ZKProofError(_) => 13,
+        ShieldedError::CommitmentError(_) => 14,
+        ShieldedError::MerkleTreeError(_) => 15,
+        ShieldedError::CryptoError(_) => 16,
+        ShieldedError::SerializationError(_) => 17,
+        ShieldedError::IoError(_) => 18,
+        ShieldedError::InvalidAmount(_) => 19,
+        ShieldedError::TransactionNotFound(_) => 20,
+        ShieldedError::WalletNotFound(_) => 21,
+        ShieldedError::StorageError(_) => 22,
+        ShieldedError::SequenceGap { .. } => 23,
+        ShieldedError::VerificationFailed { .. } => 24,
+    }
+}
+
+/// What the user can try next after an error, if there is anything useful to say
+fn suggestion(error: &ShieldedError) -> Option<&'static str> {
+    match error {
+        ShieldedError::TransactionNotFound(_) => Some("run `list-transactions` to see stored ids"),
+        ShieldedError::WalletNotFound(_) => Some("create it first with `create-wallet --name <NAME>`"),
+        ShieldedError::InvalidWalletAddress(message) if message.ends_with("already exists") => {
+            Some("wallet names must be unique; pick another name")
+        }
+        ShieldedError::InvalidWalletAddress(_) => Some("pass a stored wallet name or a `namada_` address"),
+        ShieldedError::InsufficientFunds { .. } => Some("check the balance with `balance --wallet <NAME>` or send a smaller amount"),
+        ShieldedError::InvalidAmount(_) => Some("amounts are in NAM with up to 6 decimal places, e.g. `--amount 1.5`"),
+        ShieldedError::SequenceGap { .. } => Some("the wallet's sequence is out of step with storage; check `list-transactions`"),
+        ShieldedError::VerificationFailed { .. } => Some("run `verify-all --detailed` to see which checks failed"),
+        ShieldedError::SerializationError(_) | ShieldedError::StorageError(_) => {
+            Some("a storage file may be corrupt; `clear-storage` resets it")
+        }
+        _ => None,
+    }
+}
+
+/// Per-user data directory, or the working directory on platforms without one
+fn default_data_dir() -> PathBuf {
+    directories::ProjectDirs::from("", "", "namada-shielded-demo")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_default()
+}
+
+async fn run(cli: Cli) -> Result<(), ShieldedError> {
+    // Load existing data from storage
+    let explicit_data_dir = cli.data_dir.is_some();
+    let data_dir = cli.data_dir.unwrap_or_else(default_data_dir);
+    let mut storage = if cli.allow_legacy_signatures {
+        StorageData::load_from_allowing_legacy(&data_dir, cli.verify_on_load)?
+    } else {
+        StorageData::load_from_with(&data_dir, cli.verify_on_load)?
+    };
+    storage.compress = cli.compress;
+    info!("Loaded {} transactions from {}", storage.transactions.len(), data_dir.display());
+    let mut wallets = WalletStore::load_from(&data_dir)?;
+    let json = cli.output == OutputFormat::Json;
     
     match cli.command {
-        Commands::CreateWallet { name } => {
-            let wallet = Wallet::new(&name)?;
-            println!("Created wallet: {}", wallet.address);
-            println!("Public key: {}", wallet.public_key);
+        Commands::CreateWallet { name, key_scheme, deterministic, mnemonic, password } => {
+            let phrase = mnemonic.then(Wallet::generate_mnemonic);
+            let mut wallet = if let Some(phrase) = &phrase {
+                if key_scheme != KeyScheme::Ed25519 {
+                    return Err(ShieldedError::CryptoError(
+                        "Mnemonic wallets use Ed25519 keys".to_string()
+                    ));
+                }
+                Wallet::from_mnemonic(&name, phrase)?
+            } else if deterministic {
+                Wallet::new_deterministic(&name, key_scheme)?
+            } else {
+                Wallet::new_with_scheme(&name, key_scheme)?
+            };
+            if let Some(password) = &password {
+                wallet.encrypt_private_key(password)?;
+            }
+            wallets.add_wallet(wallet.clone())?;
+            if json {
+                print_json(&json!({
+                    "name": wallet.name,
+                    "address": wallet.address,
+                    "public_key": wallet.public_key,
+                    "key_scheme": wallet.key_scheme,
+                    "encrypted": wallet.is_encrypted(),
+                    "mnemonic": phrase,
+                }))?;
+            } else {
+                println!("Created wallet: {}", wallet.address);
+                println!("Public key: {}", wallet.public_key);
+                if let Some(phrase) = &phrase {
+                    println!("Mnemonic (write it down; it is not stored): {}", phrase);
+                }
+            }
+        }
+        
+        Commands::RecoverWallet { name, mnemonic, passphrase, password } => {
+            let mut wallet = Wallet::recover(&name, &mnemonic, &passphrase)?;
+            let notes = storage.rescan(&mut wallet);
+            if let Some(password) = &password {
+                wallet.encrypt_private_key(password)?;
+            }
+            wallets.add_wallet(wallet.clone())?;
+            if json {
+                print_json(&json!({
+                    "name": wallet.name,
+                    "address": wallet.address,
+                    "public_key": wallet.public_key,
+                    "key_scheme": wallet.key_scheme,
+                    "encrypted": wallet.is_encrypted(),
+                    "notes": notes,
+                }))?;
+            } else {
+                println!("Recovered wallet: {}", wallet.address);
+                println!("Public key: {}", wallet.public_key);
+                println!("Found {} unspent notes in stored transactions", notes);
+            }
         }
         
-        Commands::CreateTransaction { from, to, amount, shielded } => {
+        Commands::CreateTransaction {
+            from,
+            to,
+            amount,
+            shielded,
+            dust_limit,
+            fee_rounding,
+            change_address,
+            max_amount,
+            daily_limit,
+            pow_difficulty,
+            ttl,
+            out,
+            dry_run,
+            password,
+        } => {
+            let policy = TransactionPolicy {
+                dust_limit: dust_limit.value,
+                fee_policy: FeePolicy::Proportional { bps: 10, min: 1, rounding: fee_rounding },
+                max_amount: max_amount.map(|max_amount| max_amount.value),
+                daily_limit: daily_limit.map(|daily_limit| daily_limit.value),
+                pow_difficulty,
+            };
+            // A stored sender always sends from its address, so its sequence stays in one place
+            let from = wallets.find(&from).map(|sender| sender.address.clone()).unwrap_or(from);
+            let to = wallets.find(&to).map(|recipient| recipient.address.clone()).unwrap_or(to);
+            // Storage enforces the caps on insert; checking here fails dry runs too
+            storage.check_daily_limit(&policy, &from, amount.value)?;
+            let change_to = match &change_address {
+                Some(change_address) => Some(
+                    wallets.find(change_address)
+                        .ok_or_else(|| ShieldedError::WalletNotFound(change_address.clone()))?,
+                ),
+                None => None,
+            };
+            // Only a stored wallet can sign, so the sender must be one. An encrypted
+            // sender is unlocked in memory only, to sign, and sealed again before it is stored
+            let stored_sender = wallets.find(&from).ok_or_else(|| ShieldedError::WalletNotFound(from.clone()))?;
+            let sealed = stored_sender.is_encrypted();
+            let mut sender = if sealed {
+                let password = password.as_deref().ok_or_else(|| ShieldedError::CryptoError(
+                    format!("Wallet '{}' is encrypted; pass --password to sign", stored_sender.name)
+                ))?;
+                stored_sender.unlocked(password)?
+            } else {
+                stored_sender.clone()
+            };
+            // The sender is debited the amount plus fee as the transaction is built. Between
+            // stored wallets, the output note is encrypted so the recipient can find it.
+            let recipient_viewing_key = wallets.find(&to).map(|recipient| recipient.viewing_public_key.as_str());
+            let transaction = if shielded {
+                ShieldedTransaction::create_shielded_from(
+                    &mut sender,
+                    &to,
+                    recipient_viewing_key,
+                    amount.value,
+                    change_to,
+                    &policy,
+                )?
+            } else {
+                ShieldedTransaction::create_public_from(&mut sender, &to, amount.value, &policy)?
+            };
+            
+            // Shielded transactions commit to the tree state they were built against
             let transaction = if shielded {
-                ShieldedTransaction::create_shielded(&from, &to, amount)?
+                transaction.with_anchor(storage.current_anchor())
             } else {
-                ShieldedTransaction::create_public(&from, &to, amount)?
+                transaction
             };
             
-            // Store the transaction persistently
-            storage.add_transaction(transaction.clone())?;
+            let transaction = match ttl {
+                Some(ttl) => transaction.with_ttl(chrono::Duration::seconds(i64::from(ttl))),
+                None => transaction,
+            };
+            
+            if dry_run {
+                let simulation = transaction.simulation()?;
+                if json {
+                    return print_json(&simulation);
+                }
+                println!("Dry run, nothing stored");
+                println!("Type: {}", if shielded { "Shielded" } else { "Public" });
+                println!("Amount: {}", amount);
+                println!("Fee: {}", Amount::new(simulation.fee));
+                println!("Total debit: {}", Amount::new(simulation.total_debit));
+                println!("Balanced: {}", simulation.balanced);
+                for commitment in &simulation.input_commitments {
+                    println!("Input commitment: {}", commitment);
+                }
+                for commitment in &simulation.output_commitments {
+                    println!("Output commitment: {}", commitment);
+                }
+                return Ok(());
+            }
+            
+            // Transactions carry the sender's next sequence number, and are signed again
+            // once the sequence and anchor are in place. Storage is the record of what was
+            // sent, so the wallet's counter picks up from there
+            sender.sequence = storage.expected_sequence(&transaction.from);
+            let mut transaction = transaction.with_sequence(sender.next_sequence());
+            transaction.resign(&sender)?;
+            if let (true, Some(password)) = (sealed, &password) {
+                sender.encrypt_private_key(password)?;
+            }
+            
+            // The nonce covers the content hash, so solve it last
+            if policy.pow_difficulty > 0 {
+                let attempts = transaction.solve_pow(policy.pow_difficulty)?;
+                info!("Solved proof of work in {} attempts", attempts);
+            }
+            
+            // Move the balances, then store the transaction persistently with storage
+            // checking the proof of work; if storage refuses it, the wallets are put back
+            let previous_wallets = wallets.clone();
+            wallets.record_transfer(Some(sender), &transaction)?;
+            storage.policy.pow_difficulty = policy.pow_difficulty;
+            storage.policy.max_amount = policy.max_amount;
+            storage.policy.daily_limit = policy.daily_limit;
+            if let Err(error) = storage.add_transaction(transaction.clone()) {
+                previous_wallets.save()?;
+                return Err(error);
+            }
+            
+            // Export what was stored, which includes the chain link storage adds
+            let stored = storage.get_transaction(&transaction.id).unwrap_or(&transaction);
+            if let Some(out) = &out {
+                std::fs::write(out, stored.to_json()?)?;
+            }
+            if json {
+                return print_json(stored);
+            }
             
             println!("Created transaction: {}", transaction.id);
             println!("Type: {}", if shielded { "Shielded" } else { "Public" });
             println!("Amount: {}", amount);
+            println!("Fee: {}", Amount::new(transaction.fee));
+            if let Some(expires_at) = transaction.expires_at {
+                println!("Expires: {}", expires_at);
+            }
+            for nullifier in &transaction.nullifiers {
+                println!("Nullifier: {}", nullifier);
+            }
             println!("Transaction saved to persistent storage!");
+            if let Some(out) = &out {
+                println!("Transaction JSON written to {}", out.display());
+            }
         }
         
         Commands::VerifyTransaction { transaction_id } => {
             // Check if transaction exists in persistent storage
             if let Some(transaction) = storage.get_transaction(&transaction_id) {
+                let is_valid = ShieldedTransaction::verify(&transaction_id)?;
+                let proof = transaction.verify_proof()?;
+                if json {
+                    return print_json(&json!({
+                        "id": transaction.id,
+                        "from": transaction.from,
+                        "to": transaction.to,
+                        "amount": transaction.amount,
+                        "transaction_type": transaction.transaction_type,
+                        "status": transaction.status,
+                        "failure_reason": transaction.failure_reason,
+                        "timestamp": transaction.timestamp,
+                        "format_valid": is_valid,
+                        "proof": proof,
+                    }));
+                }
+                
                 println!("Transaction {} found in persistent storage", transaction_id);
                 println!("From: {} -> To: {}", transaction.from, transaction.to);
-                println!("Amount: {}, Type: {:?}", transaction.amount, transaction.transaction_type);
+                println!("Amount: {}, Type: {:?}", Amount::new(transaction.amount), transaction.transaction_type);
                 println!("Status: {:?}", transaction.status);
+                if let Some(reason) = &transaction.failure_reason {
+                    println!("Failure reason: {}", reason);
+                }
                 println!("Timestamp: {}", transaction.timestamp);
                 
                 // Also verify the transaction format
-                let is_valid = ShieldedTransaction::verify(&transaction_id)?;
                 println!("Transaction format is {}", if is_valid { "valid" } else { "invalid" });
+                
+                match proof {
+                    VerifyOutcome::NotApplicable => println!("Proof: public transaction, no proof to verify"),
+                    outcome => println!("Proof is {}", outcome),
+                }
             } else {
-                println!("Transaction {} not found in persistent storage", transaction_id);
-                println!("Checking transaction format only...");
-                let is_valid = ShieldedTransaction::verify(&transaction_id)?;
-                println!("Transaction format is {}", if is_valid { "valid" } else { "invalid" });
+                return Err(ShieldedError::TransactionNotFound(transaction_id));
+            }
+        }
+        
+        Commands::VerifyAll { detailed: true, .. } => {
+            let reports = storage.verification_reports();
+            let failed = reports.iter().filter(|(_, report)| !report.passed()).count();
+            
+            if json {
+                let reports: Vec<_> = reports
+                    .iter()
+                    .map(|(id, report)| json!({ "id": id, "passed": report.passed(), "checks": report }))
+                    .collect();
+                print_json(&json!({ "checked": reports.len(), "failed": failed, "reports": reports }))?;
+            } else {
+                println!("=== Verification Report ===");
+                for (id, report) in &reports {
+                    println!("{} {}", if report.passed() { "PASSED" } else { "FAILED" }, id);
+                    for (check, outcome) in report.checks() {
+                        println!("  {}: {}", check, outcome);
+                    }
+                }
+                println!("Checked: {}, Passed: {}, Failed: {}", reports.len(), reports.len() - failed, failed);
+            }
+            
+            if failed > 0 {
+                return Err(ShieldedError::VerificationFailed { failed, checked: reports.len() });
+            }
+        }
+        
+        Commands::VerifyAll { fail_fast, .. } => {
+            let summary = storage.verify_all(fail_fast);
+            
+            if json {
+                print_json(&summary)?;
+            } else {
+                println!("=== Verification Summary ===");
+                println!("Checked: {}, Passed: {}, Failed: {}", summary.checked, summary.passed.len(), summary.failed.len());
+                for (id, reason) in &summary.failed {
+                    println!("FAILED {}: {}", id, reason);
+                }
+                if summary.stopped_early {
+                    println!("Stopped at the first failure (--fail-fast)");
+                }
+            }
+            
+            if !summary.all_passed() {
+                return Err(ShieldedError::VerificationFailed { failed: summary.failed.len(), checked: summary.checked });
             }
         }
         
         Commands::GenerateProof { transaction_id } => {
-            let proof = ZeroKnowledgeProof::generate(&transaction_id)?;
-            println!("Generated ZK proof for transaction: {}", transaction_id);
-            println!("Proof: {}", proof);
+            let transaction = storage.get_transaction(&transaction_id)
+                .ok_or_else(|| ShieldedError::TransactionNotFound(transaction_id.clone()))?;
+            let proof = MockProofSystem.generate(&transaction.proof_statement())?;
+            if json {
+                print_json(&json!({ "transaction_id": transaction_id, "proof": proof }))?;
+            } else {
+                println!("Generated ZK proof for transaction: {}", transaction_id);
+                println!("Proof: {}", proof);
+            }
+        }
+        
+        Commands::Notes { wallet } => {
+            let wallet = wallets.get_wallet(&wallet)?;
+            let received = storage.received_notes(wallet);
+            
+            if json {
+                print_json(&json!({ "wallet": wallet.name, "address": wallet.address, "notes": received }))?;
+            } else if received.is_empty() {
+                println!("No notes received by wallet {} ({}).", wallet.name, wallet.address);
+            } else {
+                println!("=== Notes for {} ===", wallet.name);
+                for (i, received) in received.iter().enumerate() {
+                    let status = match received.status {
+                        CommitmentStatus::Spent => "spent",
+                        CommitmentStatus::Unspent | CommitmentStatus::Unknown => "unspent",
+                    };
+                    println!("{}. {} ({})", i + 1, Amount::new(received.note.value), status);
+                    println!("   Transaction: {}", received.transaction_id);
+                    println!("   Commitment: {}", received.note.commitment);
+                }
+            }
+        }
+        
+        Commands::CheckNullifier { value } => {
+            let spent = storage.is_spent(&value);
+            if json {
+                print_json(&json!({ "nullifier": value, "spent": spent }))?;
+            } else if spent {
+                println!("Nullifier {} is spent", value);
+            } else {
+                println!("Nullifier {} is unspent", value);
+            }
+        }
+        
+        Commands::ShowSpent => {
+            let spent = storage.spent_commitments();
+            if json {
+                print_json(&json!({ "spent_commitments": spent }))?;
+            } else if spent.is_empty() {
+                println!("No input commitments have been spent");
+            } else {
+                println!("Spent input commitments ({}):", spent.len());
+                for commitment in spent {
+                    println!("  {}", commitment);
+                }
+            }
         }
         
         Commands::Balance { wallet } => {
-            // TODO: In production, this would actually query the blockchain state
-            println!("Balance for wallet {}: 1000 NAM (estimated)", wallet);
+            let wallet = wallets.get_wallet(&wallet)?;
+            if json {
+                print_json(&json!({
+                    "wallet": wallet.name,
+                    "transparent": wallet.balance,
+                    "shielded": wallet.shielded_balance,
+                    "total": wallet.get_total_balance()?,
+                }))?;
+            } else {
+                println!("Balance for wallet {}:", wallet.name);
+                println!("  Transparent: {}", Amount::new(wallet.balance));
+                println!("  Shielded: {}", Amount::new(wallet.shielded_balance));
+                println!("  Total: {}", Amount::new(wallet.get_total_balance()?));
+            }
+        }
+        
+        Commands::Fund { wallet, amount, shielded } => {
+            let mut wallet = wallets.get_wallet(&wallet)?.clone();
+            if shielded {
+                wallet.add_shielded_funds(amount.value)?;
+            } else {
+                wallet.add_funds(amount.value)?;
+            }
+            wallets.update_wallet(wallet.clone())?;
+            
+            if json {
+                print_json(&json!({
+                    "wallet": wallet.name,
+                    "transparent": wallet.balance,
+                    "shielded": wallet.shielded_balance,
+                }))?;
+            } else {
+                println!("Funded wallet {} with {}", wallet.name, amount);
+                println!("  Transparent: {}", Amount::new(wallet.balance));
+                println!("  Shielded: {}", Amount::new(wallet.shielded_balance));
+            }
+        }
+        
+        Commands::NewAddress { wallet, password } => {
+            let mut wallet = wallets.get_wallet(&wallet)?.clone();
+            // An encrypted wallet's seed is unlocked in memory only
+            let address = match &password {
+                Some(password) if wallet.is_encrypted() => {
+                    let mut unlocked = wallet.unlocked(password)?;
+                    let address = unlocked.new_receive_address()?;
+                    wallet.receive_addresses = unlocked.receive_addresses;
+                    address
+                }
+                _ => wallet.new_receive_address()?,
+            };
+            wallets.update_wallet(wallet.clone())?;
+            
+            if json {
+                print_json(&json!({ "wallet": wallet.name, "address": address }))?;
+            } else {
+                println!("New receive address for {}: {}", wallet.name, address);
+            }
+        }
+        
+        Commands::ListWallets => {
+            let stored = wallets.list_wallets();
+            
+            if json {
+                let stored: Vec<_> = stored
+                    .iter()
+                    .map(|wallet| Ok(json!({
+                        "name": wallet.name,
+                        "address": wallet.address,
+                        "balance": wallet.get_total_balance()?,
+                    })))
+                    .collect::<Result<_, ShieldedError>>()?;
+                print_json(&json!({ "wallets": stored }))?;
+            } else if stored.is_empty() {
+                println!("No wallets stored yet.");
+            } else {
+                println!("=== Stored Wallets ===");
+                for (i, wallet) in stored.iter().enumerate() {
+                    println!("{}. {} ({})", i + 1, wallet.name, wallet.address);
+                    println!("   Balance: {}", Amount::new(wallet.get_total_balance()?));
+                }
+            }
+        }
+        
+        Commands::ShowWallet { name } => {
+            // Private and viewing keys stay in the wallet file
+            let wallet = wallets.get_wallet(&name)?;
+            if json {
+                return print_json(&json!({
+                    "name": wallet.name,
+                    "address": wallet.address,
+                    "public_key": wallet.public_key,
+                    "key_scheme": wallet.key_scheme,
+                    "viewing_public_key": wallet.viewing_public_key,
+                    "transparent": wallet.balance,
+                    "shielded": wallet.shielded_balance,
+                    "notes": wallet.notes.len(),
+                    "sequence": wallet.sequence,
+                    "receive_addresses": wallet.receive_addresses,
+                }));
+            }
+            println!("=== Wallet {} ===", wallet.name);
+            println!("Address: {}", wallet.address);
+            for address in &wallet.receive_addresses {
+                println!("Receive address: {}", address);
+            }
+            println!("Public key: {} ({})", wallet.public_key, wallet.key_scheme);
+            println!("Viewing public key: {}", wallet.viewing_public_key);
+            println!("Transparent balance: {}", Amount::new(wallet.balance));
+            println!("Shielded balance: {}", Amount::new(wallet.shielded_balance));
+            println!("Notes held: {}", wallet.notes.len());
+            println!("Next sequence: {}", wallet.sequence);
         }
         
         Commands::DemonstrateCommitment { amount } => {
             let commitment = CommitmentScheme::commit(amount)?;
-            println!("Commitment for amount {}: {}", amount, commitment);
-            
-            let proof = CommitmentScheme::prove_knowledge(amount)?;
-            println!("Knowledge proof: {}", proof);
+            let opens = CommitmentScheme::open_commitment(&commitment, amount, &commitment.nonce)?;
+            let proof = CommitmentScheme::prove_knowledge_of(&commitment, amount, &commitment.nonce)?;
+            let is_valid = CommitmentScheme::verify_knowledge_proof(&commitment, &proof)?;
             
-            let is_valid = CommitmentScheme::verify_knowledge(&commitment, &proof)?;
+            if json {
+                return print_json(&json!({
+                    "amount": amount,
+                    "commitment": commitment.commitment_hash,
+                    "nonce": commitment.nonce,
+                    "opens": opens,
+                    "knowledge_proof": proof.proof_hash,
+                    "proof_valid": is_valid,
+                }));
+            }
+            println!("Commitment for amount {}: {}", amount, commitment.commitment_hash);
+            println!("Opening with nonce {}: {}", commitment.nonce, if opens { "valid" } else { "invalid" });
+            println!("Knowledge proof: {}", proof.proof_hash);
             println!("Proof verification: {}", if is_valid { "valid" } else { "invalid" });
         }
         
         Commands::ShowMerkleTree => {
-            // Rebuild Merkle tree from stored leaves
-            let tree = storage.rebuild_merkle_tree();
-            let transactions = storage.get_all_transactions();
+            let tree = storage.merkle_tree();
+            let transactions = storage.transactions_by_time();
             
+            if json {
+                let ids: Vec<&str> = transactions.iter().map(|transaction| transaction.id.as_str()).collect();
+                return print_json(&json!({
+                    "root": tree.root(),
+                    "height": tree.height(),
+                    "leaf_count": tree.leaf_count(),
+                    "transactions": ids,
+                }));
+            }
             println!("=== Merkle Tree State ===");
             println!("Merkle Tree Root: {}", tree.root());
             println!("Tree Height: {}", tree.height());
@@ -155,27 +897,62 @@ async fn main() -> Result<(), ShieldedError> {
             
             if !transactions.is_empty() {
                 println!("\n=== Stored Transactions ===");
-                for (id, transaction) in transactions.iter() {
-                    println!("ID: {}", id);
+                for transaction in &transactions {
+                    println!("ID: {}", transaction.id);
                     println!("  From: {} -> To: {}", transaction.from, transaction.to);
-                    println!("  Amount: {}, Type: {:?}", transaction.amount, transaction.transaction_type);
+                    println!("  Amount: {}, Type: {:?}", Amount::new(transaction.amount), transaction.transaction_type);
                     println!("  Status: {:?}", transaction.status);
                     println!();
                 }
             }
         }
         
-        Commands::ListTransactions => {
-            let transactions = storage.get_all_transactions();
+        Commands::ProveInclusion { transaction_id } => {
+            let proof = storage.inclusion_proof(&transaction_id)?;
+            if json {
+                print_json(&proof)?;
+            } else {
+                println!("{}", serde_json::to_string_pretty(&proof)?);
+            }
+        }
+        
+        Commands::MerkleRoot { leaves } => {
+            let content = std::fs::read_to_string(&leaves)?;
+            let leaves: Vec<String> = if content.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&content)?
+            };
+            let tree = MerkleTree::from_leaves(&leaves)?;
             
-            if transactions.is_empty() {
-                println!("No transactions stored yet.");
+            if json {
+                print_json(&json!({ "root": tree.root(), "height": tree.height(), "leaf_count": tree.leaf_count() }))?;
             } else {
-                println!("=== All Stored Transactions ===");
-                for (i, (id, transaction)) in transactions.iter().enumerate() {
-                    println!("{}. Transaction ID: {}", i + 1, id);
+                println!("Merkle Tree Root: {}", tree.root());
+                println!("Tree Height: {}", tree.height());
+                println!("Number of leaves: {}", tree.leaf_count());
+            }
+        }
+        
+        Commands::ListTransactions { status, transaction_type, from, limit, offset } => {
+            let filter = TransactionFilter {
+                from,
+                transaction_type,
+                status,
+                ..TransactionFilter::default()
+            };
+            let transactions = storage.query_transactions(&filter, offset, limit);
+            
+            if json {
+                print_json(&json!({ "offset": offset, "transactions": transactions }))?;
+            } else if transactions.is_empty() {
+                println!("No matching transactions.");
+            } else {
+                println!("=== Stored Transactions ===");
+                for (i, transaction) in transactions.iter().enumerate() {
+                    println!("{}. Transaction ID: {}", offset + i + 1, transaction.id);
                     println!("   From: {} -> To: {}", transaction.from, transaction.to);
-                    println!("   Amount: {}, Type: {:?}", transaction.amount, transaction.transaction_type);
+                    println!("   Amount: {}, Type: {:?}", Amount::new(transaction.amount), transaction.transaction_type);
                     println!("   Status: {:?}", transaction.status);
                     println!("   Timestamp: {}", transaction.timestamp);
                     println!();
@@ -183,13 +960,430 @@ async fn main() -> Result<(), ShieldedError> {
             }
         }
         
+        Commands::ArchiveTransaction { transaction_id } => {
+            storage.archive_transaction(&transaction_id)?;
+            if json {
+                print_json(&json!({ "archived": transaction_id }))?;
+            } else {
+                println!("Archived transaction {}; restore it with `restore-transaction`", transaction_id);
+            }
+        }
+        
+        Commands::RestoreTransaction { transaction_id } => {
+            storage.restore_archived(&transaction_id)?;
+            if json {
+                print_json(&json!({ "restored": transaction_id }))?;
+            } else {
+                println!("Restored transaction {}", transaction_id);
+            }
+        }
+        
+        Commands::ConfirmTransaction { transaction_id } => {
+            storage.confirm_transaction(&transaction_id)?;
+            if json {
+                print_json(&json!({ "confirmed": transaction_id }))?;
+            } else {
+                println!("Confirmed transaction {}", transaction_id);
+            }
+        }
+        
+        Commands::ListArchived => {
+            let archived = storage.list_archived();
+            
+            if json {
+                print_json(&json!({ "transactions": archived }))?;
+            } else if archived.is_empty() {
+                println!("No archived transactions.");
+            } else {
+                println!("=== Archived Transactions ===");
+                for (i, transaction) in archived.iter().enumerate() {
+                    println!("{}. Transaction ID: {}", i + 1, transaction.id);
+                    println!("   From: {} -> To: {}", transaction.from, transaction.to);
+                    println!("   Amount: {}, Type: {:?}", Amount::new(transaction.amount), transaction.transaction_type);
+                    println!("   Timestamp: {}", transaction.timestamp);
+                    println!();
+                }
+            }
+        }
+        
+        Commands::Stats { dust_limit } => {
+            storage.policy.dust_limit = dust_limit.value;
+            let stats = storage.stats();
+            
+            if json {
+                return print_json(&stats);
+            }
+            println!("=== Storage Statistics ===");
+            println!("Total transactions: {}", stats.total_transactions);
+            println!("Public: {}, Shielded: {}", stats.public_transactions, stats.shielded_transactions);
+            for (asset, amount) in &stats.amount_by_asset {
+                let fees = stats.fees_by_asset.get(asset).copied().unwrap_or(0);
+                println!("{}: amount {}, fees {}", asset, Amount::new(*amount), Amount::new(fees));
+            }
+            println!("Dust outputs (below {}): {}", dust_limit, stats.dust_outputs);
+            println!("Commitment bytes: {} total, {:.1} avg per shielded tx", stats.commitment_bytes_total, stats.commitment_bytes_avg);
+            println!("Proof bytes: {} total, {:.1} avg per shielded tx", stats.proof_bytes_total, stats.proof_bytes_avg);
+        }
+        
+        Commands::Stress { count, shielded, seed } => {
+            let report = if explicit_data_dir {
+                stress::run(&mut storage, count, shielded, seed)?
+            } else {
+                stress::run_scratch(count, shielded, seed)?
+            };
+            
+            if json {
+                return print_json(&report);
+            }
+            println!("=== Stress Test ===");
+            println!("Generated {} {} transactions in {:.2?}", report.transactions, if shielded { "shielded" } else { "public" }, report.elapsed);
+            println!("Throughput: {:.1} tx/sec", report.transactions_per_second);
+            println!("Merkle Tree Root: {}", report.merkle_root);
+            println!("Storage size: {} bytes", report.storage_bytes);
+        }
+        
+        #[cfg(feature = "http")]
+        Commands::Serve { addr } => {
+            if json {
+                print_json(&json!({ "serving": format!("http://{}", addr) }))?;
+            } else {
+                println!("Serving read-only endpoints on http://{}", addr);
+            }
+            namada_shielded_demo::http::serve(&addr, data_dir).await?;
+        }
+        
         Commands::ClearStorage => {
             storage.clear()?;
-            println!("All stored data has been cleared.");
-            println!("Storage files have been reset.");
+            wallets.reset_sequences()?;
+            if json {
+                print_json(&json!({ "cleared": true }))?;
+            } else {
+                println!("All stored data has been cleared.");
+                println!("Storage files have been reset.");
+            }
         }
     }
     
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    
+    thread_local! {
+        /// Lines `print_json` printed on this thread
+        pub(super) static PRINTED: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+    }
+    
+    fn run_in(dir: &std::path::Path, args: &[&str]) -> Result<(), ShieldedError> {
+        let data_dir = dir.to_str().unwrap();
+        let cli = Cli::parse_from(["namada-shielded-demo", "--data-dir", data_dir].iter().chain(args));
+        tokio::runtime::Runtime::new().unwrap().block_on(run(cli))
+    }
+    
+    /// Run a command with `--output json` and parse the single line it printed
+    fn run_json(dir: &std::path::Path, args: &[&str]) -> Value {
+        PRINTED.with(|printed| printed.borrow_mut().clear());
+        run_in(dir, &[&["--output", "json"][..], args].concat()).unwrap();
+        let printed = PRINTED.with(|printed| printed.take());
+        assert_eq!(printed.len(), 1);
+        serde_json::from_str(&printed[0]).unwrap()
+    }
+    
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        keys
+    }
+    
+    #[test]
+    fn failed_verification_is_an_error_with_its_own_exit_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let transaction = ShieldedTransaction::create_shielded(&Wallet::new("alice").unwrap(), &Wallet::new("bob").unwrap().address, 100).unwrap();
+        let id = transaction.id.clone();
+        storage.add_transaction(transaction).unwrap();
+        storage.transactions.get_mut(&id).unwrap().amount += 1;
+        storage.save().unwrap();
+        
+        for args in [&["verify-all"][..], &["verify-all", "--detailed"], &["verify-all", "--fail-fast"]] {
+            let error = run_in(dir.path(), args).unwrap_err();
+            assert!(matches!(error, ShieldedError::VerificationFailed { failed: 1, checked: 1 }));
+            assert_eq!(exit_code(&error), 24);
+        }
+    }
+    
+    #[test]
+    fn passing_verification_succeeds() {
+        let dir = tempfile::tempdir().unwrap();
+        run_in(dir.path(), &["verify-all"]).unwrap();
+    }
+    
+    #[test]
+    fn sequences_start_over_after_clearing_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "alice"]).unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        let send = ["create-transaction", "--from", "alice", "--to", "bob", "--amount", "0.0001"];
+        run_in(dir.path(), &send).unwrap();
+        run_in(dir.path(), &send).unwrap();
+        
+        run_in(dir.path(), &["clear-storage"]).unwrap();
+        assert_eq!(WalletStore::load_from(dir.path()).unwrap().get_wallet("alice").unwrap().sequence, 0);
+        run_in(dir.path(), &send).unwrap();
+        
+        let storage = StorageData::load_from(dir.path()).unwrap();
+        let sequences: Vec<Option<u64>> = storage.transactions.values().map(|transaction| transaction.sequence).collect();
+        assert_eq!(sequences, vec![Some(0)]);
+    }
+    
+    #[test]
+    fn json_output_prints_one_object_per_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let created = run_json(dir.path(), &["create-wallet", "--name", "alice"]);
+        assert_eq!(keys(&created), ["address", "encrypted", "key_scheme", "mnemonic", "name", "public_key"]);
+        assert_eq!(created["name"], "alice");
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        
+        let balance = run_json(dir.path(), &["balance", "--wallet", "alice"]);
+        assert_eq!(keys(&balance), ["shielded", "total", "transparent", "wallet"]);
+        assert_eq!(balance["transparent"], 1_000);
+        
+        let transaction = run_json(dir.path(), &["create-transaction", "--from", "alice", "--to", "bob", "--amount", "0.0001"]);
+        let transaction: ShieldedTransaction = serde_json::from_value(transaction).unwrap();
+        let storage = StorageData::load_from(dir.path()).unwrap();
+        assert_eq!(storage.get_transaction(&transaction.id).unwrap().prev_hash, transaction.prev_hash);
+    }
+    
+    #[test]
+    fn notes_shows_the_decrypted_amount_sent_to_a_wallet() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["alice", "bob", "carol"] {
+            run_in(dir.path(), &["create-wallet", "--name", name]).unwrap();
+        }
+        run_in(dir.path(), &["fund", "--wallet", "alice", "--amount", "1", "--shielded"]).unwrap();
+        run_in(dir.path(), &["create-transaction", "--from", "alice", "--to", "bob", "--amount", "0.25", "--shielded"]).unwrap();
+        
+        let notes = run_json(dir.path(), &["notes", "--wallet", "bob"]);
+        let notes = notes["notes"].as_array().unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0]["note"]["value"], 250_000);
+        assert_eq!(notes[0]["status"], "Unspent");
+        
+        let none = run_json(dir.path(), &["notes", "--wallet", "carol"]);
+        assert_eq!(none["notes"], serde_json::json!([]));
+        run_in(dir.path(), &["notes", "--wallet", "carol"]).unwrap();
+    }
+    
+    #[test]
+    fn merkle_root_of_published_leaves_matches_the_stored_root() {
+        let dir = tempfile::tempdir().unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "alice"]).unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        for amount in ["0.0001", "0.0002", "0.0003"] {
+            run_in(dir.path(), &["create-transaction", "--from", "alice", "--to", "bob", "--amount", amount]).unwrap();
+        }
+        
+        let storage = StorageData::load_from(dir.path()).unwrap();
+        let leaves: Vec<String> = storage
+            .get_merkle_leaves()
+            .iter()
+            .map(|id| storage.get_transaction(id).unwrap().merkle_leaf())
+            .collect();
+        let leaves_file = dir.path().join("leaves.json");
+        std::fs::write(&leaves_file, serde_json::to_string(&leaves).unwrap()).unwrap();
+        
+        let computed = run_json(dir.path(), &["merkle-root", "--leaves", leaves_file.to_str().unwrap()]);
+        assert_eq!(computed["root"], storage.merkle_tree().root());
+        assert_eq!(computed["height"], storage.merkle_tree().height());
+        assert_eq!(computed["leaf_count"], 3);
+        
+        std::fs::write(&leaves_file, "").unwrap();
+        let empty = run_json(dir.path(), &["merkle-root", "--leaves", leaves_file.to_str().unwrap()]);
+        assert_eq!(empty["root"], namada_shielded_demo::merkle_tree::EMPTY_ROOT);
+    }
+    
+    /// Log output collected by a test subscriber
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+    
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    #[test]
+    fn deterministic_wallets_repeat_their_address_and_warn() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt().with_writer(move || writer.clone()).with_ansi(false).finish();
+        
+        let create = |deterministic: bool| {
+            let dir = tempfile::tempdir().unwrap();
+            let flag = if deterministic { "--deterministic" } else { "--key-scheme=ed25519" };
+            run_json(dir.path(), &["create-wallet", "--name", "alice", flag])["address"].clone()
+        };
+        let (first, second) = tracing::subscriber::with_default(subscriber, || (create(true), create(true)));
+        assert_eq!(first, second);
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("WARN") && logs.contains("deterministic keys"), "{}", logs);
+        
+        assert_ne!(create(false), create(false));
+    }
+    
+    #[test]
+    fn failed_commands_print_a_hint_and_exit_with_their_variant_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = run_in(dir.path(), &["verify-transaction", "--transaction-id", "unknown"]).unwrap_err();
+        assert_eq!(exit_code(&missing), 20);
+        assert_eq!(
+            error_message(&missing),
+            format!("Error: {}\nHint: run `list-transactions` to see stored ids", missing)
+        );
+        
+        run_in(dir.path(), &["create-wallet", "--name", "alice"]).unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        let broke = run_in(dir.path(), &["create-transaction", "--from", "alice", "--to", "bob", "--amount", "5", "--shielded"]).unwrap_err();
+        assert!(matches!(broke, ShieldedError::InsufficientFunds { .. }));
+        assert_eq!(exit_code(&broke), 11);
+        assert!(error_message(&broke).ends_with("Hint: check the balance with `balance --wallet <NAME>` or send a smaller amount"));
+    }
+    
+    #[test]
+    fn out_writes_json_that_parses_back_to_the_stored_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "alice"]).unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        let out = dir.path().join("created.json");
+        run_in(dir.path(), &["create-transaction", "--from", "alice", "--to", "bob", "--amount", "0.0001", "--out", out.to_str().unwrap()]).unwrap();
+        
+        let exported = ShieldedTransaction::from_json(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        let storage = StorageData::load_from(dir.path()).unwrap();
+        let stored = storage.get_transaction(&exported.id).unwrap();
+        assert_eq!(exported.to_json().unwrap(), stored.to_json().unwrap());
+    }
+    
+    #[test]
+    fn stored_wallets_are_listed_without_private_keys_and_balance_reads_them() {
+        let dir = tempfile::tempdir().unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "alice"]).unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        run_in(dir.path(), &["fund", "--wallet", "alice", "--amount", "2", "--shielded"]).unwrap();
+        let alice = WalletStore::load_from(dir.path()).unwrap().get_wallet("alice").unwrap().clone();
+        
+        let listed = run_json(dir.path(), &["list-wallets"]);
+        let names: Vec<&str> = listed["wallets"].as_array().unwrap().iter().map(|wallet| wallet["name"].as_str().unwrap()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"alice") && names.contains(&"bob"));
+        let shown = run_json(dir.path(), &["show-wallet", "--name", "alice"]);
+        for output in [&listed, &shown] {
+            let output = output.to_string();
+            assert!(!output.contains(&alice.private_key) && !output.contains(&alice.viewing_key));
+        }
+        
+        let balance = run_json(dir.path(), &["balance", "--wallet", "alice"]);
+        assert_eq!(balance["transparent"], alice.balance);
+        assert_eq!(balance["shielded"], 2_000_000);
+    }
+    
+    #[test]
+    fn confirm_transaction_saves_the_new_status_once() {
+        let dir = tempfile::tempdir().unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "alice"]).unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        let created = run_json(dir.path(), &["create-transaction", "--from", "alice", "--to", "bob", "--amount", "0.0001"]);
+        let id = created["id"].as_str().unwrap();
+        
+        run_in(dir.path(), &["confirm-transaction", "--transaction-id", id]).unwrap();
+        let storage = StorageData::load_from(dir.path()).unwrap();
+        assert_eq!(storage.get_transaction(id).unwrap().status, TransactionStatus::Confirmed);
+        
+        let again = run_in(dir.path(), &["confirm-transaction", "--transaction-id", id]).unwrap_err();
+        assert!(matches!(again, ShieldedError::InvalidTransaction(_)));
+    }
+    
+    #[test]
+    fn recover_wallet_rebuilds_a_mnemonic_wallet() {
+        let dir = tempfile::tempdir().unwrap();
+        let created = run_json(dir.path(), &["create-wallet", "--name", "alice", "--mnemonic"]);
+        let mnemonic = created["mnemonic"].as_str().unwrap();
+        
+        let elsewhere = tempfile::tempdir().unwrap();
+        let recovered = run_json(elsewhere.path(), &["recover-wallet", "--name", "alice", "--mnemonic", mnemonic]);
+        assert_eq!(recovered["address"], created["address"]);
+        assert_eq!(recovered["public_key"], created["public_key"]);
+        
+        let bad_checksum = ["abandon"; 12].join(" ");
+        assert!(run_in(elsewhere.path(), &["recover-wallet", "--name", "bob", "--mnemonic", &bad_checksum]).is_err());
+    }
+    
+    #[test]
+    fn prove_inclusion_prints_a_proof_that_verifies_against_the_stored_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let transactions: Vec<ShieldedTransaction> = (1..=3)
+            .map(|amount| ShieldedTransaction::create_public(&Wallet::new("alice").unwrap(), &Wallet::new("bob").unwrap().address, amount * 100).unwrap())
+            .collect();
+        let id = transactions[1].id.clone();
+        storage.add_transactions(transactions).unwrap();
+        
+        let printed = run_json(dir.path(), &["prove-inclusion", "--transaction-id", &id]);
+        let proof: namada_shielded_demo::merkle_tree::MerkleProof = serde_json::from_value(printed).unwrap();
+        assert!(proof.verify());
+        assert_eq!(proof.root, storage.merkle_tree().root());
+        assert!(run_in(dir.path(), &["prove-inclusion", "--transaction-id", "missing"]).is_err());
+    }
+    
+    #[test]
+    fn dry_run_stores_nothing_and_quotes_the_real_fee() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["alice", "bob"] {
+            run_in(dir.path(), &["create-wallet", "--name", name]).unwrap();
+        }
+        run_in(dir.path(), &["fund", "--wallet", "alice", "--amount", "1", "--shielded"]).unwrap();
+        let funded = StorageData::load_from(dir.path()).unwrap().transactions_by_time().len();
+        let before = run_json(dir.path(), &["balance", "--wallet", "alice"]);
+        
+        let send = ["create-transaction", "--from", "alice", "--to", "bob", "--amount", "0.25", "--shielded"];
+        let simulation = run_json(dir.path(), &[&send[..], &["--dry-run"]].concat());
+        assert_eq!(StorageData::load_from(dir.path()).unwrap().transactions_by_time().len(), funded);
+        assert_eq!(run_json(dir.path(), &["balance", "--wallet", "alice"]), before);
+        assert_eq!(simulation["balanced"], true);
+        
+        let created = run_json(dir.path(), &send);
+        assert_eq!(simulation["fee"], created["fee"]);
+        assert_eq!(simulation["total_debit"], 250_000 + created["fee"].as_u64().unwrap());
+        assert_eq!(StorageData::load_from(dir.path()).unwrap().transactions_by_time().len(), funded + 1);
+    }
+    
+    #[test]
+    fn create_wallet_rejects_the_legacy_scheme() {
+        let args = ["namada-shielded-demo", "create-wallet", "--name", "alice", "--key-scheme", "legacy"];
+        assert!(Cli::try_parse_from(args).is_err());
+        let args = ["namada-shielded-demo", "create-wallet", "--name", "alice", "--key-scheme", "secp256k1"];
+        assert!(Cli::try_parse_from(args).is_ok());
+    }
+    
+    #[test]
+    fn transactions_only_come_from_stored_wallets() {
+        let dir = tempfile::tempdir().unwrap();
+        run_in(dir.path(), &["create-wallet", "--name", "bob"]).unwrap();
+        let stranger = Wallet::new("stranger").unwrap().address;
+        for shielded in [false, true] {
+            let mut args = vec!["create-transaction", "--from", &stranger, "--to", "bob", "--amount", "0.0001"];
+            if shielded {
+                args.push("--shielded");
+            }
+            assert!(matches!(run_in(dir.path(), &args), Err(ShieldedError::WalletNotFound(from)) if from == stranger));
+        }
+        assert!(StorageData::load_from(dir.path()).unwrap().transactions.is_empty());
+    }
+}
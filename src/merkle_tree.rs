@@ -1,24 +1,195 @@
-use crate::{error::Result, crypto::hash};
+use crate::error::Result;
 use serde::{Deserialize, Serialize};
-use sha2::{Sha256, Digest};
+use crate::crypto::{ct_eq, HashAlgo, Hasher};
 use hex;
 
+/// Root of a tree with no leaves
+pub const EMPTY_ROOT: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Leaf hash left in the slot of a removed leaf, keeping later indices stable
+///
+/// `SHA256("empty_leaf")`, so a tree whose only leaf was removed still differs from
+/// an empty tree, and no leaf data hashes to it under the `leaf:` tag.
+pub const EMPTY_LEAF: &str = "21ee09c78a2421fe40ec73fc21122d4680cdbaeb5d9b64625d63dd35849a0f3a";
+
+/// Domain of trees built without `with_domain`, keeping the original hashes
+const DEFAULT_DOMAIN: &str = "";
+
+/// Proof entry for a level where the node has no sibling and is promoted unchanged
+pub const PROMOTED: &str = "";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerkleTree {
     pub root: String,
     pub height: usize,
     pub leaf_count: usize,
-    pub leaves: Vec<String>,
+    /// Leaf hashes, private so they only change alongside the cached `nodes`
+    leaves: Vec<String>,
+    /// Tag mixed into every leaf and node hash, so deployments don't share roots
+    #[serde(default)]
+    pub domain: String,
+    /// Hash function for leaves and nodes; trees saved before it was recorded are SHA-256
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
+    /// Cached node levels above the leaves, bottom first, ending at the root
+    ///
+    /// Never saved or loaded: `resume` rebuilds them while checking a loaded tree's root.
+    #[serde(skip)]
+    nodes: Vec<Vec<String>>,
+}
+
+/// Self-contained inclusion proof, verifiable without the tree
+///
+/// `leaf` is the leaf hash as stored in the tree; `matches_leaf` checks it against
+/// the leaf data, e.g. a transaction's `merkle_leaf`. `siblings` is as returned by
+/// `MerkleTree::generate_proof`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub root: String,
+    pub leaf_index: usize,
+    pub leaf: String,
+    pub siblings: Vec<String>,
+    /// Domain tag of the tree, empty for the default domain
+    #[serde(default)]
+    pub domain: String,
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
+}
+
+impl MerkleProof {
+    /// Check that `leaf` at `leaf_index` hashes up through `siblings` to `root`
+    pub fn verify(&self) -> bool {
+        MerkleTree::fold_path(self.hashing(), self.leaf.clone(), &self.siblings, self.leaf_index, &self.root)
+    }
+    
+    /// Check that `leaf` is the hash of the given leaf data
+    pub fn matches_leaf(&self, leaf_data: &str) -> bool {
+        ct_eq(&MerkleTree::hash_leaf(self.hashing(), leaf_data), &self.leaf)
+    }
+    
+    fn hashing(&self) -> Hashing<'_> {
+        Hashing { algo: self.hash_algo, domain: &self.domain }
+    }
+}
+
+impl Default for MerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MerkleTree {
     pub fn new() -> Self {
         Self {
-            root: "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
+            root: EMPTY_ROOT.to_string(),
             height: 0,
             leaf_count: 0,
             leaves: Vec::new(),
+            domain: DEFAULT_DOMAIN.to_string(),
+            hash_algo: HashAlgo::default(),
+            nodes: Vec::new(),
+        }
+    }
+    
+    /// Create an empty tree whose hashes are separated by a domain tag
+    pub fn with_domain(tag: &str) -> Self {
+        Self {
+            domain: tag.to_string(),
+            ..Self::new()
+        }
+    }
+    
+    /// Hash this tree's leaves and nodes with another algorithm
+    ///
+    /// Leaves are stored hashed, so only an empty tree can switch.
+    pub fn with_hash_algo(mut self, algo: HashAlgo) -> Result<Self> {
+        if !self.leaves.is_empty() {
+            return Err(crate::error::ShieldedError::MerkleTreeError(
+                "Cannot change the hash algorithm of a tree with leaves".to_string()
+            ));
+        }
+        self.hash_algo = algo;
+        Ok(self)
+    }
+    
+    /// Build a tree from raw leaf data in order, hashing each leaf as `add_leaf` does
+    ///
+    /// No leaves gives `EMPTY_ROOT` and a height of 0.
+    pub fn from_leaves(leaves: &[String]) -> Result<Self> {
+        let leaves: Vec<String> = leaves.iter().map(|leaf| Self::hash_leaf(Hashing::DEFAULT, leaf)).collect();
+        let nodes = Self::build_nodes(Hashing::DEFAULT, &leaves);
+        Ok(Self {
+            root: Self::calculate_root(Hashing::DEFAULT, &leaves)?,
+            height: Self::calculate_height(leaves.len()),
+            leaf_count: leaves.len(),
+            leaves,
+            domain: DEFAULT_DOMAIN.to_string(),
+            hash_algo: HashAlgo::default(),
+            nodes,
+        })
+    }
+    
+    /// Continue a saved tree over `leaves`, appending the ones added since it was saved
+    ///
+    /// Returns `None` if the saved leaves are not the hashes of the first entries of
+    /// `leaves`, or its root is not the one they produce, so the caller can rebuild.
+    pub fn resume(mut self, leaves: &[String]) -> Option<Self> {
+        let saved = self.leaves.len();
+        if self.leaf_count != saved || saved > leaves.len() {
+            return None;
+        }
+        if !self.leaves.iter().zip(leaves).all(|(leaf, data)| *leaf == Self::hash_leaf(self.hashing(), data)) {
+            return None;
+        }
+        
+        self.nodes = Self::build_nodes(self.hashing(), &self.leaves);
+        let root = match (self.nodes.last(), self.leaves.first()) {
+            (Some(level), _) => level[0].clone(),
+            (None, Some(leaf)) => leaf.clone(),
+            (None, None) => EMPTY_ROOT.to_string(),
+        };
+        if root != self.root {
+            return None;
+        }
+        self.height = self.nodes.len();
+        
+        for leaf in &leaves[saved..] {
+            self.add_leaf(leaf).ok()?;
+        }
+        Some(self)
+    }
+    
+    /// Root of the tree built from each prefix of `leaves`, from `EMPTY_ROOT` for
+    /// no leaves up to the root over all of them
+    ///
+    /// Runs in one pass by keeping the roots of the perfect subtrees seen so far.
+    pub fn root_history(leaves: &[String]) -> Vec<String> {
+        let mut roots = vec![EMPTY_ROOT.to_string()];
+        // (leaf count, root) of each perfect subtree, largest and leftmost first
+        let mut frontier: Vec<(usize, String)> = Vec::new();
+        
+        for leaf in leaves {
+            let mut node = (1, Self::hash_leaf(Hashing::DEFAULT, leaf));
+            while let Some((size, left)) = frontier.pop() {
+                if size != node.0 {
+                    frontier.push((size, left));
+                    break;
+                }
+                node = (size * 2, Self::hash_pair(Hashing::DEFAULT, &left, &node.1));
+            }
+            frontier.push(node);
+            
+            // Smaller subtrees on the right are promoted until they pair with a larger one
+            let root = frontier
+                .iter()
+                .rev()
+                .map(|(_, hash)| hash.clone())
+                .reduce(|right, left| Self::hash_pair(Hashing::DEFAULT, &left, &right))
+                .unwrap_or_else(|| EMPTY_ROOT.to_string());
+            roots.push(root);
         }
+        
+        roots
     }
     
     pub fn root(&self) -> String {
@@ -33,20 +204,153 @@ impl MerkleTree {
         self.leaf_count
     }
     
+    /// Leaf hashes in insertion order
+    pub fn leaves(&self) -> &[String] {
+        &self.leaves
+    }
+    
     /// Add a leaf to the Merkle tree
+    ///
+    /// Only the path from the new leaf to the root is rehashed, using the cached
+    /// node levels.
     pub fn add_leaf(&mut self, data: &str) -> Result<()> {
-        let leaf_hash = Self::hash_leaf(data);
-        self.leaves.push(leaf_hash.clone());
+        // Trees saved without node levels carry no cache, so rebuild it once before updating
+        if !self.nodes_in_sync() {
+            self.nodes = Self::build_nodes(self.hashing(), &self.leaves);
+        }
+        
+        self.leaves.push(Self::hash_leaf(self.hashing(), data));
         self.leaf_count += 1;
+        self.update_path(self.leaves.len() - 1);
         
-        // Recalculate the root
-        self.root = Self::calculate_root(&self.leaves)?;
-        self.height = Self::calculate_height(self.leaf_count);
+        Ok(())
+    }
+    
+    /// Replace the data of an existing leaf
+    ///
+    /// Proofs generated before the update no longer verify against the new root;
+    /// regenerate them for any leaf.
+    pub fn update_leaf(&mut self, index: usize, new_data: &str) -> Result<()> {
+        self.set_leaf(index, Self::hash_leaf(self.hashing(), new_data))
+    }
+    
+    /// Clear a leaf to `EMPTY_LEAF`, keeping its slot so other indices are unchanged
+    pub fn remove_leaf(&mut self, index: usize) -> Result<()> {
+        self.set_leaf(index, EMPTY_LEAF.to_string())
+    }
+    
+    fn set_leaf(&mut self, index: usize, leaf_hash: String) -> Result<()> {
+        if index >= self.leaf_count {
+            return Err(crate::error::ShieldedError::MerkleTreeError(
+                "Leaf index out of bounds".to_string()
+            ));
+        }
+        if !self.nodes_in_sync() {
+            self.nodes = Self::build_nodes(self.hashing(), &self.leaves);
+        }
+        
+        self.leaves[index] = leaf_hash;
+        self.update_path(index);
         
         Ok(())
     }
     
+    /// Rehash the path from leaf `index` to the root in the cached node levels
+    ///
+    /// Levels grow as needed when the leaf was just appended.
+    fn update_path(&mut self, mut index: usize) {
+        let mut depth = 0;
+        loop {
+            let level = if depth == 0 { &self.leaves } else { &self.nodes[depth - 1] };
+            if level.len() <= 1 {
+                break;
+            }
+            // The last node of an odd-sized level has no sibling and is promoted unchanged
+            let parent = if !index.is_multiple_of(2) {
+                Self::hash_pair(self.hashing(), &level[index - 1], &level[index])
+            } else if let Some(right) = level.get(index + 1) {
+                Self::hash_pair(self.hashing(), &level[index], right)
+            } else {
+                level[index].clone()
+            };
+            
+            if self.nodes.len() == depth {
+                self.nodes.push(Vec::new());
+            }
+            let parent_level = &mut self.nodes[depth];
+            index /= 2;
+            if index < parent_level.len() {
+                parent_level[index] = parent;
+            } else {
+                parent_level.push(parent);
+            }
+            depth += 1;
+        }
+        
+        self.root = self
+            .nodes
+            .last()
+            .map_or_else(|| self.leaves[0].clone(), |level| level[0].clone());
+        self.height = self.nodes.len();
+    }
+    
+    /// All node hashes level by level, from the leaves up to a single root entry
+    ///
+    /// Each level holds half (rounded up) the nodes of the one below it. An empty
+    /// tree has no levels.
+    pub fn levels(&self) -> Vec<Vec<String>> {
+        if self.leaves.is_empty() {
+            return Vec::new();
+        }
+        
+        let nodes = if self.nodes_in_sync() {
+            self.nodes.clone()
+        } else {
+            Self::build_nodes(self.hashing(), &self.leaves)
+        };
+        let mut levels = vec![self.leaves.clone()];
+        levels.extend(nodes);
+        
+        levels
+    }
+    
+    /// Whether the cached node levels match the current leaves
+    ///
+    /// Comparing sizes is enough because `leaves` only changes through methods that
+    /// update `nodes` with it, and `nodes` is never deserialized.
+    fn nodes_in_sync(&self) -> bool {
+        self.nodes.len() == Self::calculate_height(self.leaves.len())
+            && self.nodes.first().is_none_or(|level| level.len() == self.leaves.len().div_ceil(2))
+    }
+    
+    /// Every node level above `leaves`, bottom first, ending at the root
+    fn build_nodes(hashing: Hashing, leaves: &[String]) -> Vec<Vec<String>> {
+        let mut nodes: Vec<Vec<String>> = Vec::new();
+        let mut current_level = leaves;
+        while current_level.len() > 1 {
+            nodes.push(Self::hash_level(hashing, current_level));
+            current_level = &nodes[nodes.len() - 1];
+        }
+        nodes
+    }
+    
+    /// Indices of every leaf holding `data`, in ascending order
+    ///
+    /// Identical leaves are kept as separate entries, each with its own proof by index.
+    pub fn leaf_indices(&self, data: &str) -> Vec<usize> {
+        let leaf_hash = Self::hash_leaf(self.hashing(), data);
+        self.leaves
+            .iter()
+            .enumerate()
+            .filter(|(_, leaf)| **leaf == leaf_hash)
+            .map(|(index, _)| index)
+            .collect()
+    }
+    
     /// Generate a Merkle proof for a leaf
+    ///
+    /// The proof has one entry per level; a level where the node is the odd one
+    /// out and promoted unchanged holds `PROMOTED` instead of a sibling hash.
     pub fn generate_proof(&self, leaf_index: usize) -> Result<Vec<String>> {
         if leaf_index >= self.leaf_count {
             return Err(crate::error::ShieldedError::MerkleTreeError(
@@ -54,93 +358,142 @@ impl MerkleTree {
             ));
         }
         
+        let levels = self.levels();
         let mut proof = Vec::new();
         let mut current_index = leaf_index;
-        let mut current_level = self.leaves.clone();
         
-        while current_level.len() > 1 {
-            let sibling_index = if current_index % 2 == 0 {
+        for current_level in &levels[..levels.len() - 1] {
+            let sibling_index = if current_index.is_multiple_of(2) {
                 current_index + 1
             } else {
                 current_index - 1
             };
             
-            if sibling_index < current_level.len() {
-                proof.push(current_level[sibling_index].clone());
-            }
+            proof.push(
+                current_level
+                    .get(sibling_index)
+                    .cloned()
+                    .unwrap_or_else(|| PROMOTED.to_string())
+            );
             
             // Move to parent level
             current_index /= 2;
-            current_level = Self::hash_level(&current_level)?;
         }
         
         Ok(proof)
     }
     
+    /// Bundle the proof for a leaf with its hash, index and the current root
+    pub fn inclusion_proof(&self, leaf_index: usize) -> Result<MerkleProof> {
+        let siblings = self.generate_proof(leaf_index)?;
+        Ok(MerkleProof {
+            root: self.root.clone(),
+            leaf_index,
+            leaf: self.leaves[leaf_index].clone(),
+            siblings,
+            domain: self.domain.clone(),
+            hash_algo: self.hash_algo,
+        })
+    }
+    
     /// Verify a Merkle proof
     pub fn verify_proof(&self, leaf_data: &str, proof: &[String], leaf_index: usize) -> Result<bool> {
-        let leaf_hash = Self::hash_leaf(leaf_data);
+        Self::verify_path(self.hashing(), leaf_data, proof, leaf_index, &self.root)
+    }
+    
+    /// Verify a Merkle proof against a trusted root, without the rest of the tree
+    ///
+    /// `domain` and `hash_algo` must be those of the tree the proof came from: an
+    /// empty domain and SHA-256 for a tree built with `new`. A `MerkleProof` carries
+    /// both, so `MerkleProof::verify` needs neither.
+    pub fn verify_proof_against_root(
+        leaf_data: &str,
+        proof: &[String],
+        leaf_index: usize,
+        expected_root: &str,
+        domain: &str,
+        hash_algo: HashAlgo,
+    ) -> Result<bool> {
+        Self::verify_path(Hashing { algo: hash_algo, domain }, leaf_data, proof, leaf_index, expected_root)
+    }
+    
+    fn verify_path(hashing: Hashing, leaf_data: &str, proof: &[String], leaf_index: usize, expected_root: &str) -> Result<bool> {
+        Ok(Self::fold_path(hashing, Self::hash_leaf(hashing, leaf_data), proof, leaf_index, expected_root))
+    }
+    
+    /// Hash a leaf hash up through its proof and compare the result with the root
+    fn fold_path(hashing: Hashing, leaf_hash: String, proof: &[String], leaf_index: usize, expected_root: &str) -> bool {
         let mut current_hash = leaf_hash;
         let mut current_index = leaf_index;
         
         for sibling_hash in proof {
-            let parent_hash = if current_index % 2 == 0 {
+            let parent_hash = if sibling_hash == PROMOTED {
+                // Only the last, left-hand node of a level can lack a sibling
+                if !current_index.is_multiple_of(2) {
+                    return false;
+                }
+                current_hash
+            } else if current_index.is_multiple_of(2) {
                 // Current is left child
-                Self::hash_pair(&current_hash, sibling_hash)?
+                Self::hash_pair(hashing, &current_hash, sibling_hash)
             } else {
                 // Current is right child
-                Self::hash_pair(sibling_hash, &current_hash)?
+                Self::hash_pair(hashing, sibling_hash, &current_hash)
             };
             
             current_hash = parent_hash;
             current_index /= 2;
         }
         
-        Ok(current_hash == self.root)
+        current_index == 0 && ct_eq(&current_hash, expected_root)
+    }
+    
+    fn hashing(&self) -> Hashing<'_> {
+        Hashing { algo: self.hash_algo, domain: &self.domain }
     }
     
     /// Hash a leaf node
-    fn hash_leaf(data: &str) -> String {
-        let mut hasher = Sha256::new();
+    fn hash_leaf(hashing: Hashing, data: &str) -> String {
+        let mut hasher = hashing.hasher();
         hasher.update(b"leaf:");
         hasher.update(data.as_bytes());
         hex::encode(hasher.finalize())
     }
     
     /// Hash a pair of nodes
-    fn hash_pair(left: &str, right: &str) -> Result<String> {
-        let mut hasher = Sha256::new();
+    fn hash_pair(hashing: Hashing, left: &str, right: &str) -> String {
+        let mut hasher = hashing.hasher();
         hasher.update(b"node:");
         hasher.update(left.as_bytes());
         hasher.update(right.as_bytes());
-        Ok(hex::encode(hasher.finalize()))
+        hex::encode(hasher.finalize())
     }
     
     /// Hash a level of the tree
-    fn hash_level(level: &[String]) -> Result<Vec<String>> {
+    fn hash_level(hashing: Hashing, level: &[String]) -> Vec<String> {
         let mut next_level = Vec::new();
         
         for i in (0..level.len()).step_by(2) {
             if i + 1 < level.len() {
-                next_level.push(Self::hash_pair(&level[i], &level[i + 1])?);
+                next_level.push(Self::hash_pair(hashing, &level[i], &level[i + 1]));
             } else {
                 next_level.push(level[i].clone());
             }
         }
         
-        Ok(next_level)
+        next_level
     }
     
     /// Calculate the root hash from leaves
-    fn calculate_root(leaves: &[String]) -> Result<String> {
+    fn calculate_root(hashing: Hashing, leaves: &[String]) -> Result<String> {
         if leaves.is_empty() {
-            return Ok("0000000000000000000000000000000000000000000000000000000000000000".to_string());
+            return Ok(EMPTY_ROOT.to_string());
         }
         
         let mut current_level = leaves.to_vec();
         
         while current_level.len() > 1 {
-            current_level = Self::hash_level(&current_level)?;
+            current_level = Self::hash_level(hashing, &current_level);
         }
         
         Ok(current_level[0].clone())
@@ -156,10 +509,229 @@ impl MerkleTree {
         let mut nodes = leaf_count;
         
         while nodes > 1 {
-            nodes = (nodes + 1) / 2;
+            nodes = nodes.div_ceil(2);
             height += 1;
         }
         
         height
     }
 }
+
+/// Algorithm and domain tag a tree hashes its leaves and nodes with
+#[derive(Clone, Copy)]
+struct Hashing<'a> {
+    algo: HashAlgo,
+    domain: &'a str,
+}
+
+impl Hashing<'_> {
+    /// SHA-256 without a domain tag, the hashing of trees from older versions
+    const DEFAULT: Hashing<'static> = Hashing { algo: HashAlgo::Sha256, domain: DEFAULT_DOMAIN };
+    
+    /// Hasher with the domain tag absorbed; the default domain adds nothing
+    fn hasher(self) -> Hasher {
+        let mut hasher = self.algo.hasher();
+        if !self.domain.is_empty() {
+            // Length-prefixed so no tag is a prefix of another
+            hasher.update(format!("{}:{}:", self.domain.len(), self.domain).as_bytes());
+        }
+        hasher
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn leaves() -> Vec<String> {
+        (0..5).map(|index| format!("leaf {}", index)).collect()
+    }
+    
+    #[test]
+    fn proof_verifies_against_the_root_alone() {
+        let tree = MerkleTree::from_leaves(&leaves()).unwrap();
+        let proof = tree.generate_proof(3).unwrap();
+        
+        assert!(MerkleTree::verify_proof_against_root("leaf 3", &proof, 3, &tree.root, "", HashAlgo::Sha256).unwrap());
+        assert!(!MerkleTree::verify_proof_against_root("leaf 2", &proof, 3, &tree.root, "", HashAlgo::Sha256).unwrap());
+        assert!(!MerkleTree::verify_proof_against_root("leaf 3", &proof, 3, EMPTY_ROOT, "", HashAlgo::Sha256).unwrap());
+    }
+    
+    #[test]
+    fn proof_verifies_against_the_root_with_the_tree_hashing() {
+        let mut tree = MerkleTree::with_domain("testnet").with_hash_algo(HashAlgo::Blake2b).unwrap();
+        for leaf in leaves() {
+            tree.add_leaf(&leaf).unwrap();
+        }
+        let proof = tree.generate_proof(1).unwrap();
+        
+        assert!(MerkleTree::verify_proof_against_root("leaf 1", &proof, 1, &tree.root, "testnet", HashAlgo::Blake2b).unwrap());
+        assert!(!MerkleTree::verify_proof_against_root("leaf 1", &proof, 1, &tree.root, "", HashAlgo::Blake2b).unwrap());
+        assert!(!MerkleTree::verify_proof_against_root("leaf 1", &proof, 1, &tree.root, "testnet", HashAlgo::Sha256).unwrap());
+        assert!(tree.inclusion_proof(1).unwrap().verify());
+    }
+    
+    #[test]
+    fn updated_leaf_invalidates_old_proofs() {
+        let mut tree = MerkleTree::from_leaves(&leaves()).unwrap();
+        let old_proof = tree.inclusion_proof(2).unwrap();
+        tree.update_leaf(2, "replaced").unwrap();
+        
+        assert!(!tree.verify_proof("leaf 2", &old_proof.siblings, 2).unwrap());
+        assert!(tree.verify_proof("replaced", &tree.generate_proof(2).unwrap(), 2).unwrap());
+        assert!(tree.verify_proof("leaf 4", &tree.generate_proof(4).unwrap(), 4).unwrap());
+        assert_eq!(tree.root, MerkleTree::from_leaves(&["leaf 0", "leaf 1", "replaced", "leaf 3", "leaf 4"].map(String::from)).unwrap().root);
+    }
+    
+    #[test]
+    fn removed_leaf_keeps_indices_and_differs_from_an_empty_tree() {
+        assert_ne!(EMPTY_LEAF, EMPTY_ROOT);
+        
+        let mut tree = MerkleTree::from_leaves(&leaves()).unwrap();
+        let (height, leaf_count) = (tree.height, tree.leaf_count);
+        tree.remove_leaf(1).unwrap();
+        assert_eq!((tree.height, tree.leaf_count), (height, leaf_count));
+        assert_eq!(tree.leaves[1], EMPTY_LEAF);
+        assert!(tree.verify_proof("leaf 3", &tree.generate_proof(3).unwrap(), 3).unwrap());
+        
+        let mut single = MerkleTree::from_leaves(&["only".to_string()]).unwrap();
+        single.remove_leaf(0).unwrap();
+        assert_ne!(single.root, EMPTY_ROOT);
+    }
+    
+    #[test]
+    fn resumed_tree_appends_later_leaves_and_refuses_other_ones() {
+        let all = leaves();
+        let saved = MerkleTree::from_leaves(&all[..3]).unwrap();
+        
+        let resumed = saved.clone().resume(&all).unwrap();
+        assert_eq!(resumed.root(), MerkleTree::from_leaves(&all).unwrap().root());
+        assert!(resumed.inclusion_proof(4).unwrap().verify());
+        
+        let mut changed = all.clone();
+        changed[1] = "other".to_string();
+        assert!(saved.clone().resume(&changed).is_none());
+        assert!(saved.clone().resume(&all[..2]).is_none());
+        let mut forged = saved;
+        forged.root = EMPTY_ROOT.to_string();
+        assert!(forged.resume(&all).is_none());
+    }
+    
+    #[test]
+    fn incremental_root_matches_a_full_rebuild() {
+        use rand::Rng;
+        
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let count = rng.gen_range(0..40);
+            let data: Vec<String> = (0..count).map(|_| format!("leaf {}", rng.gen::<u64>())).collect();
+            let mut tree = MerkleTree::new();
+            for (inserted, leaf) in data.iter().enumerate() {
+                tree.add_leaf(leaf).unwrap();
+                let expected = MerkleTree::calculate_root(Hashing::DEFAULT, &tree.leaves).unwrap();
+                assert_eq!(tree.root(), expected);
+                assert_eq!(tree.leaf_count(), inserted + 1);
+                assert_eq!(tree.height(), MerkleTree::calculate_height(inserted + 1));
+            }
+            assert_eq!(tree.levels(), MerkleTree::from_leaves(&data).unwrap().levels());
+        }
+    }
+    
+    #[test]
+    fn node_levels_in_a_saved_tree_are_ignored() {
+        let tree = MerkleTree::from_leaves(&leaves()).unwrap();
+        let mut saved = serde_json::to_value(&tree).unwrap();
+        assert!(saved.get("nodes").is_none());
+        saved["nodes"] = serde_json::json!([["stale"], ["stale"]]);
+        
+        let mut loaded: MerkleTree = serde_json::from_value(saved).unwrap();
+        loaded.add_leaf("leaf 5").unwrap();
+        let mut expected = tree;
+        expected.add_leaf("leaf 5").unwrap();
+        assert_eq!(loaded.root(), expected.root());
+        assert_eq!(loaded.levels(), expected.levels());
+    }
+    
+    #[test]
+    fn levels_halve_up_to_the_root() {
+        for count in 1..=9 {
+            let leaves: Vec<String> = (0..count).map(|index| format!("leaf {}", index)).collect();
+            let tree = MerkleTree::from_leaves(&leaves).unwrap();
+            let levels = tree.levels();
+            
+            assert_eq!(levels.first().unwrap().len(), count);
+            assert_eq!(levels.last().unwrap(), &vec![tree.root()]);
+            for pair in levels.windows(2) {
+                assert_eq!(pair[1].len(), pair[0].len().div_ceil(2));
+            }
+        }
+        assert!(MerkleTree::new().levels().is_empty());
+    }
+    
+    #[test]
+    fn duplicate_leaves_each_prove_and_are_all_found_in_order() {
+        let leaves: Vec<String> = ["same", "other", "same", "same", "last"].iter().map(|leaf| leaf.to_string()).collect();
+        let tree = MerkleTree::from_leaves(&leaves).unwrap();
+        
+        let indices = tree.leaf_indices("same");
+        assert_eq!(indices, vec![0, 2, 3]);
+        assert_eq!(tree.leaf_indices("same"), indices);
+        for index in indices {
+            let proof = tree.generate_proof(index).unwrap();
+            assert!(tree.verify_proof("same", &proof, index).unwrap());
+        }
+        assert!(tree.leaf_indices("missing").is_empty());
+    }
+    
+    #[test]
+    fn every_leaf_proves_for_one_to_eight_leaves() {
+        for count in 1..=8 {
+            let leaves: Vec<String> = (0..count).map(|index| format!("leaf {}", index)).collect();
+            let tree = MerkleTree::from_leaves(&leaves).unwrap();
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = tree.generate_proof(index).unwrap();
+                assert!(tree.verify_proof(leaf, &proof, index).unwrap(), "leaf {} of {}", index, count);
+                assert!(!tree.verify_proof("other", &proof, index).unwrap());
+            }
+        }
+    }
+    
+    #[test]
+    fn different_domains_give_different_roots_for_the_same_leaves() {
+        let mut first = MerkleTree::with_domain("deployment-a");
+        let mut second = MerkleTree::with_domain("deployment-b");
+        let mut same = MerkleTree::with_domain("deployment-a");
+        for leaf in leaves() {
+            first.add_leaf(&leaf).unwrap();
+            second.add_leaf(&leaf).unwrap();
+            same.add_leaf(&leaf).unwrap();
+        }
+        
+        assert_ne!(first.root(), second.root());
+        assert_eq!(first.root(), same.root());
+        let proof = first.generate_proof(2).unwrap();
+        assert!(first.verify_proof("leaf 2", &proof, 2).unwrap());
+        assert!(!second.verify_proof("leaf 2", &proof, 2).unwrap());
+        
+        let reloaded: MerkleTree = serde_json::from_str(&serde_json::to_string(&first).unwrap()).unwrap();
+        assert_eq!(reloaded.root(), first.root());
+        assert!(reloaded.verify_proof("leaf 2", &proof, 2).unwrap());
+    }
+    
+    #[test]
+    fn serialized_proof_round_trips_and_verifies_standalone() {
+        let tree = MerkleTree::from_leaves(&leaves()).unwrap();
+        let json = serde_json::to_string(&tree.inclusion_proof(2).unwrap()).unwrap();
+        drop(tree);
+        
+        let proof: MerkleProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(proof.leaf_index, 2);
+        assert!(proof.matches_leaf("leaf 2"));
+        assert!(!proof.matches_leaf("leaf 3"));
+        assert!(proof.verify());
+        
+        let mut tampered: serde_json::Value = serde_json::from_str(&json).unwrap();
+        tampered["leaf_index"] = serde_json::json!(3);
+        assert!(!serde_json::from_value::<MerkleProof>(tampered).unwrap().verify());
+    }
+}
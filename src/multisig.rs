@@ -0,0 +1,168 @@
+use crate::{
+    error::Result,
+    crypto::{verify_signature_with, KeyScheme},
+    shielded_transaction::ShieldedTransaction,
+    wallet::Wallet,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One signer's signature and the scheme it was made with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignerSignature {
+    pub scheme: KeyScheme,
+    pub signature: String,
+}
+
+/// A transaction with signatures from at least `threshold` of its `signers`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultisigTransaction {
+    pub transaction: ShieldedTransaction,
+    pub threshold: usize,
+    /// Public keys allowed to sign
+    pub signers: Vec<String>,
+    /// Signatures keyed by signer public key
+    pub signatures: BTreeMap<String, SignerSignature>,
+}
+
+impl MultisigTransaction {
+    /// Count the signatures that come from a listed signer and verify
+    pub fn valid_signature_count(&self) -> usize {
+        let message = self.transaction.signing_message();
+        self.signatures
+            .iter()
+            .filter(|(public_key, _)| self.signers.contains(public_key))
+            .filter(|(public_key, signed)| {
+                verify_signature_with(signed.scheme, &message, &signed.signature, public_key).unwrap_or(false)
+            })
+            .count()
+    }
+    
+    /// Check that at least `threshold` listed signers produced valid signatures
+    pub fn is_valid(&self) -> bool {
+        self.threshold > 0 && self.valid_signature_count() >= self.threshold
+    }
+}
+
+/// Signatures collected so far for a multisig transaction
+///
+/// Serialize it to hand to the next signer; `finalize` succeeds once enough
+/// signatures are gathered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PartiallySignedTransaction {
+    inner: MultisigTransaction,
+}
+
+impl PartiallySignedTransaction {
+    /// Start collecting signatures for a transaction
+    pub fn new(transaction: ShieldedTransaction, signers: Vec<String>, threshold: usize) -> Result<Self> {
+        if threshold == 0 || threshold > signers.len() {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Threshold {} is not between 1 and {} signers", threshold, signers.len())
+            ));
+        }
+        let mut unique = signers.clone();
+        unique.sort();
+        unique.dedup();
+        if unique.len() != signers.len() {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "Multisig signers must be distinct".to_string()
+            ));
+        }
+        
+        Ok(Self {
+            inner: MultisigTransaction {
+                transaction,
+                threshold,
+                signers,
+                signatures: BTreeMap::new(),
+            },
+        })
+    }
+    
+    /// Add a signature from one of the listed signers, replacing any earlier one from it
+    pub fn sign(&mut self, wallet: &Wallet) -> Result<()> {
+        if !self.inner.signers.contains(&wallet.public_key) {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Wallet {} is not a signer of this transaction", wallet.name)
+            ));
+        }
+        let signature = wallet.sign_message(&self.inner.transaction.signing_message())?;
+        self.inner.signatures.insert(
+            wallet.public_key.clone(),
+            SignerSignature { scheme: wallet.key_scheme, signature },
+        );
+        Ok(())
+    }
+    
+    /// The transaction being signed
+    pub fn transaction(&self) -> &ShieldedTransaction {
+        &self.inner.transaction
+    }
+    
+    /// Signatures required to finalize
+    pub fn threshold(&self) -> usize {
+        self.inner.threshold
+    }
+    
+    /// Number of valid signatures gathered so far
+    pub fn signature_count(&self) -> usize {
+        self.inner.valid_signature_count()
+    }
+    
+    /// Check whether enough signatures have been gathered to finalize
+    pub fn is_complete(&self) -> bool {
+        self.inner.is_valid()
+    }
+    
+    /// Produce the multisig transaction, failing until the threshold is met
+    pub fn finalize(self) -> Result<MultisigTransaction> {
+        let count = self.signature_count();
+        if count < self.inner.threshold {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Only {} of {} required signatures collected", count, self.inner.threshold)
+            ));
+        }
+        Ok(self.inner)
+    }
+    
+    /// Convert to JSON to hand to the next signer
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(crate::error::ShieldedError::SerializationError)
+    }
+    
+    /// Load a partially signed transaction passed on by another signer
+    pub fn from_json(json: &str) -> Result<Self> {
+        let partial: Self = serde_json::from_str(json)
+            .map_err(crate::error::ShieldedError::SerializationError)?;
+        partial.inner.transaction.validate()?;
+        Ok(partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn two_of_three_signatures_collected_across_reloads_finalize() {
+        let wallets: Vec<Wallet> = ["alice", "bob", "carol"].iter().map(|name| Wallet::new(name).unwrap()).collect();
+        let signers = wallets.iter().map(|wallet| wallet.public_key.clone()).collect();
+        let transaction = ShieldedTransaction::create_public(&wallets[0], &wallets[1].address, 1_000).unwrap();
+        
+        let mut partial = PartiallySignedTransaction::new(transaction, signers, 2).unwrap();
+        partial.sign(&wallets[0]).unwrap();
+        let handed_on = partial.to_json().unwrap();
+        assert!(partial.finalize().is_err());
+        
+        let mut reloaded = PartiallySignedTransaction::from_json(&handed_on).unwrap();
+        assert_eq!(reloaded.signature_count(), 1);
+        reloaded.sign(&wallets[2]).unwrap();
+        
+        let multisig = reloaded.finalize().unwrap();
+        assert_eq!(multisig.valid_signature_count(), 2);
+        assert!(multisig.is_valid());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{
+    error::Result,
+    asset::AssetId,
+    commitment::CommitmentScheme,
+    crypto::{ct_eq, decrypt_with_viewing_key, derive_nullifier, encrypt_to_viewing_key, generate_nonce},
+};
+use serde::{Deserialize, Serialize};
+
+/// A shielded output together with the opening of its commitment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub value: u64,
+    pub nonce: [u8; 32],
+    pub owner: String,
+    pub commitment: String,
+    /// Asset the value is denominated in
+    #[serde(default)]
+    pub asset: AssetId,
+}
+
+/// A note encrypted to its owner's viewing key, stored alongside the output commitment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct EncryptedNote {
+    pub commitment: String,
+    pub ephemeral_key: String,
+    pub ciphertext: String,
+}
+
+impl Note {
+    /// Create a note with a fresh commitment nonce
+    pub fn new(value: u64, owner: &str) -> Result<Self> {
+        Self::with_nonce(value, owner, generate_nonce())
+    }
+    
+    /// Create a note committed with a given nonce
+    pub fn with_nonce(value: u64, owner: &str, nonce: [u8; 32]) -> Result<Self> {
+        Self::with_asset_and_nonce(AssetId::native(), value, owner, nonce)
+    }
+    
+    /// Create a note of a given asset with a fresh commitment nonce
+    pub fn with_asset(asset: AssetId, value: u64, owner: &str) -> Result<Self> {
+        Self::with_asset_and_nonce(asset, value, owner, generate_nonce())
+    }
+    
+    /// Create a note of a given asset committed with a given nonce
+    pub fn with_asset_and_nonce(asset: AssetId, value: u64, owner: &str, nonce: [u8; 32]) -> Result<Self> {
+        let commitment = CommitmentScheme::create_asset_commitment(&asset, value, &nonce)?;
+        
+        Ok(Self {
+            value,
+            nonce,
+            owner: owner.to_string(),
+            commitment: commitment.commitment_hash,
+            asset,
+        })
+    }
+    
+    /// A zero-value note, such as empty change; it carries nothing to spend
+    pub fn is_empty(&self) -> bool {
+        self.value == 0
+    }
+    
+    /// Check that the commitment actually opens to this note's value and nonce
+    pub fn is_consistent(&self) -> bool {
+        CommitmentScheme::create_asset_commitment(&self.asset, self.value, &self.nonce)
+            .map(|commitment| ct_eq(&commitment.commitment_hash, &self.commitment))
+            .unwrap_or(false)
+    }
+    
+    /// Nullifier revealed when this note is spent as an input of a multi-note transaction
+    pub fn nullifier(&self) -> String {
+        derive_nullifier(&hex::encode(self.nonce), &self.commitment)
+    }
+    
+    /// Encrypt the note to the owner's viewing public key
+    pub fn encrypt(&self, viewing_public_key: &str) -> Result<EncryptedNote> {
+        let plaintext = serde_json::to_vec(self)?;
+        let (ephemeral_key, ciphertext) = encrypt_to_viewing_key(viewing_public_key, &plaintext)?;
+        
+        Ok(EncryptedNote {
+            commitment: self.commitment.clone(),
+            ephemeral_key,
+            ciphertext,
+        })
+    }
+}
+
+impl EncryptedNote {
+    /// Decrypt with a viewing key, or `None` if the note belongs to someone else
+    pub fn decrypt(&self, viewing_key: &str) -> Option<Note> {
+        let plaintext = decrypt_with_viewing_key(viewing_key, &self.ephemeral_key, &self.ciphertext)?;
+        let note: Note = serde_json::from_slice(&plaintext).ok()?;
+        
+        // A note that doesn't open the commitment it is attached to is worthless
+        (note.commitment == self.commitment && note.is_consistent()).then_some(note)
+    }
+}
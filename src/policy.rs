@@ -0,0 +1,182 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Basis points in one whole (100%)
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// How a proportional fee is rounded to a whole number of micro-units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeRounding {
+    /// Round down; may underpay the exact proportional fee
+    #[default]
+    Floor,
+    /// Round up; never underpays
+    Ceil,
+    /// Round to nearest, halves up
+    Round,
+}
+
+impl FromStr for FeeRounding {
+    type Err = crate::error::ShieldedError;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "floor" => Ok(FeeRounding::Floor),
+            "ceil" => Ok(FeeRounding::Ceil),
+            "round" => Ok(FeeRounding::Round),
+            other => Err(crate::error::ShieldedError::InvalidAmount(
+                format!("Unknown fee rounding '{}', expected floor, ceil or round", other)
+            )),
+        }
+    }
+}
+
+/// How the fee for a transaction is computed from its amount or size
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeePolicy {
+    /// `amount * bps / 10_000`, rounded as configured and never below `min`
+    Proportional { bps: u64, min: u64, rounding: FeeRounding },
+    /// The same fee for every transaction; `Flat(0)` suits zero-fee test networks
+    Flat(u64),
+    /// This many micro-units per byte of the encoded transaction
+    PerByte(u64),
+}
+
+impl Default for FeePolicy {
+    /// 0.1% with a minimum of 1, rounded down
+    fn default() -> Self {
+        FeePolicy::Proportional { bps: 10, min: 1, rounding: FeeRounding::Floor }
+    }
+}
+
+impl FeePolicy {
+    /// Compute the fee for an amount
+    ///
+    /// `PerByte` needs the transaction size and charges nothing here; see `fee_for`.
+    pub fn fee(&self, amount: u64) -> u64 {
+        self.fee_for(amount, 0)
+    }
+    
+    /// Compute the fee for a transaction of `size` encoded bytes sending `amount`
+    pub fn fee_for(&self, amount: u64, size: usize) -> u64 {
+        match *self {
+            FeePolicy::Proportional { bps, min, rounding } => {
+                let scaled = amount as u128 * bps as u128;
+                let fee = match rounding {
+                    FeeRounding::Floor => scaled / BPS_DENOMINATOR,
+                    FeeRounding::Ceil => scaled.div_ceil(BPS_DENOMINATOR),
+                    FeeRounding::Round => (scaled + BPS_DENOMINATOR / 2) / BPS_DENOMINATOR,
+                };
+                u64::try_from(fee).unwrap_or(u64::MAX).max(min)
+            }
+            FeePolicy::Flat(fee) => fee,
+            FeePolicy::PerByte(rate) => rate.saturating_mul(size as u64),
+        }
+    }
+}
+
+/// Limits applied when creating and storing transactions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionPolicy {
+    /// Smallest value accepted for a non-change output (0 disables the check)
+    pub dust_limit: u64,
+    /// Fee charged on shielded transactions created under this policy; `PerByte`
+    /// is not supported, since commitments are fixed before the size is known
+    #[serde(default)]
+    pub fee_policy: FeePolicy,
+    /// Largest amount a single transaction may send (`None` is unlimited)
+    #[serde(default)]
+    pub max_amount: Option<u64>,
+    /// Largest total a sender may send per UTC day (`None` is unlimited)
+    #[serde(default)]
+    pub daily_limit: Option<u64>,
+    /// Leading zero bits a transaction's proof-of-work hash needs (0 disables the check)
+    #[serde(default)]
+    pub pow_difficulty: u32,
+}
+
+impl TransactionPolicy {
+    /// Check whether an output value falls below the dust limit
+    pub fn is_dust(&self, value: u64) -> bool {
+        value < self.dust_limit
+    }
+    
+    /// Reject a transaction amount above the per-transaction cap
+    pub fn check_amount(&self, amount: u64) -> Result<()> {
+        match self.max_amount {
+            Some(max_amount) if amount > max_amount => Err(crate::error::ShieldedError::InvalidAmount(
+                format!("Amount {} exceeds the per-transaction cap of {}", amount, max_amount)
+            )),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Reject an amount that would take a sender's total for the day over the daily cap
+    pub fn check_daily(&self, amount: u64, sent_today: u64) -> Result<()> {
+        let Some(daily_limit) = self.daily_limit else {
+            return Ok(());
+        };
+        match sent_today.checked_add(amount) {
+            Some(total) if total <= daily_limit => Ok(()),
+            _ => Err(crate::error::ShieldedError::InvalidAmount(
+                format!("Amount {} on top of {} sent today exceeds the daily cap of {}", amount, sent_today, daily_limit)
+            )),
+        }
+    }
+    
+    /// Reject a non-change output below the dust limit
+    pub fn check_output(&self, value: u64) -> Result<()> {
+        if self.is_dust(value) {
+            return Err(crate::error::ShieldedError::InvalidAmount(
+                format!("Output {} is below the dust limit of {}", value, self.dust_limit)
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn tenth_of_a_percent(rounding: FeeRounding) -> FeePolicy {
+        FeePolicy::Proportional { bps: 10, min: 0, rounding }
+    }
+    
+    #[test]
+    fn proportional_fee_rounds_as_configured() {
+        let cases = [
+            (FeeRounding::Floor, 1, 2),
+            (FeeRounding::Ceil, 2, 3),
+            (FeeRounding::Round, 2, 3),
+        ];
+        for (rounding, fee_for_1999, fee_for_2500) in cases {
+            assert_eq!(tenth_of_a_percent(rounding).fee(1999), fee_for_1999, "{:?}", rounding);
+            assert_eq!(tenth_of_a_percent(rounding).fee(2500), fee_for_2500, "{:?}", rounding);
+        }
+        // Exact fees are the same under every mode
+        for rounding in [FeeRounding::Floor, FeeRounding::Ceil, FeeRounding::Round] {
+            assert_eq!(tenth_of_a_percent(rounding).fee(3000), 3);
+        }
+    }
+    
+    #[test]
+    fn each_fee_policy_charges_as_documented() {
+        assert_eq!(FeePolicy::Flat(25).fee(1), 25);
+        assert_eq!(FeePolicy::Flat(0).fee(1_000_000), 0);
+        
+        // The default 0.1% floors, with a minimum of 1
+        let default = FeePolicy::default();
+        assert_eq!(default.fee(0), 1);
+        assert_eq!(default.fee(999), 1);
+        assert_eq!(default.fee(1_999), 1);
+        assert_eq!(default.fee(2_000), 2);
+        assert_eq!(FeePolicy::Proportional { bps: 10, min: 5, rounding: FeeRounding::Floor }.fee(2_000), 5);
+        assert_eq!(FeePolicy::Proportional { bps: 10_000, min: 0, rounding: FeeRounding::Floor }.fee(u64::MAX), u64::MAX);
+        
+        assert_eq!(FeePolicy::PerByte(3).fee_for(1_000, 200), 600);
+        assert_eq!(FeePolicy::PerByte(3).fee(1_000), 0);
+        assert_eq!(FeePolicy::PerByte(u64::MAX).fee_for(1_000, 2), u64::MAX);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{error::Result, zk_proof::ZeroKnowledgeProof};
+
+/// Public statement a shielded transaction's proof is made for
+#[derive(Debug, Clone, Copy)]
+pub struct ProofStatement<'a> {
+    pub transaction_id: &'a str,
+    pub input_commitments: &'a [String],
+    pub output_commitments: &'a [String],
+}
+
+impl ProofStatement<'_> {
+    /// The commitments as proof public inputs, inputs first, each tagged with its side
+    pub fn public_inputs(&self) -> Vec<String> {
+        let inputs = self.input_commitments.iter().map(|commitment| format!("input:{}", commitment));
+        let outputs = self.output_commitments.iter().map(|commitment| format!("output:{}", commitment));
+        inputs.chain(outputs).collect()
+    }
+}
+
+/// A proving backend for shielded transactions
+///
+/// Proofs are opaque strings, stored in `ShieldedTransaction::zk_proof`. `verify`
+/// returns `Ok(false)` for a proof that does not hold, malformed ones included, and
+/// keeps errors for a check that could not be run at all.
+pub trait ProofSystem: Send + Sync {
+    fn generate(&self, statement: &ProofStatement) -> Result<String>;
+    
+    fn verify(&self, proof: &str, statement: &ProofStatement) -> Result<bool>;
+}
+
+/// The hash-based demo proofs, which bind fresh randomness to the transaction id
+/// and the statement's commitments
+///
+/// Used by every `ShieldedTransaction` constructor that takes no proof system.
+/// Verification goes through the global verifier registry.
+pub struct MockProofSystem;
+
+impl ProofSystem for MockProofSystem {
+    fn generate(&self, statement: &ProofStatement) -> Result<String> {
+        ZeroKnowledgeProof::generate(statement.transaction_id, &statement.public_inputs())
+    }
+    
+    fn verify(&self, proof: &str, statement: &ProofStatement) -> Result<bool> {
+        let public_inputs = statement.public_inputs();
+        let Ok(proof) = ZeroKnowledgeProof::decode(statement.transaction_id, &public_inputs, proof) else {
+            return Ok(false);
+        };
+        proof.verify_for(statement.transaction_id, &public_inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn statement<'a>(transaction_id: &'a str, commitments: &'a [String]) -> ProofStatement<'a> {
+        ProofStatement {
+            transaction_id,
+            input_commitments: &commitments[..1],
+            output_commitments: &commitments[1..],
+        }
+    }
+    
+    #[test]
+    fn proof_for_one_transaction_fails_for_another() {
+        let commitments = vec!["in".to_string(), "out".to_string()];
+        let proof = MockProofSystem.generate(&statement("tx_a", &commitments)).unwrap();
+        
+        assert!(MockProofSystem.verify(&proof, &statement("tx_a", &commitments)).unwrap());
+        assert!(!MockProofSystem.verify(&proof, &statement("tx_b", &commitments)).unwrap());
+    }
+    
+    #[test]
+    fn proof_binds_the_statement_commitments() {
+        let commitments = vec!["in".to_string(), "out".to_string()];
+        let proof = MockProofSystem.generate(&statement("tx_a", &commitments)).unwrap();
+        
+        let swapped_output = vec!["in".to_string(), "other".to_string()];
+        assert!(!MockProofSystem.verify(&proof, &statement("tx_a", &swapped_output)).unwrap());
+        // Moving a commitment from the inputs to the outputs changes the statement too
+        let moved = ProofStatement { transaction_id: "tx_a", input_commitments: &[], output_commitments: &commitments };
+        assert!(!MockProofSystem.verify(&proof, &moved).unwrap());
+    }
+    
+    #[test]
+    fn malformed_proof_does_not_verify() {
+        let commitments = vec!["in".to_string(), "out".to_string()];
+        assert!(!MockProofSystem.verify("not a proof", &statement("tx_a", &commitments)).unwrap());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{error::Result, crypto::ct_eq, zk_proof::{ProofType, ZeroKnowledgeProof}};
+use once_cell::sync::Lazy;
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// A verification backend for one proof type and version
+pub trait ProofVerifier: Send + Sync {
+    fn verify(&self, proof: &ZeroKnowledgeProof) -> Result<bool>;
+    
+    /// Verify several proofs of this verifier's type and version, one result per proof
+    ///
+    /// Checks each proof in turn unless the backend can do better.
+    fn verify_batch(&self, proofs: &[&ZeroKnowledgeProof]) -> Result<Vec<bool>> {
+        proofs.iter().map(|proof| self.verify(proof)).collect()
+    }
+}
+
+/// The simulated verifier used by the demo proofs
+pub struct DemoProofVerifier;
+
+impl ProofVerifier for DemoProofVerifier {
+    fn verify(&self, proof: &ZeroKnowledgeProof) -> Result<bool> {
+        // In a real implementation, this would verify the actual ZK proof
+        // For this demo, we recompute the binding to the transaction and inputs
+        Ok(ct_eq(&proof.proof_data, &ZeroKnowledgeProof::binding(&proof.transaction_id, &proof.proof_id, &proof.public_inputs)))
+    }
+    
+    fn verify_batch(&self, proofs: &[&ZeroKnowledgeProof]) -> Result<Vec<bool>> {
+        // One hasher for the whole batch, reset after each binding
+        let mut hasher = Sha256::new();
+        Ok(proofs
+            .iter()
+            .map(|proof| {
+                let binding = ZeroKnowledgeProof::binding_with(&mut hasher, &proof.transaction_id, &proof.proof_id, &proof.public_inputs);
+                ct_eq(&proof.proof_data, &binding)
+            })
+            .collect())
+    }
+}
+
+/// Maps a `(proof_type, version)` pair to the verifier responsible for it
+pub struct ProofVerifierRegistry {
+    verifiers: HashMap<(ProofType, u32), Box<dyn ProofVerifier>>,
+}
+
+impl Default for ProofVerifierRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+impl ProofVerifierRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            verifiers: HashMap::new(),
+        }
+    }
+    
+    /// Create a registry with the demo verifier registered for every proof type
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        for proof_type in [
+            ProofType::SpendProof,
+            ProofType::OutputProof,
+            ProofType::BalanceProof,
+            ProofType::RangeProof,
+        ] {
+            registry.register(
+                proof_type,
+                ZeroKnowledgeProof::DEFAULT_VERSION,
+                Box::new(DemoProofVerifier),
+            );
+        }
+        registry
+    }
+    
+    /// Register a verifier, returning the one it replaced (if any)
+    pub fn register(
+        &mut self,
+        proof_type: ProofType,
+        version: u32,
+        verifier: Box<dyn ProofVerifier>,
+    ) -> Option<Box<dyn ProofVerifier>> {
+        self.verifiers.insert((proof_type, version), verifier)
+    }
+    
+    /// Check whether a verifier is registered for a proof type and version
+    pub fn is_registered(&self, proof_type: ProofType, version: u32) -> bool {
+        self.verifiers.contains_key(&(proof_type, version))
+    }
+    
+    /// Route a proof to the verifier registered for its type and version
+    pub fn verify(&self, proof: &ZeroKnowledgeProof) -> Result<bool> {
+        self.verifier_for(proof.proof_type, proof.version)?.verify(proof)
+    }
+    
+    /// Verify many proofs, handing each verifier all of its proofs in one batch
+    ///
+    /// Results are in the order of `proofs`. Fails before verifying anything if
+    /// some proof has no registered verifier.
+    pub fn verify_batch(&self, proofs: &[ZeroKnowledgeProof]) -> Result<Vec<bool>> {
+        let mut groups: HashMap<(ProofType, u32), Vec<usize>> = HashMap::new();
+        for (index, proof) in proofs.iter().enumerate() {
+            groups.entry((proof.proof_type, proof.version)).or_default().push(index);
+        }
+        
+        let mut batches = Vec::with_capacity(groups.len());
+        for ((proof_type, version), indices) in groups {
+            batches.push((self.verifier_for(proof_type, version)?, indices));
+        }
+        
+        let mut results = vec![false; proofs.len()];
+        for (verifier, indices) in batches {
+            let batch: Vec<&ZeroKnowledgeProof> = indices.iter().map(|&index| &proofs[index]).collect();
+            for (index, valid) in indices.into_iter().zip(verifier.verify_batch(&batch)?) {
+                results[index] = valid;
+            }
+        }
+        Ok(results)
+    }
+    
+    fn verifier_for(&self, proof_type: ProofType, version: u32) -> Result<&dyn ProofVerifier> {
+        self.verifiers
+            .get(&(proof_type, version))
+            .map(|verifier| verifier.as_ref())
+            .ok_or_else(|| crate::error::ShieldedError::ZKProofError(format!(
+                "No verifier registered for {:?} version {}",
+                proof_type, version
+            )))
+    }
+}
+
+static GLOBAL_REGISTRY: Lazy<RwLock<ProofVerifierRegistry>> =
+    Lazy::new(|| RwLock::new(ProofVerifierRegistry::with_defaults()));
+
+/// Register a verifier in the process-wide registry used by `ZeroKnowledgeProof::verify`
+pub fn register_verifier(proof_type: ProofType, version: u32, verifier: Box<dyn ProofVerifier>) -> Result<()> {
+    let mut registry = GLOBAL_REGISTRY
+        .write()
+        .map_err(|_| crate::error::ShieldedError::ZKProofError("Verifier registry is poisoned".to_string()))?;
+    registry.register(proof_type, version, verifier);
+    Ok(())
+}
+
+/// Verify a proof with the process-wide registry
+pub fn verify_registered(proof: &ZeroKnowledgeProof) -> Result<bool> {
+    let registry = GLOBAL_REGISTRY
+        .read()
+        .map_err(|_| crate::error::ShieldedError::ZKProofError("Verifier registry is poisoned".to_string()))?;
+    registry.verify(proof)
+}
+
+/// Verify a batch of proofs with the process-wide registry
+pub fn verify_batch_registered(proofs: &[ZeroKnowledgeProof]) -> Result<Vec<bool>> {
+    let registry = GLOBAL_REGISTRY
+        .read()
+        .map_err(|_| crate::error::ShieldedError::ZKProofError("Verifier registry is poisoned".to_string()))?;
+    registry.verify_batch(proofs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    
+    /// Accepts every proof, counting how many it was handed
+    struct CountingVerifier(Arc<AtomicUsize>);
+    
+    impl ProofVerifier for CountingVerifier {
+        fn verify(&self, _proof: &ZeroKnowledgeProof) -> Result<bool> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+    }
+    
+    fn spend_proof() -> ZeroKnowledgeProof {
+        ZeroKnowledgeProof::create_spend_proof("tx", &["in".to_string()], &["out".to_string()], "balance").unwrap()
+    }
+    
+    #[test]
+    fn registry_routes_each_proof_to_its_verifier() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry = ProofVerifierRegistry::with_defaults();
+        registry.register(ProofType::SpendProof, 7, Box::new(CountingVerifier(calls.clone())));
+        
+        // The custom verifier accepts a proof the demo verifier would reject
+        let mut tagged = spend_proof().with_version(7);
+        tagged.proof_data = "forged".to_string();
+        assert!(tagged.verify_with(&registry).unwrap());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        
+        assert!(spend_proof().verify_with(&registry).unwrap());
+        let mut forged = spend_proof();
+        forged.proof_data = "forged".to_string();
+        assert!(!forged.verify_with(&registry).unwrap());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+    
+    #[test]
+    fn unregistered_proof_type_is_an_error() {
+        let registry = ProofVerifierRegistry::with_defaults();
+        assert!(!registry.is_registered(ProofType::SpendProof, 8));
+        assert!(spend_proof().with_version(8).verify_with(&registry).is_err());
+        assert!(spend_proof().verify_with(&ProofVerifierRegistry::new()).is_err());
+    }
+    
+    #[test]
+    fn batch_results_match_each_proof_in_order() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry = ProofVerifierRegistry::with_defaults();
+        registry.register(ProofType::SpendProof, 7, Box::new(CountingVerifier(calls.clone())));
+        
+        let mut corrupted_data = spend_proof();
+        corrupted_data.proof_data = "forged".to_string();
+        let mut corrupted_inputs = spend_proof();
+        corrupted_inputs.public_inputs.push("extra".to_string());
+        let proofs = vec![spend_proof(), corrupted_data, spend_proof().with_version(7), corrupted_inputs, spend_proof()];
+        
+        let results = registry.verify_batch(&proofs).unwrap();
+        assert_eq!(results, vec![true, false, true, false, true]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let one_by_one: Vec<bool> = proofs.iter().map(|proof| proof.verify_with(&registry).unwrap()).collect();
+        assert_eq!(results, one_by_one);
+        
+        let default_proofs: Vec<_> = proofs.iter().filter(|proof| proof.version == ZeroKnowledgeProof::DEFAULT_VERSION).cloned().collect();
+        assert_eq!(ZeroKnowledgeProof::verify_batch(&default_proofs).unwrap(), vec![true, false, false, true]);
+        assert!(registry.verify_batch(&[]).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn batch_with_an_unregistered_proof_fails_before_verifying() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry = ProofVerifierRegistry::new();
+        registry.register(ProofType::SpendProof, 7, Box::new(CountingVerifier(calls.clone())));
+        
+        let proofs = vec![spend_proof().with_version(7), spend_proof().with_version(8)];
+        assert!(registry.verify_batch(&proofs).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
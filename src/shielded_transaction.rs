@@ -1,16 +1,41 @@
 use crate::{
-    error::Result,
-    crypto::{hash, generate_nonce},
-    commitment::CommitmentScheme,
-    zk_proof::ZeroKnowledgeProof,
+    error::{Result, ShieldedError},
+    amount::{Amount, DEFAULT_DECIMALS},
+    asset::AssetId,
+    crypto::{ct_eq, decrypt_with_viewing_key, derive_opening, encrypt_to_viewing_key, generate_nonce, leading_zero_bits, sign_with, verify_legacy_signature, verify_signature_with, KeyScheme},
+    commitment::{CommitmentScheme, IDENTITY_COMMITMENT},
+    proof_system::{MockProofSystem, ProofStatement, ProofSystem},
+    policy::{FeePolicy, TransactionPolicy},
+    note::{EncryptedNote, Note},
+    wallet::Wallet,
 };
+use bincode::Options;
+use curve25519_dalek::scalar::Scalar;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sha2::{Sha256, Digest};
 use hex;
-use chrono::{DateTime, Utc};
-use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Earliest timestamp accepted when validating a transaction (2020-01-01T00:00:00Z)
+pub const MIN_TIMESTAMP_SECS: i64 = 1_577_836_800;
+
+/// Default allowance for a transaction timestamp to run ahead of the local clock
+pub const DEFAULT_MAX_FUTURE_SKEW_SECS: i64 = 300;
+
+/// Longest memo plaintext accepted, in bytes
+pub const MAX_MEMO_BYTES: usize = 512;
+
+/// Highest proof-of-work difficulty, the bit length of `pow_hash`
+pub const MAX_POW_DIFFICULTY: u32 = 256;
+
+/// Top-level Namada transfer fields that `from_namada_json` maps into this model
+const NAMADA_MAPPED_FIELDS: &[&str] = &["source", "target", "amount", "token", "shielded", "masp_tx"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct ShieldedTransaction {
     pub id: String,
     pub from: String,
@@ -22,31 +47,301 @@ pub struct ShieldedTransaction {
     pub output_commitments: Vec<String>,
     pub zk_proof: Option<String>,
     pub signature: String,
+    #[cfg_attr(feature = "borsh", borsh(serialize_with = "crate::borsh_time::serialize", deserialize_with = "crate::borsh_time::deserialize"))]
     pub timestamp: DateTime<Utc>,
     pub status: TransactionStatus,
+    /// Notes encrypted to their owners, each attached to one output commitment
+    #[serde(default)]
+    pub encrypted_notes: Vec<EncryptedNote>,
+    /// Memo encrypted to the recipient's viewing key as `ephemeral_key:ciphertext`;
+    /// not covered by the signature, which signs `memo_commitment` instead
+    #[serde(default)]
+    pub encrypted_memo: Option<String>,
+    /// Pedersen commitments to the input values, in the same order as `input_commitments`
+    #[serde(default)]
+    pub input_value_commitments: Vec<String>,
+    /// Pedersen commitments to the output values, in the same order as `output_commitments`
+    #[serde(default)]
+    pub output_value_commitments: Vec<String>,
+    /// Public key that produced `signature`; verification requires its address to be `from`
+    #[serde(default)]
+    pub signer_public_key: Option<String>,
+    /// Scheme `signature` was made with, set when signing
+    #[serde(default = "KeyScheme::legacy")]
+    pub sig_scheme: KeyScheme,
+    /// Free-form local label; not covered by the signature
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Position in the sender's strictly increasing transaction sequence, if sequenced
+    #[serde(default)]
+    pub sequence: Option<u64>,
+    /// `chain_hash` of the transaction stored before this one, set by storage
+    #[serde(default)]
+    pub prev_hash: Option<String>,
+    /// Transparent recipients of a multi-recipient public transaction; when set,
+    /// `to` is the first of them and `amount` their total
+    #[serde(default)]
+    pub public_outputs: Vec<PublicOutput>,
+    /// Nullifiers of the notes spent by this transaction, empty for public transactions
+    #[serde(default)]
+    pub nullifiers: Vec<String>,
+    /// Merkle root of the tree state this transaction was built against
+    #[serde(default)]
+    pub anchor: Option<String>,
+    /// Proof-of-work nonce found by `solve_pow`; not covered by the signature
+    #[serde(default)]
+    pub pow_nonce: Option<u64>,
+    /// Time after which a still-pending transaction no longer verifies
+    #[serde(default)]
+    #[cfg_attr(feature = "borsh", borsh(serialize_with = "crate::borsh_time::serialize_option", deserialize_with = "crate::borsh_time::deserialize_option"))]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Why the transaction failed, recorded by `fail`; not covered by the signature
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    /// Random salt hashed into `id` by `compute_id`; absent on transactions whose
+    /// id was drawn at random and cannot be recomputed
+    #[serde(default)]
+    pub salt: Option<String>,
+    /// Asset `amount` and `fee` are denominated in
+    #[serde(default)]
+    pub asset: AssetId,
+    /// Value of each input note, in the same order as `input_commitments`; recorded
+    /// by the multi-note constructors, empty when `amount` plus `fee` is the input
+    #[serde(default)]
+    pub input_values: Vec<NoteValue>,
+    /// Value of each output note, change included, in the same order as `output_commitments`
+    #[serde(default)]
+    pub output_values: Vec<NoteValue>,
+    /// Hash of the memo and the nonce encrypted alongside it, so the memo's reader can
+    /// re-encrypt it without breaking the signature but can't swap it for another
+    #[serde(default)]
+    pub memo_commitment: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Asset and value of one note, the public part of its opening
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct NoteValue {
+    pub asset: AssetId,
+    pub value: u64,
+}
+
+impl From<&Note> for NoteValue {
+    fn from(note: &Note) -> Self {
+        Self { asset: note.asset.clone(), value: note.value }
+    }
+}
+
+/// One transparent recipient and the amount credited to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct PublicOutput {
+    pub to: String,
+    pub amount: u64,
+    /// Asset credited, which must be the transaction's own asset to balance
+    #[serde(default)]
+    pub asset: AssetId,
+}
+
+/// The fields `signed_bytes` encodes, borrowed in declaration order
+///
+/// Timestamps are Unix seconds and nanoseconds, so the encoding doesn't depend on
+/// how chrono formats them.
+#[derive(Serialize)]
+struct SignedFields<'a> {
+    id: &'a str,
+    from: &'a str,
+    to: &'a str,
+    amount: u64,
+    fee: u64,
+    transaction_type: TransactionType,
+    input_commitments: &'a [String],
+    output_commitments: &'a [String],
+    zk_proof: Option<&'a str>,
+    timestamp: (i64, u32),
+    input_value_commitments: &'a [String],
+    output_value_commitments: &'a [String],
+    sequence: Option<u64>,
+    public_outputs: &'a [PublicOutput],
+    nullifiers: &'a [String],
+    anchor: Option<&'a str>,
+    expires_at: Option<(i64, u32)>,
+    salt: Option<&'a str>,
+    asset: &'a AssetId,
+    input_values: &'a [NoteValue],
+    output_values: &'a [NoteValue],
+    memo_commitment: Option<&'a str>,
+}
+
+impl<'a> From<&'a ShieldedTransaction> for SignedFields<'a> {
+    fn from(transaction: &'a ShieldedTransaction) -> Self {
+        let seconds_and_nanos = |time: &DateTime<Utc>| (time.timestamp(), time.timestamp_subsec_nanos());
+        Self {
+            id: &transaction.id,
+            from: &transaction.from,
+            to: &transaction.to,
+            amount: transaction.amount,
+            fee: transaction.fee,
+            transaction_type: transaction.transaction_type,
+            input_commitments: &transaction.input_commitments,
+            output_commitments: &transaction.output_commitments,
+            zk_proof: transaction.zk_proof.as_deref(),
+            timestamp: seconds_and_nanos(&transaction.timestamp),
+            input_value_commitments: &transaction.input_value_commitments,
+            output_value_commitments: &transaction.output_value_commitments,
+            sequence: transaction.sequence,
+            public_outputs: &transaction.public_outputs,
+            nullifiers: &transaction.nullifiers,
+            anchor: transaction.anchor.as_deref(),
+            expires_at: transaction.expires_at.as_ref().map(seconds_and_nanos),
+            salt: transaction.salt.as_deref(),
+            asset: &transaction.asset,
+            input_values: &transaction.input_values,
+            output_values: &transaction.output_values,
+            memo_commitment: transaction.memo_commitment.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[cfg_attr(feature = "borsh", borsh(use_discriminant = true))]
 pub enum TransactionType {
-    Public,
-    Shielded,
+    Public = 0,
+    Shielded = 1,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl FromStr for TransactionType {
+    type Err = crate::error::ShieldedError;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "public" => Ok(TransactionType::Public),
+            "shielded" => Ok(TransactionType::Shielded),
+            other => Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Unknown transaction type '{}', expected public or shielded", other)
+            )),
+        }
+    }
+}
+
+/// What creating a transaction would do, from `ShieldedTransaction::simulate`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulationResult {
+    pub fee: u64,
+    /// Amount plus fee taken from the sender
+    pub total_debit: u64,
+    pub balanced: bool,
+    pub input_commitments: Vec<String>,
+    pub output_commitments: Vec<String>,
+}
+
+/// Result of a verification check that ran to completion
+///
+/// Errors are reserved for checks that could not be run at all.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerifyOutcome {
+    Valid,
+    Invalid(String),
+    /// The check does not apply to this transaction, e.g. a proof on a public transfer
+    NotApplicable,
+}
+
+impl VerifyOutcome {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, VerifyOutcome::Valid)
+    }
+    
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, VerifyOutcome::Invalid(_))
+    }
+}
+
+impl std::fmt::Display for VerifyOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyOutcome::Valid => write!(f, "valid"),
+            VerifyOutcome::Invalid(reason) => write!(f, "invalid ({})", reason),
+            VerifyOutcome::NotApplicable => write!(f, "not applicable"),
+        }
+    }
+}
+
+/// Lifecycle of a transaction: it starts `Pending` and settles exactly once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[cfg_attr(feature = "borsh", borsh(use_discriminant = true))]
 pub enum TransactionStatus {
-    Pending,
-    Confirmed,
-    Failed,
+    Pending = 0,
+    Confirmed = 1,
+    Failed = 2,
+}
+
+impl FromStr for TransactionStatus {
+    type Err = crate::error::ShieldedError;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "pending" => Ok(TransactionStatus::Pending),
+            "confirmed" => Ok(TransactionStatus::Confirmed),
+            "failed" => Ok(TransactionStatus::Failed),
+            other => Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Unknown transaction status '{}', expected pending, confirmed or failed", other)
+            )),
+        }
+    }
 }
 
 impl ShieldedTransaction {
-    /// Create a public transaction (visible amounts)
-    pub fn create_public(from: &str, to: &str, amount: u64) -> Result<Self> {
-        let id = Self::generate_transaction_id(from, to, amount)?;
-        let fee = Self::calculate_fee(amount);
+    /// Create a public transaction (visible amounts), signed by the sender
+    pub fn create_public(sender: &Wallet, to: &str, amount: u64) -> Result<Self> {
+        Self::create_public_with_policy(sender, to, amount, &TransactionPolicy::default())
+    }
+    
+    /// Create a public transaction whose fee follows the given policy
+    ///
+    /// A `PerByte` fee is charged on the JSON encoding of the transaction before
+    /// its fee is set.
+    pub fn create_with_fee(sender: &Wallet, to: &str, amount: u64, strategy: &FeePolicy) -> Result<Self> {
+        Wallet::validate_address(&sender.address)?;
+        Wallet::validate_address(to)?;
+        TransactionPolicy::default().check_amount(amount)?;
+        // Drafted without a fee, so the default fee can't overflow an amount the strategy fits
+        let draft = Self::public_with_fee(sender, to, amount, 0)?;
+        let size = serde_json::to_vec(&draft)?.len();
+        // The id and signature cover the fee, so both are made for the final one
+        Self::public_with_fee(sender, to, amount, strategy.fee_for(amount, size))
+    }
+    
+    /// Create a public transaction from a wallet, debiting its transparent balance
+    /// by the amount plus fee
+    ///
+    /// `InsufficientFunds` leaves the wallet unchanged. Setting a sequence or anchor
+    /// afterwards needs a `resign`.
+    pub fn create_public_from(sender: &mut Wallet, to: &str, amount: u64, policy: &TransactionPolicy) -> Result<Self> {
+        let transaction = Self::create_public_with_policy(sender, to, amount, policy)?;
+        sender.debit_for(&transaction)?;
+        Ok(transaction)
+    }
+    
+    /// Create a public transaction, enforcing the policy's per-transaction cap
+    ///
+    /// Both addresses must pass `Wallet::validate_address`.
+    pub fn create_public_with_policy(sender: &Wallet, to: &str, amount: u64, policy: &TransactionPolicy) -> Result<Self> {
+        Wallet::validate_address(&sender.address)?;
+        Wallet::validate_address(to)?;
+        policy.check_amount(amount)?;
+        Self::public_with_fee(sender, to, amount, Self::calculate_fee(amount))
+    }
+    
+    /// A public transaction with the given fee, its id and signature made over it
+    fn public_with_fee(sender: &Wallet, to: &str, amount: u64, fee: u64) -> Result<Self> {
+        let from = sender.address.as_str();
+        Self::input_total(amount, fee)?;
+        let (id, salt) = Self::generate_transaction_id(from, to, amount, fee);
         let signature = Self::generate_signature(&id, from)?;
         
-        Ok(Self {
+        let mut transaction = Self {
             id,
             from: from.to_string(),
             to: to.to_string(),
@@ -59,40 +354,538 @@ impl ShieldedTransaction {
             signature,
             timestamp: Utc::now(),
             status: TransactionStatus::Pending,
+            encrypted_notes: vec![],
+            encrypted_memo: None,
+            input_value_commitments: vec![],
+            output_value_commitments: vec![],
+            signer_public_key: None,
+            sig_scheme: KeyScheme::default(),
+            label: None,
+            sequence: None,
+            prev_hash: None,
+            public_outputs: vec![],
+            nullifiers: vec![],
+            anchor: None,
+            pow_nonce: None,
+            expires_at: None,
+            failure_reason: None,
+            salt: Some(salt),
+            asset: AssetId::native(),
+            input_values: vec![],
+            output_values: vec![],
+            memo_commitment: None,
+        };
+        transaction.resign(sender)?;
+        Ok(transaction)
+    }
+    
+    /// Create one public transaction paying several recipients, with a single fee
+    /// charged on the total
+    pub fn create_public_multi(sender: &Wallet, outputs: &[(String, u64)]) -> Result<Self> {
+        let Some((first_to, _)) = outputs.first() else {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "A multi-recipient transaction needs at least one output".to_string()
+            ));
+        };
+        let total = outputs
+            .iter()
+            .try_fold(0u64, |total, (_, amount)| total.checked_add(*amount))
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount(
+                "Sum of outputs overflows".to_string()
+            ))?;
+        
+        for (to, _) in outputs {
+            Wallet::validate_address(to)?;
+        }
+        
+        let mut transaction = Self::create_public(sender, first_to, total)?;
+        transaction.public_outputs = outputs
+            .iter()
+            .map(|(to, amount)| PublicOutput { to: to.clone(), amount: *amount, asset: transaction.asset.clone() })
+            .collect();
+        transaction.resign(sender)?;
+        Ok(transaction)
+    }
+    
+    /// Create a shielded transaction (hidden amounts), signed by the sender
+    ///
+    /// Openings are derived from the wallet's spending key, as in `create_shielded_to`,
+    /// but no note is encrypted to the recipient.
+    pub fn create_shielded(sender: &Wallet, to: &str, amount: u64) -> Result<Self> {
+        Self::create_shielded_with_policy(sender, to, amount, &TransactionPolicy::default())
+    }
+    
+    /// Build a shielded transaction under the default policy and report its fee,
+    /// debit and commitments, without storing it or touching any balance
+    pub fn simulate(sender: &Wallet, to: &str, amount: u64) -> Result<SimulationResult> {
+        Self::create_shielded(sender, to, amount)?.simulation()
+    }
+    
+    /// Summarize an already built transaction as `simulate` would
+    pub fn simulation(&self) -> Result<SimulationResult> {
+        Ok(SimulationResult {
+            fee: self.fee,
+            total_debit: self.debit_total()?,
+            balanced: self.is_balanced(),
+            input_commitments: self.input_commitments.clone(),
+            output_commitments: self.output_commitments.clone(),
         })
     }
     
-    /// Create a shielded transaction (hidden amounts)
-    pub fn create_shielded(from: &str, to: &str, amount: u64) -> Result<Self> {
-        let id = Self::generate_transaction_id(from, to, amount)?;
-        let fee = Self::calculate_fee(amount);
+    /// Like `create_shielded`, with the proof made by the given backend
+    ///
+    /// Verify it with `verify_proof_with` and the same backend; `verify_proof` only
+    /// accepts `MockProofSystem` proofs.
+    pub fn create_shielded_with_proof_system(sender: &Wallet, to: &str, amount: u64, proof_system: &dyn ProofSystem) -> Result<Self> {
+        Self::build_shielded(sender, to, amount, &TransactionPolicy::default(), None, proof_system)
+    }
+    
+    /// Replace the transaction's proof with one made by the given backend
+    ///
+    /// The proof is signed, so the transaction must be re-signed with `resign` afterwards.
+    pub fn prove_with(&mut self, proof_system: &dyn ProofSystem) -> Result<()> {
+        if let TransactionType::Public = self.transaction_type {
+            return Err(crate::error::ShieldedError::ZKProofError(
+                "Public transactions carry no proof".to_string()
+            ));
+        }
+        self.zk_proof = Some(proof_system.generate(&self.proof_statement())?);
+        Ok(())
+    }
+    
+    /// The statement the transaction's proof is made for
+    pub fn proof_statement(&self) -> ProofStatement<'_> {
+        ProofStatement {
+            transaction_id: &self.id,
+            input_commitments: &self.input_commitments,
+            output_commitments: &self.output_commitments,
+        }
+    }
+    
+    /// Create a shielded transaction, enforcing the policy's limits on its outputs
+    pub fn create_shielded_with_policy(sender: &Wallet, to: &str, amount: u64, policy: &TransactionPolicy) -> Result<Self> {
+        Self::build_shielded(sender, to, amount, policy, None, &MockProofSystem)
+    }
+    
+    /// Create a shielded transaction from a wallet whose output note is encrypted
+    /// to the recipient's viewing public key, so the recipient can find it by scanning
+    ///
+    /// The change note is encrypted to the sender, so only the sender can tell
+    /// whether it carries any value; see `create_shielded_with_change`.
+    pub fn create_shielded_to(sender: &Wallet, to: &str, recipient_viewing_key: &str, amount: u64) -> Result<Self> {
+        Self::create_shielded_to_with_policy(sender, to, recipient_viewing_key, amount, &TransactionPolicy::default())
+    }
+    
+    /// Like `create_shielded_to`, enforcing the policy's limits and fee
+    pub fn create_shielded_to_with_policy(
+        sender: &Wallet,
+        to: &str,
+        recipient_viewing_key: &str,
+        amount: u64,
+        policy: &TransactionPolicy,
+    ) -> Result<Self> {
+        Self::create_shielded_with_change(sender, to, recipient_viewing_key, amount, None, policy)
+    }
+    
+    /// Like `create_shielded_to_with_policy`, sending change to a separate wallet
+    ///
+    /// The change note is owned by and encrypted to `change_to`, e.g. a fresh wallet
+    /// so change can't be linked to the sender; `None` sends change to the sender.
+    pub fn create_shielded_with_change(
+        sender: &Wallet,
+        to: &str,
+        recipient_viewing_key: &str,
+        amount: u64,
+        change_to: Option<&Wallet>,
+        policy: &TransactionPolicy,
+    ) -> Result<Self> {
+        let change_to = change_to.unwrap_or(sender);
+        Self::build_shielded(sender, to, amount, policy, Some((recipient_viewing_key, change_to)), &MockProofSystem)
+    }
+    
+    /// Create a shielded transaction from a wallet, debiting its shielded balance by
+    /// the amount plus fee
+    ///
+    /// Openings are derived from the spending key, so the wallet must be unlocked.
+    /// Given the recipient's viewing key, the output and change notes are encrypted
+    /// as in `create_shielded_with_change`. `InsufficientFunds` leaves the wallet
+    /// unchanged. Setting a sequence or anchor afterwards needs a `resign`.
+    pub fn create_shielded_from(
+        sender: &mut Wallet,
+        to: &str,
+        recipient_viewing_key: Option<&str>,
+        amount: u64,
+        change_to: Option<&Wallet>,
+        policy: &TransactionPolicy,
+    ) -> Result<Self> {
+        let transaction = Self::build_shielded(
+            sender,
+            to,
+            amount,
+            policy,
+            recipient_viewing_key.map(|viewing_key| (viewing_key, change_to.unwrap_or(sender))),
+            &MockProofSystem,
+        )?;
+        sender.debit_for(&transaction)?;
+        Ok(transaction)
+    }
+    
+    /// Like `create_shielded_to`, carrying a memo only the recipient can read
+    ///
+    /// The memo is encrypted to `recipient_viewing_key` and bound to the signature
+    /// through `memo_commitment`, so a swapped memo fails `decrypt_memo`.
+    pub fn create_shielded_with_memo(
+        sender: &Wallet,
+        to: &str,
+        amount: u64,
+        memo: &str,
+        recipient_viewing_key: &str,
+    ) -> Result<Self> {
+        if memo.len() > MAX_MEMO_BYTES {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Memo is {} bytes, above the {} byte limit", memo.len(), MAX_MEMO_BYTES)
+            ));
+        }
+        
+        let mut transaction = Self::create_shielded_to(sender, to, recipient_viewing_key, amount)?;
+        transaction.encrypt_memo(memo, recipient_viewing_key)?;
+        transaction.resign(sender)?;
+        Ok(transaction)
+    }
+    
+    /// Attach `memo` encrypted to a viewing public key, with a fresh commitment; resign afterwards
+    pub(crate) fn encrypt_memo(&mut self, memo: &str, viewing_public_key: &str) -> Result<()> {
+        let mut payload = generate_nonce().to_vec();
+        payload.extend_from_slice(memo.as_bytes());
+        self.memo_commitment = Some(Self::commit_memo(&payload));
+        self.seal_memo(&payload, viewing_public_key)
+    }
+    
+    /// Re-encrypt the memo from `viewing_key` to another viewing public key
+    ///
+    /// The commitment is kept, so the signature still verifies. Returns false, changing
+    /// nothing, if the memo doesn't decrypt with `viewing_key`.
+    pub(crate) fn reencrypt_memo(&mut self, viewing_key: &str, new_viewing_public_key: &str) -> Result<bool> {
+        match self.open_memo(viewing_key) {
+            Ok(payload) => self.seal_memo(&payload, new_viewing_public_key).map(|_| true),
+            Err(_) => Ok(false),
+        }
+    }
+    
+    /// Decrypt the memo with a viewing key
+    ///
+    /// Fails with a `CryptoError` if there is no memo, it was encrypted to someone else,
+    /// or it doesn't match the signed `memo_commitment`.
+    pub fn decrypt_memo(&self, viewing_key: &str) -> Result<String> {
+        let payload = self.open_memo(viewing_key)?;
+        if payload.len() < 32 || self.memo_commitment.as_deref() != Some(Self::commit_memo(&payload).as_str()) {
+            return Err(crate::error::ShieldedError::CryptoError(
+                "Memo does not match its signed commitment".to_string()
+            ));
+        }
+        String::from_utf8(payload[32..].to_vec())
+            .map_err(|_| crate::error::ShieldedError::CryptoError("Memo is not valid UTF-8".to_string()))
+    }
+    
+    fn seal_memo(&mut self, payload: &[u8], viewing_public_key: &str) -> Result<()> {
+        let (ephemeral_key, ciphertext) = encrypt_to_viewing_key(viewing_public_key, payload)?;
+        self.encrypted_memo = Some(format!("{}:{}", ephemeral_key, ciphertext));
+        Ok(())
+    }
+    
+    fn open_memo(&self, viewing_key: &str) -> Result<Vec<u8>> {
+        let memo = self.encrypted_memo.as_deref().ok_or_else(|| {
+            crate::error::ShieldedError::CryptoError(format!("Transaction {} has no memo", self.id))
+        })?;
+        memo
+            .split_once(':')
+            .and_then(|(ephemeral_key, ciphertext)| decrypt_with_viewing_key(viewing_key, ephemeral_key, ciphertext))
+            .ok_or_else(|| crate::error::ShieldedError::CryptoError("Memo is not addressed to this viewing key".to_string()))
+    }
+    
+    fn commit_memo(payload: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"memo_commitment");
+        hasher.update(payload);
+        hex::encode(hasher.finalize())
+    }
+    
+    /// Create a shielded transaction spending several notes to several recipients
+    ///
+    /// Every input note is spent under its own commitment and reveals a nullifier
+    /// keyed by its nonce. The fee is charged once on the recipients' total, which is
+    /// also the public `amount`, and whatever the inputs leave over goes to a change
+    /// output back to `from`. Output openings are not encrypted to anyone; use
+    /// `create_shielded_to` for recipients who scan with a viewing key.
+    pub fn create_shielded_multi(sender: &Wallet, outputs: &[(String, u64)], input_notes: &[Note]) -> Result<Self> {
+        let outputs: Vec<(String, AssetId, u64)> = outputs
+            .iter()
+            .map(|(to, amount)| (to.clone(), AssetId::native(), *amount))
+            .collect();
+        Self::create_shielded_multi_asset(sender, &outputs, input_notes)
+    }
+    
+    /// Like `create_shielded_multi`, with each output naming its asset
+    ///
+    /// The fee is charged in the first output's asset, which becomes the transaction's
+    /// `asset` and `amount` the total sent in it. Each asset's inputs must cover its
+    /// outputs, with a change note per asset back to `from`.
+    pub fn create_shielded_multi_asset(sender: &Wallet, outputs: &[(String, AssetId, u64)], input_notes: &[Note]) -> Result<Self> {
+        Self::build_multi(sender, outputs, input_notes, None, None)
+    }
+    
+    /// Spend notes held by a wallet, chosen with `Wallet::select_notes`, to one recipient
+    ///
+    /// The output note is encrypted to the recipient and the change note to the
+    /// sender, so both can pick them up with `Wallet::track`.
+    pub fn create_shielded_from_notes(sender: &Wallet, to: &str, recipient_viewing_key: &str, amount: u64) -> Result<Self> {
+        let fee = TransactionPolicy::default().fee_policy.fee(amount);
+        let required = Self::input_total(amount, fee)?;
+        let input_notes = sender.select_notes(required)?;
+        
+        Self::build_multi(
+            sender,
+            &[(to.to_string(), AssetId::native(), amount)],
+            &input_notes,
+            Some(recipient_viewing_key),
+            Some(&sender.viewing_public_key),
+        )
+    }
+    
+    /// Build a multi-note transaction, encrypting the outputs and the change notes
+    /// to the given viewing keys when set
+    fn build_multi(
+        sender: &Wallet,
+        outputs: &[(String, AssetId, u64)],
+        input_notes: &[Note],
+        recipient_viewing_key: Option<&str>,
+        change_viewing_key: Option<&str>,
+    ) -> Result<Self> {
+        let from = sender.address.as_str();
+        let policy = TransactionPolicy::default();
+        let Some((first_to, asset, _)) = outputs.first() else {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "A multi-output transaction needs at least one output".to_string()
+            ));
+        };
+        Wallet::validate_address(from)?;
+        for (to, _, _) in outputs {
+            Wallet::validate_address(to)?;
+        }
+        if input_notes.is_empty() {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "A multi-input transaction needs at least one input note".to_string()
+            ));
+        }
+        if input_notes.iter().any(|note| !note.is_consistent()) {
+            return Err(crate::error::ShieldedError::CommitmentError(
+                "Input note does not open its commitment".to_string()
+            ));
+        }
+        
+        let mut required: BTreeMap<&AssetId, u64> = BTreeMap::new();
+        for (_, output_asset, amount) in outputs {
+            let total = required.entry(output_asset).or_default();
+            *total = total
+                .checked_add(*amount)
+                .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Sum of outputs overflows".to_string()))?;
+            policy.check_output(*amount)?;
+        }
+        let mut available: BTreeMap<&AssetId, u64> = BTreeMap::new();
+        for note in input_notes {
+            let total = available.entry(&note.asset).or_default();
+            *total = total
+                .checked_add(note.value)
+                .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Sum of input notes overflows".to_string()))?;
+        }
         
-        // Create input commitment (spending from shielded balance)
-        let input_commitment = CommitmentScheme::commit(amount + fee)?;
+        let output_total = required[asset];
+        policy.check_amount(output_total)?;
+        let fee = policy.fee_policy.fee(output_total);
+        let fee_total = required.entry(asset).or_default();
+        *fee_total = fee_total
+            .checked_add(fee)
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Outputs plus fee overflow".to_string()))?;
         
-        // Create output commitment (sending to recipient)
-        let output_commitment = CommitmentScheme::commit(amount)?;
+        // Whatever each asset's inputs leave over goes back to the sender in that asset
+        let mut change = Vec::new();
+        for (input_asset, &input_total) in &available {
+            let required = required.get(input_asset).copied().unwrap_or(0);
+            let left = input_total
+                .checked_sub(required)
+                .ok_or(crate::error::ShieldedError::InsufficientFunds { required, available: input_total })?;
+            if left > 0 {
+                change.push(((*input_asset).clone(), left));
+            }
+        }
+        if let Some((_, &required)) = required.iter().find(|(output_asset, _)| !available.contains_key(*output_asset)) {
+            return Err(crate::error::ShieldedError::InsufficientFunds { required, available: 0 });
+        }
         
-        // Create change commitment (if any)
-        let change_commitment = if fee > 0 {
-            Some(CommitmentScheme::commit(0)?) // Change goes back to sender
-        } else {
-            None
+        let (id, salt) = Self::generate_transaction_id(from, first_to, output_total, fee);
+        let mut output_notes = outputs
+            .iter()
+            .map(|(to, output_asset, amount)| Note::with_asset(output_asset.clone(), *amount, to))
+            .collect::<Result<Vec<Note>>>()?;
+        let mut encrypted_notes = match recipient_viewing_key {
+            Some(viewing_key) => output_notes
+                .iter()
+                .map(|note| note.encrypt(viewing_key))
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![],
+        };
+        for (change_asset, left) in change {
+            let note = Note::with_asset(change_asset, left, from)?;
+            if let Some(viewing_key) = change_viewing_key {
+                encrypted_notes.push(note.encrypt(viewing_key)?);
+            }
+            output_notes.push(note);
+        }
+        
+        let (input_blindings, output_blindings) = Self::balanced_blindings(
+            input_notes.len(),
+            output_notes.len(),
+            |_, _| CommitmentScheme::random_blinding(),
+        );
+        
+        let input_commitments: Vec<String> = input_notes.iter().map(|note| note.commitment.clone()).collect();
+        let output_commitments: Vec<String> = output_notes.iter().map(|note| note.commitment.clone()).collect();
+        let zk_proof = MockProofSystem.generate(&ProofStatement {
+            transaction_id: &id,
+            input_commitments: &input_commitments,
+            output_commitments: &output_commitments,
+        })?;
+        let signature = Self::generate_signature(&id, from)?;
+        
+        let mut transaction = Self {
+            id,
+            from: from.to_string(),
+            to: first_to.clone(),
+            amount: output_total,
+            fee,
+            transaction_type: TransactionType::Shielded,
+            input_commitments,
+            output_commitments,
+            zk_proof: Some(zk_proof),
+            signature,
+            timestamp: Utc::now(),
+            status: TransactionStatus::Pending,
+            encrypted_notes,
+            encrypted_memo: None,
+            input_value_commitments: Self::note_value_commitments(input_notes, &input_blindings),
+            output_value_commitments: Self::note_value_commitments(&output_notes, &output_blindings),
+            signer_public_key: None,
+            sig_scheme: KeyScheme::default(),
+            label: None,
+            sequence: None,
+            prev_hash: None,
+            public_outputs: vec![],
+            nullifiers: input_notes
+                .iter()
+                .map(Note::nullifier)
+                .collect(),
+            anchor: None,
+            pow_nonce: None,
+            expires_at: None,
+            failure_reason: None,
+            salt: Some(salt),
+            asset: asset.clone(),
+            input_values: input_notes.iter().map(NoteValue::from).collect(),
+            output_values: output_notes.iter().map(NoteValue::from).collect(),
+            memo_commitment: None,
+        };
+        transaction.resign(sender)?;
+        Ok(transaction)
+    }
+    
+    /// Build a one-input shielded transaction, proved by `proof_system`
+    ///
+    /// With `encrypt_to` set, the output note is encrypted to the recipient's viewing
+    /// key and the change note is owned by and encrypted to the given wallet.
+    fn build_shielded(
+        sender: &Wallet,
+        to: &str,
+        amount: u64,
+        policy: &TransactionPolicy,
+        encrypt_to: Option<(&str, &Wallet)>,
+        proof_system: &dyn ProofSystem,
+    ) -> Result<Self> {
+        let from = sender.address.as_str();
+        let spending_key = sender.spending_key()?;
+        Wallet::validate_address(from)?;
+        Wallet::validate_address(to)?;
+        // Change is exempt from the dust limit, only the recipient output is checked
+        policy.check_amount(amount)?;
+        policy.check_output(amount)?;
+        if let FeePolicy::PerByte(_) = policy.fee_policy {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "Per-byte fees are only supported for public transactions".to_string()
+            ));
+        }
+        
+        let fee = policy.fee_policy.fee(amount);
+        let input_total = Self::input_total(amount, fee)?;
+        let (id, salt) = Self::generate_transaction_id(from, to, amount, fee);
+        
+        // Openings come from the spending key, so the sender can re-derive them
+        // later for `export_audit_bundle`
+        let nonce = |role: &str, index: usize| derive_opening(spending_key, &id, role, index);
+        
+        // Create input note (spending from shielded balance). It reveals the nullifier
+        // `Note::nullifier` gives, so the same note spent through `create_shielded_multi`
+        // or again here is caught as a double spend.
+        let input_note = Note::with_nonce(input_total, from, nonce("input", 0))?;
+        
+        // Create output commitment (sending to recipient), encrypting its
+        // opening to the recipient when we know their viewing key
+        let mut encrypted_notes = Vec::new();
+        let output_commitment = match encrypt_to {
+            Some((viewing_key, _)) => {
+                let note = Note::with_nonce(amount, to, nonce("output", 0))?;
+                encrypted_notes.push(note.encrypt(viewing_key)?);
+                note.commitment
+            }
+            None => CommitmentScheme::create_commitment(amount, &nonce("output", 0))?.commitment_hash,
         };
         
-        let mut input_commitments = vec![input_commitment];
+        // Create change commitment (if any). Change goes to the change address and is
+        // committed with a fresh nonce even when it is zero, so it stays opaque.
+        let change_commitment = match (fee > 0, encrypt_to) {
+            (false, _) => None,
+            (true, Some((_, change_to))) => {
+                let note = Note::with_nonce(0, &change_to.address, nonce("output", 1))?;
+                encrypted_notes.push(note.encrypt(&change_to.viewing_public_key)?);
+                Some(note.commitment)
+            }
+            (true, None) => Some(CommitmentScheme::create_commitment(0, &nonce("output", 1))?.commitment_hash),
+        };
+        
+        let nullifiers = vec![input_note.nullifier()];
+        let input_commitments = vec![input_note.commitment];
         let mut output_commitments = vec![output_commitment];
+        let mut output_values = vec![amount];
         
         if let Some(change) = change_commitment {
             output_commitments.push(change);
+            output_values.push(0);
         }
         
-        // Generate zero-knowledge proof
-        let zk_proof = ZeroKnowledgeProof::generate(&id)?;
+        let (input_blindings, output_blindings) = Self::balanced_blindings(1, output_values.len(), |role, index| {
+            Scalar::from_bytes_mod_order(nonce(role, index))
+        });
+        let input_value_commitments = Self::value_commitments(&[input_total], &input_blindings);
+        let output_value_commitments = Self::value_commitments(&output_values, &output_blindings);
+        
+        // Generate zero-knowledge proof over the commitments
+        let zk_proof = proof_system.generate(&ProofStatement {
+            transaction_id: &id,
+            input_commitments: &input_commitments,
+            output_commitments: &output_commitments,
+        })?;
         
         let signature = Self::generate_signature(&id, from)?;
         
-        Ok(Self {
+        let mut transaction = Self {
             id,
             from: from.to_string(),
             to: to.to_string(),
@@ -105,9 +898,128 @@ impl ShieldedTransaction {
             signature,
             timestamp: Utc::now(),
             status: TransactionStatus::Pending,
+            encrypted_notes,
+            encrypted_memo: None,
+            input_value_commitments,
+            output_value_commitments,
+            signer_public_key: None,
+            sig_scheme: KeyScheme::default(),
+            label: None,
+            sequence: None,
+            prev_hash: None,
+            public_outputs: vec![],
+            nullifiers,
+            anchor: None,
+            pow_nonce: None,
+            expires_at: None,
+            failure_reason: None,
+            salt: Some(salt),
+            asset: AssetId::native(),
+            input_values: vec![],
+            output_values: vec![],
+            memo_commitment: None,
+        };
+        transaction.resign(sender)?;
+        Ok(transaction)
+    }
+    
+    /// Pick value commitment blindings that cancel out, so that the inputs minus
+    /// outputs minus fee nets to the identity point
+    ///
+    /// `blinding` is asked for the `("value_input", i)` and `("value_output", i)` blindings.
+    pub(crate) fn balanced_blindings(
+        input_count: usize,
+        output_count: usize,
+        blinding: impl Fn(&str, usize) -> Scalar,
+    ) -> (Vec<Scalar>, Vec<Scalar>) {
+        let input_blindings: Vec<Scalar> = (0..input_count).map(|index| blinding("value_input", index)).collect();
+        let mut output_blindings: Vec<Scalar> = (0..output_count).map(|index| blinding("value_output", index)).collect();
+        
+        // The last output absorbs whatever blinding is left over
+        if let Some((last, others)) = output_blindings.split_last_mut() {
+            let input_sum: Scalar = input_blindings.iter().sum();
+            let other_outputs: Scalar = others.iter().sum();
+            *last = input_sum - other_outputs;
+        }
+        
+        (input_blindings, output_blindings)
+    }
+    
+    /// Pedersen-commit to each value with its blinding
+    fn value_commitments(values: &[u64], blindings: &[Scalar]) -> Vec<String> {
+        values
+            .iter()
+            .zip(blindings)
+            .map(|(value, blinding)| CommitmentScheme::commit_pedersen(*value, blinding))
+            .collect()
+    }
+    
+    /// Pedersen-commit to each note's value on its asset's generator
+    fn note_value_commitments(notes: &[Note], blindings: &[Scalar]) -> Vec<String> {
+        notes
+            .iter()
+            .zip(blindings)
+            .map(|(note, blinding)| CommitmentScheme::commit_pedersen_asset(&note.asset, note.value, blinding))
+            .collect()
+    }
+    
+    /// Aggregate the value commitments into a single point:
+    /// `sum(inputs) - sum(outputs) - fee * G_asset`, where `G_asset` is the value generator
+    /// of the fee's asset
+    ///
+    /// For a balanced transaction this is the identity, `IDENTITY_COMMITMENT`.
+    pub fn net_commitment(&self) -> Result<String> {
+        let mut net = -(Scalar::from(self.fee) * self.asset.value_generator());
+        for commitment in &self.input_value_commitments {
+            net += CommitmentScheme::decode_pedersen(commitment)?;
+        }
+        for commitment in &self.output_value_commitments {
+            net -= CommitmentScheme::decode_pedersen(commitment)?;
+        }
+        
+        Ok(CommitmentScheme::encode_pedersen(&net))
+    }
+    
+    /// Check that the value commitments balance with a single equality check
+    pub fn verify_commitment_balance(&self) -> Result<VerifyOutcome> {
+        if let TransactionType::Public = self.transaction_type {
+            return Ok(VerifyOutcome::NotApplicable);
+        }
+        if self.input_value_commitments.is_empty() || self.output_value_commitments.is_empty() {
+            return Ok(VerifyOutcome::Invalid("shielded transaction has no value commitments".to_string()));
+        }
+        
+        Ok(if self.net_commitment()? == IDENTITY_COMMITMENT {
+            VerifyOutcome::Valid
+        } else {
+            VerifyOutcome::Invalid("value commitments do not balance".to_string())
         })
     }
     
+    /// Decrypt every note in this transaction addressed to a viewing key
+    ///
+    /// Notes are only returned when they open an output commitment of this transaction.
+    /// Zero-value change notes are included; see `Note::is_empty`.
+    pub fn decrypt_notes(&self, viewing_key: &str) -> Vec<Note> {
+        self.encrypted_notes
+            .iter()
+            .filter(|encrypted| self.output_commitments.contains(&encrypted.commitment))
+            .filter_map(|encrypted| encrypted.decrypt(viewing_key))
+            .collect()
+    }
+    
+    /// Total value of the outputs a viewing key can open, or `None` if it opens none
+    ///
+    /// Needs only the viewing key, so an auditor can read amounts without being
+    /// able to spend; change notes back to the sender count when it is the sender's key.
+    pub fn decrypt_output_value(&self, viewing_key: &str) -> Option<u64> {
+        let notes = self.decrypt_notes(viewing_key);
+        if notes.is_empty() {
+            return None;
+        }
+        notes.iter().try_fold(0u64, |total, note| total.checked_add(note.value))
+    }
+    
     /// Verify a transaction
     pub fn verify(transaction_id: &str) -> Result<bool> {
         // In a real implementation, this would verify the transaction on the blockchain
@@ -115,64 +1027,839 @@ impl ShieldedTransaction {
         Ok(transaction_id.len() >= 32)
     }
     
-    /// Generate a transaction ID
-    fn generate_transaction_id(from: &str, to: &str, amount: u64) -> Result<String> {
-        let mut hasher = Sha256::new();
-        hasher.update(from.as_bytes());
-        hasher.update(to.as_bytes());
-        hasher.update(amount.to_le_bytes());
-        hasher.update(generate_nonce());
-        hasher.update(Uuid::new_v4().as_bytes());
+    /// Run every check that applies to this transaction, stopping at the first failure
+    ///
+    /// Covers the id, the timestamp window, the amount balance, the value commitment
+    /// balance, the zero-knowledge proof and the sender's signature. Public
+    /// transactions take a fast path that never touches proof or commitment
+    /// logic; see `verify_public`. Signatures in the forgeable legacy scheme
+    /// don't verify; see `verify_full_with`.
+    pub fn verify_full(&self) -> Result<VerifyOutcome> {
+        self.verify_full_with(false)
+    }
+    
+    /// `verify_full`, also accepting legacy-scheme signatures when `allow_legacy_signatures`
+    /// is set; only for transactions stored before a secure scheme was required
+    pub fn verify_full_with(&self, allow_legacy_signatures: bool) -> Result<VerifyOutcome> {
+        if let TransactionType::Public = self.transaction_type {
+            return Ok(self.verify_public_with(allow_legacy_signatures));
+        }
         
-        Ok(hex::encode(hasher.finalize()))
+        if let outcome @ VerifyOutcome::Invalid(_) = self.verify_id() {
+            return Ok(outcome);
+        }
+        if let outcome @ VerifyOutcome::Invalid(_) = self.validation_outcome() {
+            return Ok(outcome);
+        }
+        if !self.is_balanced() {
+            return Ok(VerifyOutcome::Invalid("inputs do not equal outputs plus fee".to_string()));
+        }
+        
+        for outcome in [self.verify_commitment_balance()?, self.verify_proof()?] {
+            if outcome.is_invalid() {
+                return Ok(outcome);
+            }
+        }
+        if let outcome @ VerifyOutcome::Invalid(_) = self.signature_outcome(allow_legacy_signatures) {
+            return Ok(outcome);
+        }
+        
+        Ok(VerifyOutcome::Valid)
     }
     
-    /// Calculate transaction fee
-    fn calculate_fee(amount: u64) -> u64 {
-        // Simple fee calculation: 0.1% of amount, minimum 1
-        std::cmp::max(1, amount / 1000)
+    /// `validate` as an outcome; whatever error it returns, such as an amount
+    /// total that overflows, makes the transaction invalid
+    fn validation_outcome(&self) -> VerifyOutcome {
+        match self.validate() {
+            Ok(()) => VerifyOutcome::Valid,
+            Err(crate::error::ShieldedError::InvalidTransaction(reason)) => VerifyOutcome::Invalid(reason),
+            Err(error) => VerifyOutcome::Invalid(error.to_string()),
+        }
     }
     
-    /// Generate a signature for the transaction
-    fn generate_signature(transaction_id: &str, signer: &str) -> Result<String> {
+    /// Checks for a public transaction: id, timestamp window, balance and the
+    /// sender's signature
+    ///
+    /// Public transactions must not carry a proof or commitments at all.
+    pub fn verify_public(&self) -> VerifyOutcome {
+        self.verify_public_with(false)
+    }
+    
+    fn verify_public_with(&self, allow_legacy_signatures: bool) -> VerifyOutcome {
+        if !Self::verify(&self.id).unwrap_or(false) {
+            return VerifyOutcome::Invalid("malformed transaction id".to_string());
+        }
+        if let outcome @ VerifyOutcome::Invalid(_) = self.verify_id() {
+            return outcome;
+        }
+        if let outcome @ VerifyOutcome::Invalid(_) = self.validation_outcome() {
+            return outcome;
+        }
+        if !self.is_balanced() {
+            return VerifyOutcome::Invalid("inputs do not equal outputs plus fee".to_string());
+        }
+        let carries_shielded_data = self.zk_proof.is_some()
+            || !self.input_commitments.is_empty()
+            || !self.output_commitments.is_empty()
+            || !self.input_value_commitments.is_empty()
+            || !self.output_value_commitments.is_empty();
+        if carries_shielded_data {
+            return VerifyOutcome::Invalid("public transaction carries shielded data".to_string());
+        }
+        if let outcome @ VerifyOutcome::Invalid(_) = self.signature_outcome(allow_legacy_signatures) {
+            return outcome;
+        }
+        
+        VerifyOutcome::Valid
+    }
+    
+    /// Verify the transaction's zero-knowledge proof as a `MockProofSystem` proof
+    ///
+    /// Public transactions carry no proof, so the check is `NotApplicable` for them.
+    pub fn verify_proof(&self) -> Result<VerifyOutcome> {
+        self.verify_proof_with(&MockProofSystem)
+    }
+    
+    /// Verify the transaction's zero-knowledge proof with the given backend
+    pub fn verify_proof_with(&self, proof_system: &dyn ProofSystem) -> Result<VerifyOutcome> {
+        if let TransactionType::Public = self.transaction_type {
+            return Ok(VerifyOutcome::NotApplicable);
+        }
+        
+        let encoded = match &self.zk_proof {
+            Some(encoded) => encoded,
+            None => return Ok(VerifyOutcome::Invalid("shielded transaction has no proof".to_string())),
+        };
+        
+        Ok(if proof_system.verify(encoded, &self.proof_statement())? {
+            VerifyOutcome::Valid
+        } else {
+            VerifyOutcome::Invalid("proof failed verification".to_string())
+        })
+    }
+    
+    /// Hash linking this transaction into the storage log
+    ///
+    /// Covers the id, the `content_hash` and `prev_hash`, so changing any earlier
+    /// transaction's content changes every link after it.
+    pub fn chain_hash(&self) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(transaction_id.as_bytes());
-        hasher.update(signer.as_bytes());
-        hasher.update(generate_nonce());
+        hasher.update(b"tx_chain");
+        Self::update_field(&mut hasher, self.prev_hash.as_deref().unwrap_or_default().as_bytes());
+        Self::update_field(&mut hasher, self.id.as_bytes());
+        Self::update_field(&mut hasher, self.content_hash().as_bytes());
+        hex::encode(hasher.finalize())
+    }
+    
+    /// Data committed as this transaction's Merkle leaf, the hash of `signed_bytes`
+    pub fn merkle_leaf(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"tx_leaf");
+        hasher.update(self.signed_bytes());
+        hex::encode(hasher.finalize())
+    }
+    
+    /// Stable hash of the transaction's logical content, for external anchoring
+    ///
+    /// Covers `from`, `to`, `amount`, `fee`, `transaction_type`, the input and
+    /// output commitments in order, and any public outputs, nullifiers, anchor,
+    /// memo commitment, recorded note values and non-native asset. The id, signature, proof, timestamp and status
+    /// are excluded because they differ between otherwise identical transfers.
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"tx_content");
+        Self::update_field(&mut hasher, self.from.as_bytes());
+        Self::update_field(&mut hasher, self.to.as_bytes());
+        hasher.update(self.amount.to_le_bytes());
+        hasher.update(self.fee.to_le_bytes());
+        hasher.update([match self.transaction_type {
+            TransactionType::Public => 0u8,
+            TransactionType::Shielded => 1u8,
+        }]);
         
-        Ok(hex::encode(hasher.finalize()))
+        for commitments in [&self.input_commitments, &self.output_commitments] {
+            hasher.update((commitments.len() as u64).to_le_bytes());
+            for commitment in commitments {
+                Self::update_field(&mut hasher, commitment.as_bytes());
+            }
+        }
+        
+        // Only multi-recipient transactions hash their outputs, so older hashes are unchanged
+        if !self.public_outputs.is_empty() {
+            hasher.update((self.public_outputs.len() as u64).to_le_bytes());
+            for output in &self.public_outputs {
+                Self::update_field(&mut hasher, output.to.as_bytes());
+                hasher.update(output.amount.to_le_bytes());
+                if !output.asset.is_native() {
+                    hasher.update(b"asset");
+                    Self::update_field(&mut hasher, output.asset.0.as_bytes());
+                }
+            }
+        }
+        
+        // Native transfers hash as they did before assets were recorded
+        if !self.asset.is_native() {
+            hasher.update(b"asset");
+            Self::update_field(&mut hasher, self.asset.0.as_bytes());
+        }
+        
+        // Likewise only transactions that reveal nullifiers hash them
+        if !self.nullifiers.is_empty() {
+            hasher.update((self.nullifiers.len() as u64).to_le_bytes());
+            for nullifier in &self.nullifiers {
+                Self::update_field(&mut hasher, nullifier.as_bytes());
+            }
+        }
+        
+        if let Some(anchor) = &self.anchor {
+            hasher.update(b"anchor");
+            Self::update_field(&mut hasher, anchor.as_bytes());
+        }
+        
+        if let Some(commitment) = &self.memo_commitment {
+            hasher.update(b"memo");
+            Self::update_field(&mut hasher, commitment.as_bytes());
+        }
+        
+        // Note values are only recorded, and hashed, by the multi-note constructors
+        for (tag, values) in [(&b"input_values"[..], &self.input_values), (&b"output_values"[..], &self.output_values)] {
+            if !values.is_empty() {
+                hasher.update(tag);
+                hasher.update((values.len() as u64).to_le_bytes());
+                for note in values {
+                    Self::update_field(&mut hasher, note.asset.0.as_bytes());
+                    hasher.update(note.value.to_le_bytes());
+                }
+            }
+        }
+        
+        hex::encode(hasher.finalize())
     }
     
-    /// Get the total input amount (for shielded transactions)
-    pub fn get_input_total(&self) -> u64 {
-        self.amount + self.fee
+    /// Set the sender sequence number; resign afterwards
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
     }
     
-    /// Get the total output amount (for shielded transactions)
-    pub fn get_output_total(&self) -> u64 {
-        self.amount
+    /// Set the time after which the transaction expires; resign afterwards
+    pub fn with_expiry(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
     }
     
-    /// Check if the transaction is balanced (inputs = outputs + fee)
-    pub fn is_balanced(&self) -> bool {
-        self.get_input_total() == self.get_output_total() + self.fee
+    /// Expire the transaction `ttl` after its timestamp; resign afterwards
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        let expires_at = self.timestamp.checked_add_signed(ttl).unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.with_expiry(expires_at)
     }
     
-    /// Convert to JSON for storage/transmission
-    pub fn to_json(&self) -> Result<String> {
-        serde_json::to_string_pretty(self)
-            .map_err(|e| crate::error::ShieldedError::SerializationError(e))
+    /// Move a pending transaction to `Confirmed`
+    ///
+    /// Settled and expired transactions can't be confirmed.
+    pub fn confirm(&mut self) -> Result<()> {
+        if self.is_expired(Utc::now()) {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Transaction {} has expired and can't be confirmed", self.id)
+            ));
+        }
+        self.transition(TransactionStatus::Confirmed)
     }
     
-    /// Create from JSON
-    pub fn from_json(json: &str) -> Result<Self> {
-        serde_json::from_str(json)
-            .map_err(|e| crate::error::ShieldedError::SerializationError(e))
+    /// Move a pending transaction to `Failed`, recording why
+    pub fn fail(&mut self, reason: &str) -> Result<()> {
+        self.transition(TransactionStatus::Failed)?;
+        self.failure_reason = Some(reason.to_string());
+        Ok(())
     }
-}
-
-impl std::fmt::Display for ShieldedTransaction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    
+    fn transition(&mut self, to: TransactionStatus) -> Result<()> {
+        if self.status != TransactionStatus::Pending {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Transaction {} can't move from {:?} to {:?}", self.id, self.status, to)
+            ));
+        }
+        self.status = to;
+        Ok(())
+    }
+    
+    /// Whether the expiry time has been reached at `now`; never for transactions without one
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+    
+    /// Hash of the transaction content with a proof-of-work nonce
+    pub fn pow_hash(&self, nonce: u64) -> [u8; 32] {
+        Self::pow_digest(&self.content_hash(), nonce)
+    }
+    
+    fn pow_digest(content_hash: &str, nonce: u64) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"tx_pow");
+        Self::update_field(&mut hasher, content_hash.as_bytes());
+        hasher.update(nonce.to_le_bytes());
+        hasher.finalize().into()
+    }
+    
+    /// Search for a nonce whose `pow_hash` has `difficulty` leading zero bits,
+    /// record it and return how many nonces were tried
+    ///
+    /// Each extra bit of difficulty doubles the expected number of attempts. A
+    /// difficulty above the hash's 256 bits, or no nonce up to `u64::MAX` meeting
+    /// it, is an `InvalidTransaction` error.
+    pub fn solve_pow(&mut self, difficulty: u32) -> Result<u64> {
+        if difficulty > MAX_POW_DIFFICULTY {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Proof-of-work difficulty {} is above the {} bits of the hash", difficulty, MAX_POW_DIFFICULTY)
+            ));
+        }
+        let content_hash = self.content_hash();
+        for nonce in 0..=u64::MAX {
+            if leading_zero_bits(&Self::pow_digest(&content_hash, nonce)) >= difficulty {
+                self.pow_nonce = Some(nonce);
+                return Ok(nonce.saturating_add(1));
+            }
+        }
+        Err(crate::error::ShieldedError::InvalidTransaction(
+            format!("No proof-of-work nonce meets difficulty {}", difficulty)
+        ))
+    }
+    
+    /// Check the recorded proof-of-work nonce against a difficulty
+    pub fn verify_pow(&self, difficulty: u32) -> VerifyOutcome {
+        if difficulty == 0 {
+            return VerifyOutcome::NotApplicable;
+        }
+        match self.pow_nonce {
+            None => VerifyOutcome::Invalid("missing proof-of-work nonce".to_string()),
+            Some(nonce) if leading_zero_bits(&self.pow_hash(nonce)) >= difficulty => VerifyOutcome::Valid,
+            Some(_) => VerifyOutcome::Invalid(format!("proof of work is below difficulty {}", difficulty)),
+        }
+    }
+    
+    /// Record the Merkle root this transaction was built against; resign afterwards
+    pub fn with_anchor(mut self, anchor: String) -> Self {
+        self.anchor = Some(anchor);
+        self
+    }
+    
+    /// Check the signature against the recorded signer's public key, and that the key
+    /// is the one behind `from`
+    ///
+    /// Only signed fields matter: the id, the content covered by `content_hash`,
+    /// the value commitments, the proof, the timestamp, the sequence number and
+    /// the expiry. Changing the status or the label leaves the signature valid.
+    /// A legacy-scheme signature is never valid, since anyone can forge one.
+    pub fn is_signature_valid(&self) -> bool {
+        self.signature_outcome(false).is_valid()
+    }
+    
+    /// The sender's signature as an outcome; a missing one is invalid, and so is one
+    /// whose key doesn't own `from`
+    ///
+    /// A receive address is owned by its derived key, which is what signs for it.
+    pub(crate) fn signature_outcome(&self, allow_legacy_signatures: bool) -> VerifyOutcome {
+        let Some(public_key) = &self.signer_public_key else {
+            return VerifyOutcome::Invalid("transaction is not signed".to_string());
+        };
+        if Wallet::generate_address(public_key).ok().as_deref() != Some(self.from.as_str()) {
+            return VerifyOutcome::Invalid("signer does not own the sending address".to_string());
+        }
+        if !self.signature_verifies(allow_legacy_signatures) {
+            return VerifyOutcome::Invalid("signature does not verify".to_string());
+        }
+        VerifyOutcome::Valid
+    }
+    
+    fn signature_verifies(&self, allow_legacy_signatures: bool) -> bool {
+        match &self.signer_public_key {
+            Some(public_key) if allow_legacy_signatures => self.verify_legacy_signature(public_key),
+            Some(public_key) => self.verify_signature(public_key),
+            None => false,
+        }
+    }
+    
+    /// Check the signature against a given public key, e.g. the sender's known key
+    /// rather than the one the transaction claims
+    ///
+    /// The scheme comes from the transaction, so the forgeable legacy scheme is refused.
+    pub fn verify_signature(&self, public_key: &str) -> bool {
+        self.sig_scheme != KeyScheme::Legacy
+            && verify_signature_with(self.sig_scheme, &self.signing_message(), &self.signature, public_key)
+                .unwrap_or(false)
+    }
+    
+    /// `verify_signature`, also accepting a legacy-scheme signature
+    ///
+    /// Only for transactions stored before a secure scheme was required.
+    pub fn verify_legacy_signature(&self, public_key: &str) -> bool {
+        match self.sig_scheme {
+            KeyScheme::Legacy => verify_legacy_signature(&self.signing_message(), &self.signature, public_key)
+                .unwrap_or(false),
+            _ => self.verify_signature(public_key),
+        }
+    }
+    
+    /// Sign the transaction with a wallet, replacing any previous signature
+    ///
+    /// A transaction from one of the wallet's receive addresses is signed with that
+    /// address's derived key. Only needed after changing a signed field; see
+    /// `is_signature_valid`.
+    pub fn resign(&mut self, wallet: &Wallet) -> Result<()> {
+        let (public_key, private_key) = wallet.signing_keys(&self.from)?;
+        self.signature = sign_with(wallet.key_scheme, &self.signing_message(), &private_key)?;
+        self.signer_public_key = Some(public_key);
+        self.sig_scheme = wallet.key_scheme;
+        Ok(())
+    }
+    
+    /// Digest of `signed_bytes`, used as the signature message
+    pub(crate) fn signing_message(&self) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"tx_signature");
+        hasher.update(self.signed_bytes());
+        hasher.finalize().to_vec()
+    }
+    
+    /// Canonical encoding of the fields the signature and the Merkle leaf cover
+    ///
+    /// Uses the `to_bytes` codec over every field but the signature, which is not
+    /// part of its own preimage, the signer's key and scheme, the encrypted note
+    /// openings and memo, which their reader may re-encrypt under a new viewing key,
+    /// and the local bookkeeping fields (`status`, `label`, `failure_reason`, `prev_hash`, `pow_nonce`).
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        Self::codec()
+            .serialize(&SignedFields::from(self))
+            .expect("signed fields have no size limit to exceed")
+    }
+    
+    /// Feed a length-prefixed field so adjacent fields can't run into each other
+    fn update_field(hasher: &mut Sha256, bytes: &[u8]) {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    }
+    
+    /// Validate fields that may come from an untrusted source
+    pub fn validate(&self) -> Result<()> {
+        self.validate_with_skew(Duration::seconds(DEFAULT_MAX_FUTURE_SKEW_SECS))
+    }
+    
+    /// Validate with a custom allowance for clock skew into the future
+    pub fn validate_with_skew(&self, max_future_skew: Duration) -> Result<()> {
+        let earliest = DateTime::<Utc>::from_timestamp(MIN_TIMESTAMP_SECS, 0)
+            .expect("minimum timestamp is representable");
+        if self.timestamp < earliest {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Timestamp {} is before {}", self.timestamp, earliest)
+            ));
+        }
+        
+        let latest = Utc::now()
+            .checked_add_signed(max_future_skew)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        if self.timestamp > latest {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                format!("Timestamp {} is too far in the future", self.timestamp)
+            ));
+        }
+        
+        // Only pending transactions can expire; a settled one stays valid
+        if let (TransactionStatus::Pending, Some(expires_at)) = (&self.status, self.expires_at) {
+            if self.is_expired(Utc::now()) {
+                return Err(crate::error::ShieldedError::InvalidTransaction(
+                    format!("Transaction expired at {}", expires_at)
+                ));
+            }
+        }
+        
+        let has_empty_sentinel = self.input_commitments
+            .iter()
+            .chain(&self.output_commitments)
+            .chain(&self.input_value_commitments)
+            .chain(&self.output_value_commitments)
+            .any(|commitment| CommitmentScheme::is_empty_sentinel(commitment));
+        if has_empty_sentinel {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "Transaction contains the empty-commitment sentinel".to_string()
+            ));
+        }
+        
+        let recorded_values = [(&self.input_values, &self.input_commitments), (&self.output_values, &self.output_commitments)];
+        if recorded_values.iter().any(|(values, commitments)| !values.is_empty() && values.len() != commitments.len()) {
+            return Err(crate::error::ShieldedError::InvalidTransaction(
+                "Note values do not match the commitments".to_string()
+            ));
+        }
+        
+        self.debit_total()?;
+        self.get_input_total()?;
+        self.get_output_total()?;
+        self.output_totals()?;
+        
+        Ok(())
+    }
+    
+    /// Draw a fresh salt, returning the id it gives along with the salt to store
+    fn generate_transaction_id(from: &str, to: &str, amount: u64, fee: u64) -> (String, String) {
+        let salt = hex::encode(generate_nonce());
+        (Self::compute_id(from, to, amount, fee, &salt), salt)
+    }
+    
+    /// Transaction id for the given fields and salt
+    ///
+    /// Deterministic: the same inputs and salt always give the same id. Commitments
+    /// are left out because shielded openings are derived from the id, and the
+    /// timestamp so ids stay reproducible under `with_seed`; both are signed.
+    pub fn compute_id(from: &str, to: &str, amount: u64, fee: u64, salt: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"tx_id");
+        Self::update_field(&mut hasher, from.as_bytes());
+        Self::update_field(&mut hasher, to.as_bytes());
+        hasher.update(amount.to_le_bytes());
+        hasher.update(fee.to_le_bytes());
+        Self::update_field(&mut hasher, salt.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+    
+    /// The id `compute_id` gives for this transaction's fields, if it has a salt
+    pub fn expected_id(&self) -> Option<String> {
+        self.salt.as_ref().map(|salt| {
+            Self::compute_id(&self.from, &self.to, self.amount, self.fee, salt)
+        })
+    }
+    
+    /// Check the id against its recomputation from the transaction's fields
+    ///
+    /// `NotApplicable` for transactions stored before ids were salted.
+    pub fn verify_id(&self) -> VerifyOutcome {
+        match self.expected_id() {
+            Some(expected) if ct_eq(&expected, &self.id) => VerifyOutcome::Valid,
+            Some(_) => VerifyOutcome::Invalid("id does not match the transaction's fields".to_string()),
+            None => VerifyOutcome::NotApplicable,
+        }
+    }
+    
+    /// Calculate transaction fee
+    fn calculate_fee(amount: u64) -> u64 {
+        // Default policy: 0.1% of amount rounded down, minimum 1
+        FeePolicy::default().fee(amount)
+    }
+    
+    /// Generate a signature for the transaction
+    fn generate_signature(transaction_id: &str, signer: &str) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(transaction_id.as_bytes());
+        hasher.update(signer.as_bytes());
+        hasher.update(generate_nonce());
+        
+        Ok(hex::encode(hasher.finalize()))
+    }
+    
+    /// Get the total input amount (for shielded transactions)
+    ///
+    /// Sums the input notes in the transaction's asset when their values are
+    /// recorded, change included, and is the amount plus fee otherwise. Fails with
+    /// `InvalidAmount` when the total does not fit in a `u64`.
+    pub fn get_input_total(&self) -> Result<u64> {
+        if !self.input_values.is_empty() {
+            return self.note_total(&self.input_values, "Sum of input notes overflows");
+        }
+        Self::input_total(self.amount, self.fee)
+    }
+    
+    /// Sum of the values in the transaction's asset, or `InvalidAmount` if it overflows
+    fn note_total(&self, values: &[NoteValue], overflow: &str) -> Result<u64> {
+        values
+            .iter()
+            .filter(|note| note.asset == self.asset)
+            .try_fold(0u64, |total, note| total.checked_add(note.value))
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount(overflow.to_string()))
+    }
+    
+    /// What the sender is out by: the amount plus fee, change having come back
+    pub fn debit_total(&self) -> Result<u64> {
+        Self::input_total(self.amount, self.fee)
+    }
+    
+    /// Amount plus fee, or `InvalidAmount` if it overflows
+    fn input_total(amount: u64, fee: u64) -> Result<u64> {
+        amount.checked_add(fee).ok_or_else(|| crate::error::ShieldedError::InvalidAmount(
+            format!("Amount {} plus fee {} overflows", amount, fee)
+        ))
+    }
+    
+    /// Get the total output amount, summing the recipients of a multi-recipient transaction
+    /// or, for a multi-note one, every output note in its asset including change
+    pub fn get_output_total(&self) -> Result<u64> {
+        if !self.output_values.is_empty() {
+            return self.note_total(&self.output_values, "Sum of output notes overflows");
+        }
+        if self.public_outputs.is_empty() {
+            return Ok(self.amount);
+        }
+        self.public_outputs
+            .iter()
+            .try_fold(0u64, |total, output| total.checked_add(output.amount))
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Sum of outputs overflows".to_string()))
+    }
+    
+    /// Public amounts credited to each recipient, in output order
+    pub fn credits(&self) -> Vec<(String, u64)> {
+        if self.public_outputs.is_empty() {
+            return vec![(self.to.clone(), self.amount)];
+        }
+        self.public_outputs
+            .iter()
+            .map(|output| (output.to.clone(), output.amount))
+            .collect()
+    }
+    
+    /// Check if the transaction is balanced (inputs = outputs + fee) in every asset
+    ///
+    /// Shielded transactions hide the value of each note, so when they carry value
+    /// commitments the check sums across all of them; each asset commits on its own
+    /// generator, so the sum only vanishes when every asset balances. Others compare
+    /// public totals, whose inputs are all in the transaction's asset.
+    pub fn is_balanced(&self) -> bool {
+        if matches!(self.transaction_type, TransactionType::Shielded) && !self.input_value_commitments.is_empty() {
+            return self.net_commitment().is_ok_and(|net| net == IDENTITY_COMMITMENT);
+        }
+        let Ok(outputs) = self.output_totals() else {
+            return false;
+        };
+        outputs.keys().all(|asset| *asset == self.asset)
+            && outputs.get(&self.asset).copied().unwrap_or(0) == self.amount
+    }
+    
+    /// Asset of each input commitment, in order
+    ///
+    /// Inputs without recorded note values are in the transaction's own `asset`.
+    pub fn input_assets(&self) -> Vec<&AssetId> {
+        Self::note_assets(&self.input_values, self.input_commitments.len(), &self.asset)
+    }
+    
+    /// Asset of each output commitment, in order; see `input_assets`
+    pub fn output_assets(&self) -> Vec<&AssetId> {
+        Self::note_assets(&self.output_values, self.output_commitments.len(), &self.asset)
+    }
+    
+    fn note_assets<'a>(values: &'a [NoteValue], count: usize, asset: &'a AssetId) -> Vec<&'a AssetId> {
+        if values.is_empty() {
+            vec![asset; count]
+        } else {
+            values.iter().map(|note| &note.asset).collect()
+        }
+    }
+    
+    /// Public output totals per asset, summing the recipients of a multi-recipient transaction
+    ///
+    /// Fails with `InvalidAmount` when an asset's outputs sum past `u64::MAX`.
+    pub fn output_totals(&self) -> Result<BTreeMap<AssetId, u64>> {
+        let mut totals = BTreeMap::new();
+        if self.public_outputs.is_empty() {
+            totals.insert(self.asset.clone(), self.amount);
+        }
+        for output in &self.public_outputs {
+            let total: &mut u64 = totals.entry(output.asset.clone()).or_default();
+            *total = total
+                .checked_add(output.amount)
+                .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Sum of outputs overflows".to_string()))?;
+        }
+        Ok(totals)
+    }
+    
+    /// Convert to JSON for storage/transmission
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(crate::error::ShieldedError::SerializationError)
+    }
+    
+    /// Create from JSON, rejecting transactions that fail `validate`
+    pub fn from_json(json: &str) -> Result<Self> {
+        let transaction: Self = serde_json::from_str(json)
+            .map_err(crate::error::ShieldedError::SerializationError)?;
+        transaction.validate()?;
+        Ok(transaction)
+    }
+    
+    /// Canonical binary encoding of every field, in declaration order
+    ///
+    /// Integers are fixed-width little-endian and strings and lists are
+    /// length-prefixed, so unlike `to_json` the bytes depend only on the field
+    /// values and two equal transactions always encode identically.
+    ///
+    /// The signature and the Merkle leaf hash `signed_bytes` instead, which uses the
+    /// same codec but leaves out the signature and the local bookkeeping fields.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Self::codec()
+            .serialize(self)
+            .map_err(|e| crate::error::ShieldedError::InvalidTransaction(format!("Failed to encode transaction: {}", e)))
+    }
+    
+    /// Decode `to_bytes` output, rejecting trailing bytes and transactions that fail `validate`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let transaction: Self = Self::codec()
+            .deserialize(bytes)
+            .map_err(|e| crate::error::ShieldedError::InvalidTransaction(format!("Failed to decode transaction: {}", e)))?;
+        transaction.validate()?;
+        Ok(transaction)
+    }
+    
+    /// Borsh encoding, as Namada uses on-chain
+    ///
+    /// Fields are written in declaration order and enums by their fixed
+    /// discriminants; timestamps are Unix seconds followed by nanoseconds.
+    #[cfg(feature = "borsh")]
+    pub fn to_borsh(&self) -> Result<Vec<u8>> {
+        borsh::to_vec(self)
+            .map_err(|e| crate::error::ShieldedError::InvalidTransaction(format!("Failed to encode transaction: {}", e)))
+    }
+    
+    /// Decode `to_borsh` output, rejecting trailing bytes and transactions that fail `validate`
+    #[cfg(feature = "borsh")]
+    pub fn from_borsh(bytes: &[u8]) -> Result<Self> {
+        let transaction: Self = borsh::from_slice(bytes)
+            .map_err(|e| crate::error::ShieldedError::InvalidTransaction(format!("Failed to decode transaction: {}", e)))?;
+        transaction.validate()?;
+        Ok(transaction)
+    }
+    
+    fn codec() -> impl Options {
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .with_little_endian()
+            .reject_trailing_bytes()
+    }
+    
+    /// Best-effort import of a Namada shielded-transfer JSON object
+    ///
+    /// Maps `source`, `target` and `amount` (a denominated string such as `"1.5"`
+    /// or a raw integer), treats a non-null `shielded` section hash as a shielded
+    /// transfer, and takes output commitments from `masp_tx.sapling_bundle.shielded_outputs[].cmu`
+    /// where present. Anything else is logged and ignored. This is an interop
+    /// convenience, not a compatibility guarantee: the result carries a placeholder
+    /// signature and no proof.
+    pub fn from_namada_json(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json)
+            .map_err(ShieldedError::SerializationError)?;
+        let transfer = value
+            .as_object()
+            .ok_or_else(|| Self::namada_error("expected a transfer object"))?;
+        
+        for field in transfer.keys().filter(|field| !NAMADA_MAPPED_FIELDS.contains(&field.as_str())) {
+            tracing::warn!("Namada field '{}' has no equivalent and was not imported", field);
+        }
+        
+        let address = |field: &str| -> Result<String> {
+            transfer
+                .get(field)
+                .and_then(Value::as_str)
+                .filter(|address| !address.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| Self::namada_error(&format!("missing or non-string '{}'", field)))
+        };
+        let from = address("source")?;
+        let to = address("target")?;
+        let amount = Self::namada_amount(transfer.get("amount"))?;
+        
+        if let Some(token) = transfer.get("token") {
+            tracing::warn!("Namada token {} was not mapped; amount is treated as NAM", token);
+        }
+        
+        let shielded = transfer.get("shielded").is_some_and(|hash| !hash.is_null());
+        let output_commitments = match transfer.get("masp_tx") {
+            Some(masp_tx) => Self::namada_output_commitments(masp_tx),
+            None => vec![],
+        };
+        
+        let fee = Self::calculate_fee(amount);
+        let (id, salt) = Self::generate_transaction_id(&from, &to, amount, fee);
+        let signature = Self::generate_signature(&id, &from)?;
+        let transaction = Self {
+            id,
+            fee,
+            transaction_type: if shielded { TransactionType::Shielded } else { TransactionType::Public },
+            from,
+            to,
+            amount,
+            input_commitments: vec![],
+            output_commitments,
+            zk_proof: None,
+            signature,
+            timestamp: Utc::now(),
+            status: TransactionStatus::Pending,
+            encrypted_notes: vec![],
+            encrypted_memo: None,
+            input_value_commitments: vec![],
+            output_value_commitments: vec![],
+            signer_public_key: None,
+            sig_scheme: KeyScheme::default(),
+            label: None,
+            sequence: None,
+            prev_hash: None,
+            public_outputs: vec![],
+            nullifiers: vec![],
+            anchor: None,
+            pow_nonce: None,
+            expires_at: None,
+            failure_reason: None,
+            salt: Some(salt),
+            asset: AssetId::native(),
+            input_values: vec![],
+            output_values: vec![],
+            memo_commitment: None,
+        };
+        transaction.validate()?;
+        Ok(transaction)
+    }
+    
+    /// Read a Namada amount, either a denominated string or a raw integer
+    fn namada_amount(amount: Option<&Value>) -> Result<u64> {
+        match amount {
+            Some(Value::String(denominated)) => Amount::parse(denominated, DEFAULT_DECIMALS)
+                .map(|amount| amount.value)
+                .map_err(|e| Self::namada_error(&format!("cannot map amount: {}", e))),
+            Some(Value::Number(raw)) => raw
+                .as_u64()
+                .ok_or_else(|| Self::namada_error(&format!("amount {} is not a raw u64", raw))),
+            Some(other) => Err(Self::namada_error(&format!("unsupported amount structure {}", other))),
+            None => Err(Self::namada_error("missing 'amount'")),
+        }
+    }
+    
+    /// Collect hex note commitments from a MASP transaction's shielded outputs
+    fn namada_output_commitments(masp_tx: &Value) -> Vec<String> {
+        let bundle = masp_tx.get("sapling_bundle");
+        if bundle.and_then(|bundle| bundle.get("shielded_spends")).is_some() {
+            tracing::warn!("Namada shielded spends carry nullifiers, not note commitments, and were not imported");
+        }
+        
+        let outputs = bundle
+            .and_then(|bundle| bundle.get("shielded_outputs"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        outputs
+            .iter()
+            .filter_map(|output| match output.get("cmu").and_then(Value::as_str) {
+                Some(cmu) if cmu.len() == 64 && hex::decode(cmu).is_ok() => Some(cmu.to_lowercase()),
+                _ => {
+                    tracing::warn!("Namada shielded output without a hex 'cmu' was not imported");
+                    None
+                }
+            })
+            .collect()
+    }
+    
+    fn namada_error(reason: &str) -> ShieldedError {
+        ShieldedError::SerializationError(serde::de::Error::custom(
+            format!("Cannot import Namada transfer: {}", reason)
+        ))
+    }
+}
+
+impl std::fmt::Display for ShieldedTransaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "Transaction({}, {} -> {}, amount: {}, type: {:?}, status: {:?})",
@@ -185,3 +1872,837 @@ impl std::fmt::Display for ShieldedTransaction {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::fixtures::{address, test_wallet};
+    
+    #[test]
+    fn solved_pow_meets_its_difficulty() {
+        let mut transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        assert!(transaction.verify_pow(8).is_invalid());
+        
+        transaction.solve_pow(8).unwrap();
+        assert!(transaction.verify_pow(8).is_valid());
+    }
+    
+    #[test]
+    fn pow_below_the_difficulty_is_rejected() {
+        let mut transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        let weak_nonce = (0..).find(|nonce| leading_zero_bits(&transaction.pow_hash(*nonce)) < 8).unwrap();
+        transaction.pow_nonce = Some(weak_nonce);
+        assert!(transaction.verify_pow(8).is_invalid());
+    }
+    
+    #[test]
+    fn higher_pow_difficulty_takes_more_attempts() {
+        let (mut easy, mut hard) = (0, 0);
+        for amount in 1..=3 {
+            let mut transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), amount * 1_000).unwrap();
+            easy += transaction.solve_pow(2).unwrap();
+            hard += transaction.solve_pow(12).unwrap();
+        }
+        assert!(hard > easy);
+    }
+    
+    #[test]
+    fn pow_difficulty_above_the_hash_length_is_refused() {
+        let mut transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        assert!(transaction.solve_pow(MAX_POW_DIFFICULTY + 1).is_err());
+        assert_eq!(transaction.pow_nonce, None);
+    }
+    
+    #[test]
+    fn shielded_proof_fails_after_an_output_commitment_is_swapped() {
+        let mut transaction = ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        assert!(transaction.verify_proof().unwrap().is_valid());
+        
+        transaction.output_commitments[0] = CommitmentScheme::create_commitment(1_000, &generate_nonce()).unwrap().commitment_hash;
+        assert!(transaction.verify_proof().unwrap().is_invalid());
+    }
+    
+    /// A backend whose proof is a plain hash of the whole statement
+    struct StatementHashProofSystem;
+    
+    impl ProofSystem for StatementHashProofSystem {
+        fn generate(&self, statement: &ProofStatement) -> Result<String> {
+            let mut hasher = Sha256::new();
+            hasher.update(statement.transaction_id.as_bytes());
+            for input in statement.public_inputs() {
+                hasher.update(input.as_bytes());
+            }
+            Ok(format!("statement_hash:{}", hex::encode(hasher.finalize())))
+        }
+        
+        fn verify(&self, proof: &str, statement: &ProofStatement) -> Result<bool> {
+            Ok(proof == self.generate(statement)?)
+        }
+    }
+    
+    #[test]
+    fn custom_proof_system_proves_at_creation() {
+        let transaction = ShieldedTransaction::create_shielded_with_proof_system(
+            &test_wallet('a'),
+            &address('b'),
+            1_000,
+            &StatementHashProofSystem,
+        ).unwrap();
+        
+        let expected = StatementHashProofSystem.generate(&transaction.proof_statement()).unwrap();
+        assert_eq!(transaction.zk_proof.as_deref(), Some(expected.as_str()));
+        assert!(transaction.verify_proof_with(&StatementHashProofSystem).unwrap().is_valid());
+        assert!(transaction.verify_proof().unwrap().is_invalid());
+    }
+    
+    #[test]
+    fn to_bytes_round_trips() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let transactions = [
+            ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap().with_sequence(3),
+            ShieldedTransaction::create_shielded_to(&sender, &recipient.address, &recipient.viewing_public_key, 1_000).unwrap(),
+        ];
+        for transaction in transactions {
+            let bytes = transaction.to_bytes().unwrap();
+            let decoded = ShieldedTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.to_bytes().unwrap(), bytes);
+            assert_eq!(decoded.to_json().unwrap(), transaction.to_json().unwrap());
+        }
+    }
+    
+    #[test]
+    fn to_bytes_ignores_json_field_order() {
+        let transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        let Value::Object(fields) = serde_json::to_value(&transaction).unwrap() else {
+            panic!("transaction JSON is an object")
+        };
+        // Written by hand, as `serde_json::Map` would sort the keys again
+        let entries: Vec<String> = fields.iter().map(|(key, value)| format!("{:?}:{}", key, value)).collect();
+        let forward = format!("{{{}}}", entries.join(","));
+        let backward = format!("{{{}}}", entries.iter().rev().cloned().collect::<Vec<_>>().join(","));
+        assert_ne!(forward, backward);
+        assert_eq!(
+            ShieldedTransaction::from_json(&forward).unwrap().to_bytes().unwrap(),
+            ShieldedTransaction::from_json(&backward).unwrap().to_bytes().unwrap(),
+        );
+    }
+    
+    #[test]
+    fn memo_decrypts_only_for_its_recipient() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let transaction = ShieldedTransaction::create_shielded_with_memo(
+            &sender, &recipient.address, 100, "invoice 42", &recipient.viewing_public_key,
+        ).unwrap();
+        
+        assert_eq!(transaction.decrypt_memo(&recipient.viewing_key).unwrap(), "invoice 42");
+        assert!(transaction.decrypt_memo(&sender.viewing_key).is_err());
+    }
+    
+    #[test]
+    fn swapped_memo_breaks_the_signature_or_its_commitment() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let original = ShieldedTransaction::create_shielded_with_memo(
+            &sender, &recipient.address, 100, "invoice 42", &recipient.viewing_public_key,
+        ).unwrap();
+        assert!(original.is_signature_valid());
+        
+        let mut swapped = original.clone();
+        swapped.encrypt_memo("invoice 43", &recipient.viewing_public_key).unwrap();
+        assert!(!swapped.is_signature_valid());
+        
+        let mut spliced = original.clone();
+        spliced.encrypted_memo = swapped.encrypted_memo.clone();
+        assert!(spliced.is_signature_valid());
+        assert!(spliced.decrypt_memo(&recipient.viewing_key).is_err());
+    }
+    
+    #[test]
+    fn reencrypted_memo_keeps_the_signature() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let mut rotated = recipient.clone();
+        rotated.rotate_viewing_key();
+        let mut transaction = ShieldedTransaction::create_shielded_with_memo(
+            &sender, &recipient.address, 100, "invoice 42", &recipient.viewing_public_key,
+        ).unwrap();
+        
+        assert!(!transaction.reencrypt_memo(&sender.viewing_key, &rotated.viewing_public_key).unwrap());
+        assert!(transaction.reencrypt_memo(&recipient.viewing_key, &rotated.viewing_public_key).unwrap());
+        assert_eq!(transaction.decrypt_memo(&rotated.viewing_key).unwrap(), "invoice 42");
+        assert!(transaction.decrypt_memo(&recipient.viewing_key).is_err());
+        assert!(transaction.is_signature_valid());
+    }
+    
+    fn input_notes(owner: &str, values: &[u64]) -> Vec<Note> {
+        values.iter().map(|value| Note::new(*value, owner).unwrap()).collect()
+    }
+    
+    #[test]
+    fn two_in_three_out_with_change_sums_every_note() {
+        let outputs = vec![(address('b'), 100), (address('c'), 200), (address('d'), 300)];
+        let transaction = ShieldedTransaction::create_shielded_multi(&test_wallet('a'), &outputs, &input_notes(&address('a'), &[400, 500])).unwrap();
+        
+        assert_eq!(transaction.input_commitments.len(), 2);
+        assert_eq!(transaction.output_commitments.len(), 4);
+        assert_eq!(transaction.fee, 1);
+        assert_eq!(transaction.get_input_total().unwrap(), 900);
+        assert_eq!(transaction.get_output_total().unwrap(), 899);
+        assert_eq!(transaction.output_values.last().unwrap().value, 299);
+        assert_eq!(transaction.debit_total().unwrap(), 601);
+        assert!(transaction.is_balanced());
+    }
+    
+    #[test]
+    fn two_in_three_out_without_change_sums_every_note() {
+        let outputs = vec![(address('b'), 100), (address('c'), 200), (address('d'), 300)];
+        let transaction = ShieldedTransaction::create_shielded_multi(&test_wallet('a'), &outputs, &input_notes(&address('a'), &[301, 300])).unwrap();
+        
+        assert_eq!(transaction.output_commitments.len(), 3);
+        assert_eq!(transaction.get_input_total().unwrap(), 601);
+        assert_eq!(transaction.get_output_total().unwrap(), 600);
+        assert!(transaction.is_balanced());
+    }
+    
+    #[test]
+    fn edited_note_values_break_the_signature() {
+        let sender = Wallet::new("alice").unwrap();
+        let outputs = vec![(address('b'), 100), (address('c'), 200)];
+        let mut transaction = ShieldedTransaction::create_shielded_multi(&sender, &outputs, &input_notes(&sender.address, &[400])).unwrap();
+        transaction.resign(&sender).unwrap();
+        
+        transaction.input_values[0].value = 10_000;
+        assert!(!transaction.is_signature_valid());
+    }
+    
+    #[test]
+    fn two_asset_transfer_balances_per_asset() {
+        let atom = AssetId("ATOM".to_string());
+        let inputs = vec![
+            Note::new(1_000, &address('a')).unwrap(),
+            Note::with_asset(atom.clone(), 50, &address('a')).unwrap(),
+        ];
+        let outputs = vec![(address('b'), AssetId::native(), 500), (address('c'), atom.clone(), 20)];
+        let transaction = ShieldedTransaction::create_shielded_multi_asset(&test_wallet('a'), &outputs, &inputs).unwrap();
+        
+        assert!(transaction.is_balanced());
+        let change = &transaction.output_values[2..];
+        assert_eq!(change.len(), 2);
+        assert!(change.contains(&NoteValue { asset: AssetId::native(), value: 499 }));
+        assert!(change.contains(&NoteValue { asset: atom, value: 30 }));
+    }
+    
+    #[test]
+    fn cross_asset_transfer_is_unbalanced() {
+        let atom = AssetId("ATOM".to_string());
+        let inputs = vec![Note::with_asset(atom.clone(), 1_001, &address('a')).unwrap()];
+        let outputs = vec![(address('b'), AssetId::native(), 1_000)];
+        assert!(ShieldedTransaction::create_shielded_multi_asset(&test_wallet('a'), &outputs, &inputs).is_err());
+        
+        // The same values forged directly: ATOM in, NAM out, NAM fee
+        let mut transaction = ShieldedTransaction::create_shielded_multi(&test_wallet('a'), &[(address('b'), 1_000)], &input_notes(&address('a'), &[1_001])).unwrap();
+        let (input_blindings, output_blindings) = ShieldedTransaction::balanced_blindings(1, 1, |_, _| CommitmentScheme::random_blinding());
+        transaction.input_value_commitments = vec![CommitmentScheme::commit_pedersen_asset(&atom, 1_001, &input_blindings[0])];
+        transaction.output_value_commitments = vec![CommitmentScheme::commit_pedersen_asset(&AssetId::native(), 1_000, &output_blindings[0])];
+        transaction.output_commitments.truncate(1);
+        assert!(!transaction.is_balanced());
+        
+        transaction.input_value_commitments = vec![CommitmentScheme::commit_pedersen_asset(&AssetId::native(), 1_001, &input_blindings[0])];
+        assert!(transaction.is_balanced());
+    }
+    
+    #[test]
+    fn merkle_leaf_binds_id_and_content() {
+        let transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        let mut edited = transaction.clone();
+        edited.amount = 2_000;
+        let mut renamed = transaction.clone();
+        renamed.id = "other".to_string();
+        
+        assert_ne!(edited.merkle_leaf(), transaction.merkle_leaf());
+        assert_ne!(renamed.merkle_leaf(), transaction.merkle_leaf());
+        assert_eq!(transaction.clone().merkle_leaf(), transaction.merkle_leaf());
+    }
+    
+    #[test]
+    fn same_inputs_and_salt_give_the_same_id() {
+        let (from, to) = (address('a'), address('b'));
+        let id = ShieldedTransaction::compute_id(&from, &to, 1_000, 1, "salt");
+        
+        assert_eq!(ShieldedTransaction::compute_id(&from, &to, 1_000, 1, "salt"), id);
+        assert_ne!(ShieldedTransaction::compute_id(&from, &to, 1_000, 1, "other salt"), id);
+        assert_ne!(ShieldedTransaction::compute_id(&from, &to, 1_000, 2, "salt"), id);
+        let transaction = ShieldedTransaction::create_public(&test_wallet('a'), &to, 1_000).unwrap();
+        assert_eq!(transaction.expected_id(), Some(transaction.id.clone()));
+    }
+    
+    #[test]
+    fn fee_policy_transaction_has_the_id_of_its_final_fee() {
+        let sender = Wallet::new("alice").unwrap();
+        let mut transaction = ShieldedTransaction::create_with_fee(&sender, &address('b'), 1_000, &FeePolicy::PerByte(2)).unwrap();
+        
+        assert!(transaction.fee > 0);
+        assert!(transaction.verify_id().is_valid());
+        transaction.resign(&sender).unwrap();
+        assert!(transaction.is_signature_valid());
+        assert!(transaction.verify_id().is_valid());
+    }
+    
+    #[test]
+    fn overflowing_totals_fail_full_verification() {
+        let sender = Wallet::new("alice").unwrap();
+        let public = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        let shielded = ShieldedTransaction::create_shielded(&sender, &address('b'), 100).unwrap();
+        
+        for mut transaction in [public, shielded] {
+            // Without a salt the id is not recomputed, so validation is what must catch it
+            transaction.salt = None;
+            transaction.amount = u64::MAX;
+            assert!(matches!(transaction.validate(), Err(crate::error::ShieldedError::InvalidAmount(_))));
+            assert!(transaction.verify_full().unwrap().is_invalid());
+        }
+    }
+    
+    #[test]
+    fn net_commitment_matches_adding_the_commitments_one_by_one() {
+        let sender = Wallet::new("alice").unwrap();
+        let balanced = ShieldedTransaction::create_shielded(&sender, &address('b'), 100).unwrap();
+        let mut unbalanced = balanced.clone();
+        unbalanced.fee += 1;
+        
+        for transaction in [&balanced, &unbalanced] {
+            let fee = CommitmentScheme::commit_pedersen_asset(&transaction.asset, transaction.fee, &Scalar::ZERO);
+            let mut expected = CommitmentScheme::sub(IDENTITY_COMMITMENT, &fee).unwrap();
+            for commitment in &transaction.input_value_commitments {
+                expected = CommitmentScheme::add(&expected, commitment).unwrap();
+            }
+            for commitment in &transaction.output_value_commitments {
+                expected = CommitmentScheme::sub(&expected, commitment).unwrap();
+            }
+            assert_eq!(transaction.net_commitment().unwrap(), expected);
+        }
+        assert_eq!(balanced.net_commitment().unwrap(), IDENTITY_COMMITMENT);
+        assert_ne!(unbalanced.net_commitment().unwrap(), IDENTITY_COMMITMENT);
+    }
+    
+    #[test]
+    fn from_json_rejects_timestamps_outside_the_window() {
+        let transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        let with_timestamp = |timestamp: &str| {
+            let mut json = serde_json::to_value(&transaction).unwrap();
+            json["timestamp"] = Value::String(timestamp.to_string());
+            ShieldedTransaction::from_json(&json.to_string())
+        };
+        
+        for timestamp in ["9999-12-31T23:59:59Z", "1999-12-31T23:59:59Z"] {
+            let error = with_timestamp(timestamp).unwrap_err();
+            assert!(matches!(error, crate::error::ShieldedError::InvalidTransaction(_)), "{}", timestamp);
+        }
+        let accepted = with_timestamp(&transaction.timestamp.to_rfc3339()).unwrap();
+        assert_eq!(accepted.timestamp, transaction.timestamp);
+    }
+    
+    #[test]
+    fn content_hash_ignores_nonces_but_not_content() {
+        let (from, to) = (test_wallet('a'), address('b'));
+        let first = ShieldedTransaction::create_public(&from, &to, 1_000).unwrap();
+        let mut second = ShieldedTransaction::create_public(&from, &to, 1_000).unwrap();
+        second.status = TransactionStatus::Confirmed;
+        second.label = Some("rent".to_string());
+        second.pow_nonce = Some(7);
+        
+        assert_ne!((&first.id, &first.salt, &first.signature), (&second.id, &second.salt, &second.signature));
+        assert_eq!(first.content_hash(), second.content_hash());
+        
+        let edits: [fn(&mut ShieldedTransaction); 4] = [
+            |transaction| transaction.amount += 1,
+            |transaction| transaction.fee += 1,
+            |transaction| transaction.to = address('c'),
+            |transaction| transaction.nullifiers.push("nullifier".to_string()),
+        ];
+        for edit in edits {
+            let mut edited = first.clone();
+            edit(&mut edited);
+            assert_ne!(edited.content_hash(), first.content_hash());
+        }
+    }
+    
+    #[test]
+    fn outputs_below_the_dust_limit_are_rejected() {
+        let policy = TransactionPolicy { dust_limit: 100, ..TransactionPolicy::default() };
+        
+        let below = ShieldedTransaction::create_shielded_with_policy(&test_wallet('a'), &address('b'), 99, &policy);
+        assert!(matches!(below, Err(crate::error::ShieldedError::InvalidAmount(_))));
+        assert!(ShieldedTransaction::create_shielded_with_policy(&test_wallet('a'), &address('b'), 100, &policy).is_ok());
+        
+        let lower = TransactionPolicy { dust_limit: 50, ..TransactionPolicy::default() };
+        assert!(ShieldedTransaction::create_shielded_with_policy(&test_wallet('a'), &address('b'), 99, &lower).is_ok());
+    }
+    
+    #[test]
+    fn proof_verification_distinguishes_each_outcome() {
+        let mut shielded = ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        assert_eq!(shielded.verify_proof().unwrap(), VerifyOutcome::Valid);
+        
+        shielded.zk_proof = None;
+        assert!(shielded.verify_proof().unwrap().is_invalid());
+        
+        let public = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        assert_eq!(public.verify_proof().unwrap(), VerifyOutcome::NotApplicable);
+    }
+    
+    #[test]
+    fn label_edits_keep_the_signature_but_amount_edits_need_a_resign() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let mut transaction = ShieldedTransaction::create_shielded_with_memo(
+            &sender, &recipient.address, 100, "rent", &recipient.viewing_public_key,
+        ).unwrap();
+        assert!(transaction.is_signature_valid());
+        
+        transaction.label = Some("rent".to_string());
+        assert!(transaction.is_signature_valid());
+        
+        transaction.amount += 1;
+        assert!(!transaction.is_signature_valid());
+        transaction.resign(&sender).unwrap();
+        assert!(transaction.is_signature_valid());
+    }
+    
+    #[test]
+    fn zero_change_is_empty_to_its_owner_and_opaque_to_everyone_else() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let mut transaction = ShieldedTransaction::create_shielded_to(&sender, &recipient.address, &recipient.viewing_public_key, 100).unwrap();
+        
+        let change = transaction.decrypt_notes(&sender.viewing_key);
+        assert_eq!(change.len(), 1);
+        assert!(change[0].is_empty());
+        assert!(sender.scan_outputs(&transaction).is_empty());
+        
+        // Publicly the change is just another commitment, not the sentinel
+        let change_commitment = transaction.output_commitments.last().unwrap();
+        assert_eq!(change_commitment, &change[0].commitment);
+        assert!(!CommitmentScheme::is_empty_sentinel(change_commitment));
+        assert!(transaction.validate().is_ok());
+        
+        *transaction.output_commitments.last_mut().unwrap() = crate::commitment::EMPTY_COMMITMENT.to_string();
+        assert!(transaction.validate().is_err());
+    }
+    
+    #[test]
+    fn namada_transfer_json_maps_to_a_valid_transaction() {
+        let cmu = "ab".repeat(32);
+        let json = serde_json::json!({
+            "source": address('a'),
+            "target": address('b'),
+            "token": "tnam1qxgfw7myv4dh0qna4hq0xdg6lx77fzl7dcem8h7e",
+            "amount": "1.5",
+            "shielded": "5b0f8c8d5e1e2c4f",
+            "masp_tx": { "sapling_bundle": { "shielded_outputs": [{ "cmu": cmu }] } },
+        });
+        
+        let transaction = ShieldedTransaction::from_namada_json(&json.to_string()).unwrap();
+        assert_eq!(transaction.amount, 1_500_000);
+        assert_eq!(transaction.from, address('a'));
+        assert_eq!(transaction.to, address('b'));
+        assert!(matches!(transaction.transaction_type, TransactionType::Shielded));
+        assert_eq!(transaction.output_commitments, vec![cmu]);
+        assert!(transaction.validate().is_ok());
+    }
+    
+    #[test]
+    fn namada_transfer_without_required_fields_is_rejected() {
+        let no_target = serde_json::json!({ "source": address('a'), "amount": 1_000 });
+        assert!(matches!(
+            ShieldedTransaction::from_namada_json(&no_target.to_string()),
+            Err(ShieldedError::SerializationError(_))
+        ));
+        
+        let no_amount = serde_json::json!({ "source": address('a'), "target": address('b') });
+        assert!(matches!(
+            ShieldedTransaction::from_namada_json(&no_amount.to_string()),
+            Err(ShieldedError::SerializationError(_))
+        ));
+    }
+    
+    #[test]
+    fn every_fee_rounding_keeps_the_transaction_balanced() {
+        use crate::policy::FeeRounding;
+        
+        for rounding in [FeeRounding::Floor, FeeRounding::Ceil, FeeRounding::Round] {
+            let policy = TransactionPolicy {
+                fee_policy: FeePolicy::Proportional { bps: 10, min: 0, rounding },
+                ..TransactionPolicy::default()
+            };
+            for amount in [1999, 2500] {
+                let transaction = ShieldedTransaction::create_shielded_with_policy(&test_wallet('a'), &address('b'), amount, &policy).unwrap();
+                assert_eq!(transaction.fee, policy.fee_policy.fee(amount));
+                assert!(transaction.is_balanced());
+            }
+        }
+    }
+    
+    #[test]
+    fn change_goes_to_the_change_wallet_or_else_the_sender() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let change_wallet = Wallet::new("alice-change").unwrap();
+        let policy = TransactionPolicy::default();
+        
+        let separate = ShieldedTransaction::create_shielded_with_change(
+            &sender, &recipient.address, &recipient.viewing_public_key, 1_000, Some(&change_wallet), &policy,
+        ).unwrap();
+        let change = separate.decrypt_notes(&change_wallet.viewing_key);
+        assert_eq!(change.len(), 1);
+        assert_eq!(change[0].owner, change_wallet.address);
+        assert!(separate.decrypt_notes(&sender.viewing_key).is_empty());
+        
+        let default = ShieldedTransaction::create_shielded_with_change(
+            &sender, &recipient.address, &recipient.viewing_public_key, 1_000, None, &policy,
+        ).unwrap();
+        let change = default.decrypt_notes(&sender.viewing_key);
+        assert_eq!(change.len(), 1);
+        assert_eq!(change[0].owner, sender.address);
+        assert!(default.decrypt_notes(&change_wallet.viewing_key).is_empty());
+    }
+    
+    #[test]
+    fn signatures_verify_under_the_recorded_scheme_only() {
+        let recipient = address('b');
+        let ed25519 = Wallet::new_with_scheme("alice", KeyScheme::Ed25519).unwrap();
+        let secp256k1 = Wallet::new_with_scheme("carol", KeyScheme::Secp256k1).unwrap();
+        
+        let from_ed25519 = ShieldedTransaction::create_public(&ed25519, &recipient, 1_000).unwrap();
+        let from_secp256k1 = ShieldedTransaction::create_public(&secp256k1, &recipient, 1_000).unwrap();
+        assert_eq!(from_ed25519.sig_scheme, KeyScheme::Ed25519);
+        assert_eq!(from_secp256k1.sig_scheme, KeyScheme::Secp256k1);
+        assert!(from_ed25519.is_signature_valid());
+        assert!(from_secp256k1.is_signature_valid());
+        
+        // A secp256k1 signature claimed as Ed25519 is rejected, and the other way round
+        let mut mislabeled = from_secp256k1.clone();
+        mislabeled.sig_scheme = KeyScheme::Ed25519;
+        assert!(!mislabeled.is_signature_valid());
+        let mut mislabeled = from_ed25519.clone();
+        mislabeled.sig_scheme = KeyScheme::Secp256k1;
+        assert!(!mislabeled.is_signature_valid());
+    }
+    
+    #[test]
+    fn public_verification_never_reaches_the_proof_check() {
+        let public = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        assert_eq!(public.verify_full().unwrap(), VerifyOutcome::Valid);
+        assert_eq!(public.verify_proof().unwrap(), VerifyOutcome::NotApplicable);
+        
+        // Even a proof that verifies elsewhere is refused as shielded data, not checked
+        let shielded = ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        let mut with_proof = public.clone();
+        with_proof.zk_proof = shielded.zk_proof.clone();
+        assert_eq!(
+            with_proof.verify_full().unwrap(),
+            VerifyOutcome::Invalid("public transaction carries shielded data".to_string())
+        );
+    }
+    
+    #[test]
+    fn create_with_fee_applies_each_policy() {
+        let create = |policy: &FeePolicy| ShieldedTransaction::create_with_fee(&test_wallet('a'), &address('b'), 10_000, policy).unwrap();
+        
+        let free = create(&FeePolicy::Flat(0));
+        assert_eq!(free.fee, 0);
+        assert!(free.is_balanced());
+        assert_eq!(create(&FeePolicy::Flat(7)).fee, 7);
+        assert_eq!(create(&FeePolicy::default()).fee, ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 10_000).unwrap().fee);
+        
+        let per_byte = create(&FeePolicy::PerByte(2));
+        assert!(per_byte.fee > 0 && per_byte.fee % 2 == 0);
+        assert!(per_byte.is_balanced());
+    }
+    
+    #[test]
+    fn tampering_with_the_amount_invalidates_the_wallet_signature() {
+        let sender = Wallet::new("alice").unwrap();
+        let mut transaction = ShieldedTransaction::create_public(&sender, &address('b'), 1_000).unwrap();
+        assert!(transaction.verify_signature(&sender.public_key));
+        assert!(!transaction.verify_signature(&Wallet::new("mallory").unwrap().public_key));
+        
+        transaction.amount += 1;
+        assert!(!transaction.verify_signature(&sender.public_key));
+    }
+    
+    #[test]
+    fn pending_transaction_is_invalid_once_expired() {
+        let mut transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000)
+            .unwrap()
+            .with_ttl(Duration::minutes(10));
+        transaction.resign(&test_wallet('a')).unwrap();
+        let expires_at = transaction.expires_at.unwrap();
+        
+        assert!(!transaction.is_expired(expires_at - Duration::seconds(1)));
+        assert!(transaction.is_expired(expires_at));
+        assert!(transaction.is_expired(expires_at + Duration::seconds(1)));
+        assert!(transaction.verify_full().unwrap().is_valid());
+        
+        let mut expired = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000)
+            .unwrap()
+            .with_expiry(Utc::now() - Duration::seconds(1));
+        expired.resign(&test_wallet('a')).unwrap();
+        assert!(expired.verify_full().unwrap().is_invalid());
+    }
+    
+    #[test]
+    fn only_pending_transactions_can_settle() {
+        let pending = || ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        
+        let mut confirmed = pending();
+        confirmed.confirm().unwrap();
+        assert_eq!(confirmed.status, TransactionStatus::Confirmed);
+        let mut failed = pending();
+        failed.fail("rejected by the node").unwrap();
+        assert_eq!(failed.status, TransactionStatus::Failed);
+        assert_eq!(failed.failure_reason.as_deref(), Some("rejected by the node"));
+        
+        for settled in [&mut confirmed, &mut failed] {
+            let status = settled.status;
+            assert!(matches!(settled.confirm(), Err(ShieldedError::InvalidTransaction(_))));
+            assert!(matches!(settled.fail("again"), Err(ShieldedError::InvalidTransaction(_))));
+            assert_eq!(settled.status, status);
+        }
+    }
+    
+    #[test]
+    fn same_seed_creates_the_same_transaction() {
+        use crate::crypto::{generate_keypair, with_seed};
+        
+        let create = |seed| with_seed(seed, || ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 5_000).unwrap());
+        let first = create(7);
+        let second = create(7);
+        assert_eq!(first.id, second.id);
+        assert_eq!(first.salt, second.salt);
+        assert_eq!(first.output_commitments, second.output_commitments);
+        assert_ne!(create(8).id, first.id);
+        
+        assert_eq!(with_seed(7, generate_keypair).unwrap(), with_seed(7, generate_keypair).unwrap());
+        // Outside the closure the thread RNG is back
+        assert_ne!(ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 5_000).unwrap().id, first.id);
+    }
+    
+    #[test]
+    fn only_a_matching_viewing_key_opens_the_output_value() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let auditor = Wallet::new("carol").unwrap();
+        let transaction = ShieldedTransaction::create_shielded_to(&sender, &recipient.address, &recipient.viewing_public_key, 700).unwrap();
+        
+        assert_eq!(transaction.decrypt_output_value(&recipient.viewing_key), Some(700));
+        assert_eq!(transaction.decrypt_output_value(&auditor.viewing_key), None);
+        // The spend key is not a viewing key
+        assert_ne!(recipient.viewing_key, recipient.private_key);
+        assert_eq!(transaction.decrypt_output_value(&recipient.private_key), None);
+    }
+    
+    #[test]
+    fn scanning_a_created_transaction_recovers_the_recipients_note() {
+        let sender = Wallet::new("alice").unwrap();
+        let mut recipient = Wallet::new("bob").unwrap();
+        let transaction = ShieldedTransaction::create_shielded_to(&sender, &recipient.address, &recipient.viewing_public_key, 700).unwrap();
+        
+        let notes = recipient.scan_outputs(&transaction);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].value, 700);
+        assert_eq!(notes[0].owner, recipient.address);
+        assert!(notes[0].is_consistent());
+        assert!(transaction.output_commitments.contains(&notes[0].commitment));
+        
+        assert_eq!(recipient.track(&transaction), 1);
+        assert_eq!(recipient.track(&transaction), 0);
+        assert_eq!(recipient.note_balance(), 700);
+        
+        // The tracked note funds the recipient's own spend, which leaves only the change
+        let spend = ShieldedTransaction::create_shielded_from_notes(&recipient, &sender.address, &sender.viewing_public_key, 300).unwrap();
+        recipient.track(&spend);
+        assert_eq!(recipient.note_balance(), 700 - 300 - spend.fee);
+        assert!(recipient.notes.iter().all(|note| note.commitment != notes[0].commitment));
+    }
+    
+    #[test]
+    fn creating_with_an_amount_of_u64_max_fails_cleanly() {
+        let is_invalid_amount = |result: Result<ShieldedTransaction>| matches!(result, Err(crate::error::ShieldedError::InvalidAmount(_)));
+        assert!(is_invalid_amount(ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), u64::MAX)));
+        assert!(is_invalid_amount(ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), u64::MAX)));
+        assert!(is_invalid_amount(ShieldedTransaction::create_with_fee(&test_wallet('a'), &address('b'), u64::MAX, &FeePolicy::Flat(1))));
+        let outputs = [(address('b'), u64::MAX - 1), (address('c'), 2)];
+        assert!(is_invalid_amount(ShieldedTransaction::create_public_multi(&test_wallet('a'), &outputs)));
+        
+        // The largest amount whose fee still fits goes through balanced
+        let largest = ShieldedTransaction::create_with_fee(&test_wallet('a'), &address('b'), u64::MAX - 1, &FeePolicy::Flat(1)).unwrap();
+        assert_eq!(largest.get_input_total().unwrap(), u64::MAX);
+        assert!(largest.is_balanced());
+    }
+    
+    #[test]
+    fn simulation_reports_the_fee_and_debit_of_a_real_creation() {
+        let simulation = ShieldedTransaction::simulate(&test_wallet('a'), &address('b'), 250_000).unwrap();
+        let created = ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 250_000).unwrap();
+        assert_eq!(simulation.fee, created.fee);
+        assert_eq!(simulation.total_debit, 250_000 + created.fee);
+        assert!(simulation.balanced);
+        assert_eq!(simulation.output_commitments.len(), created.output_commitments.len());
+        assert!(ShieldedTransaction::simulate(&test_wallet('a'), &address('b'), u64::MAX).is_err());
+    }
+    
+    /// Sign as `KeyScheme::Legacy` does, which only needs the public key
+    fn forge_legacy_signature(transaction: &mut ShieldedTransaction, public_key: &str) {
+        let mut hasher = Sha256::new();
+        hasher.update(transaction.signing_message());
+        hasher.update(hex::decode(public_key).unwrap());
+        transaction.signature = hex::encode(hasher.finalize());
+        transaction.signer_public_key = Some(public_key.to_string());
+        transaction.sig_scheme = KeyScheme::Legacy;
+    }
+    
+    #[test]
+    fn legacy_signatures_verify_only_when_allowed() {
+        let alice = test_wallet('a');
+        let mut forged = ShieldedTransaction::create_public(&alice, &address('b'), 1_000).unwrap();
+        forge_legacy_signature(&mut forged, &alice.public_key);
+        
+        assert!(!forged.is_signature_valid());
+        assert!(!forged.verify_signature(&alice.public_key));
+        assert_eq!(forged.verify_full().unwrap(), VerifyOutcome::Invalid("signature does not verify".to_string()));
+        
+        assert!(forged.verify_legacy_signature(&alice.public_key));
+        assert_eq!(forged.verify_full_with(true).unwrap(), VerifyOutcome::Valid);
+        forged.amount += 1;
+        assert!(forged.verify_full_with(true).unwrap().is_invalid());
+    }
+    
+    #[test]
+    fn unsigned_or_foreign_signed_transactions_are_invalid() {
+        let (alice, mallory) = (test_wallet('a'), test_wallet('m'));
+        for transaction in [
+            ShieldedTransaction::create_public(&alice, &address('b'), 1_000).unwrap(),
+            ShieldedTransaction::create_shielded(&alice, &address('b'), 1_000).unwrap(),
+        ] {
+            assert_eq!(transaction.verify_full().unwrap(), VerifyOutcome::Valid);
+            
+            let mut unsigned = transaction.clone();
+            unsigned.signer_public_key = None;
+            assert!(!unsigned.is_signature_valid());
+            assert_eq!(unsigned.verify_full().unwrap(), VerifyOutcome::Invalid("transaction is not signed".to_string()));
+            
+            // A valid signature, but by a key that isn't behind `from`
+            let mut impersonated = transaction.clone();
+            impersonated.resign(&mallory).unwrap();
+            assert!(impersonated.verify_signature(&mallory.public_key));
+            assert!(!impersonated.is_signature_valid());
+            assert_eq!(
+                impersonated.verify_full().unwrap(),
+                VerifyOutcome::Invalid("signer does not own the sending address".to_string())
+            );
+        }
+    }
+    
+    #[test]
+    fn signature_and_merkle_leaf_hash_the_signed_bytes() {
+        let transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        let digest = |tag: &[u8]| {
+            let mut hasher = Sha256::new();
+            hasher.update(tag);
+            hasher.update(transaction.signed_bytes());
+            hasher.finalize().to_vec()
+        };
+        assert_eq!(transaction.signing_message(), digest(b"tx_signature"));
+        assert_eq!(transaction.merkle_leaf(), hex::encode(digest(b"tx_leaf")));
+        
+        let mut bookkeeping = transaction.clone();
+        bookkeeping.signature = "other".to_string();
+        bookkeeping.signer_public_key = None;
+        bookkeeping.status = TransactionStatus::Confirmed;
+        bookkeeping.label = Some("rent".to_string());
+        bookkeeping.prev_hash = Some("prev".to_string());
+        bookkeeping.pow_nonce = Some(1);
+        bookkeeping.failure_reason = Some("reason".to_string());
+        assert_eq!(bookkeeping.signed_bytes(), transaction.signed_bytes());
+        
+        let sequenced = transaction.clone().with_sequence(1);
+        assert_ne!(sequenced.signed_bytes(), transaction.signed_bytes());
+        assert_ne!(sequenced.merkle_leaf(), transaction.merkle_leaf());
+        assert!(!sequenced.is_signature_valid());
+    }
+}
+
+#[cfg(all(test, feature = "borsh"))]
+mod borsh_tests {
+    use super::*;
+    use crate::wallet::fixtures::{address, test_wallet};
+    
+    #[test]
+    fn borsh_round_trips() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let transactions = [
+            ShieldedTransaction::create_public(&sender, &recipient.address, 1_000).unwrap(),
+            ShieldedTransaction::create_shielded_to(&sender, &recipient.address, &recipient.viewing_public_key, 1_000)
+                .unwrap()
+                .with_ttl(Duration::seconds(60)),
+        ];
+        for transaction in transactions {
+            let bytes = transaction.to_borsh().unwrap();
+            let decoded = ShieldedTransaction::from_borsh(&bytes).unwrap();
+            assert_eq!(decoded.to_borsh().unwrap(), bytes);
+            assert_eq!(decoded.to_json().unwrap(), transaction.to_json().unwrap());
+        }
+    }
+    
+    #[test]
+    fn borsh_encoding_is_fixed() {
+        let mut transaction = ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), 1_000).unwrap().with_sequence(7);
+        transaction.signer_public_key = None;
+        transaction.id = "id".to_string();
+        transaction.from = "alice".to_string();
+        transaction.to = "bob".to_string();
+        transaction.signature = "sig".to_string();
+        transaction.timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        transaction.salt = None;
+        
+        let expected: Vec<u8> = [
+            &b"\x02\0\0\0id"[..],                   // id
+            b"\x05\0\0\0alice",                     // from
+            b"\x03\0\0\0bob",                       // to
+            b"\xe8\x03\0\0\0\0\0\0",                // amount
+            b"\x01\0\0\0\0\0\0\0",                  // fee
+            b"\x00",                                // transaction_type: Public
+            b"\0\0\0\0",                            // input_commitments
+            b"\0\0\0\0",                            // output_commitments
+            b"\x00",                                // zk_proof: None
+            b"\x03\0\0\0sig",                       // signature
+            b"\x00\xf1\x53\x65\0\0\0\0\0\0\0\0",    // timestamp: seconds, nanoseconds
+            b"\x00",                                // status: Pending
+            b"\0\0\0\0",                            // encrypted_notes
+            b"\x00",                                // encrypted_memo: None
+            b"\0\0\0\0",                            // input_value_commitments
+            b"\0\0\0\0",                            // output_value_commitments
+            b"\x00",                                // signer_public_key: None
+            b"\x01",                                // sig_scheme: Ed25519
+            b"\x00",                                // label: None
+            b"\x01\x07\0\0\0\0\0\0\0",              // sequence: Some(7)
+            b"\x00",                                // prev_hash: None
+            b"\0\0\0\0",                            // public_outputs
+            b"\0\0\0\0",                            // nullifiers
+            b"\x00",                                // anchor: None
+            b"\x00",                                // pow_nonce: None
+            b"\x00",                                // expires_at: None
+            b"\x00",                                // failure_reason: None
+            b"\x00",                                // salt: None
+            b"\x03\0\0\0NAM",                       // asset
+            b"\0\0\0\0",                            // input_values
+            b"\0\0\0\0",                            // output_values
+            b"\x00",                                // memo_commitment: None
+        ].concat();
+        assert_eq!(transaction.to_borsh().unwrap(), expected);
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    error::{Result, ShieldedError},
+    merkle_tree::{MerkleTree, EMPTY_ROOT},
+    policy::TransactionPolicy,
+    shielded_transaction::ShieldedTransaction,
+    storage::{spent_inputs, InsertChecks, Storage, GENESIS_HASH},
+};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Tables created on open; `leaves` is the transaction log, with the Merkle root
+/// after each append so anchors can be looked up without rebuilding the tree
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS transactions (
+        id TEXT PRIMARY KEY,
+        sender TEXT NOT NULL,
+        sequence INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS transactions_by_sender ON transactions (sender, sequence);
+    CREATE TABLE IF NOT EXISTS leaves (
+        position INTEGER PRIMARY KEY,
+        transaction_id TEXT NOT NULL,
+        root TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS leaves_by_root ON leaves (root);
+    CREATE TABLE IF NOT EXISTS nullifiers (
+        nullifier TEXT PRIMARY KEY,
+        transaction_id TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS spent_commitments (
+        commitment TEXT PRIMARY KEY,
+        transaction_id TEXT NOT NULL
+    );
+";
+
+/// Transaction store backed by a SQLite database, one row per transaction
+///
+/// Adding a transaction writes only its own rows, where `StorageData` rewrites
+/// a whole shard file.
+pub struct SqliteStorage {
+    connection: Connection,
+    /// Tree over each logged transaction's `merkle_leaf`, rebuilt on open
+    merkle_tree: MerkleTree,
+    pub policy: TransactionPolicy,
+}
+
+impl SqliteStorage {
+    /// Open a database file, creating it and its tables if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_connection(Connection::open(path).map_err(sql_error)?)
+    }
+
+    /// Open a database that lives only as long as this value
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(sql_error)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(sql_error)?;
+        let mut storage = Self {
+            connection,
+            merkle_tree: MerkleTree::new(),
+            policy: TransactionPolicy::default(),
+        };
+        storage.merkle_tree = storage.rebuild_merkle_tree()?;
+        Ok(storage)
+    }
+
+    /// The Merkle tree over the transaction log
+    pub fn merkle_tree(&self) -> &MerkleTree {
+        &self.merkle_tree
+    }
+
+    /// Rebuild the Merkle tree from the stored log
+    ///
+    /// An id whose transaction is missing is committed as-is, as in `StorageData`.
+    pub fn rebuild_merkle_tree(&self) -> Result<MerkleTree> {
+        let mut statement = self.connection
+            .prepare(
+                "SELECT leaves.transaction_id, transactions.data FROM leaves
+                 LEFT JOIN transactions ON transactions.id = leaves.transaction_id
+                 ORDER BY leaves.position",
+            )
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+            .map_err(sql_error)?;
+        
+        let mut leaves = Vec::new();
+        for row in rows {
+            let (id, data) = row.map_err(sql_error)?;
+            leaves.push(match data {
+                Some(data) => decode(&data)?.merkle_leaf(),
+                None => id,
+            });
+        }
+        MerkleTree::from_leaves(&leaves)
+    }
+
+    /// Sequence number the sender's next transaction must carry
+    pub fn expected_sequence(&self, sender: &str) -> Result<u64> {
+        let last: Option<i64> = self.connection
+            .query_row("SELECT MAX(sequence) FROM transactions WHERE sender = ?1", params![sender], |row| row.get(0))
+            .map_err(sql_error)?;
+        match last {
+            None => Ok(0),
+            Some(last) => u64::try_from(last)
+                .ok()
+                .and_then(|last| last.checked_add(1))
+                .ok_or_else(|| ShieldedError::StorageError(format!("Stored sequence {} for {} is out of range", last, sender))),
+        }
+    }
+
+    /// Whether a nullifier has been revealed by a stored transaction
+    pub fn is_spent(&self, nullifier: &str) -> Result<bool> {
+        self.exists("SELECT 1 FROM nullifiers WHERE nullifier = ?1", nullifier)
+    }
+
+    /// Whether a stored shielded transaction has spent this input commitment
+    pub fn is_commitment_spent(&self, commitment: &str) -> Result<bool> {
+        self.exists("SELECT 1 FROM spent_commitments WHERE commitment = ?1", commitment)
+    }
+
+    /// Whether `anchor` is a root the tree has had, including the empty root
+    pub fn is_known_anchor(&self, anchor: &str) -> Result<bool> {
+        if anchor == EMPTY_ROOT {
+            return Ok(true);
+        }
+        self.exists("SELECT 1 FROM leaves WHERE root = ?1", anchor)
+    }
+
+    fn exists(&self, query: &str, value: &str) -> Result<bool> {
+        self.connection
+            .query_row(query, params![value], |_| Ok(()))
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(sql_error)
+    }
+
+    /// `chain_hash` of the last transaction in the log, or the genesis hash
+    fn chain_tip(&self) -> Result<String> {
+        let last: Option<String> = self.connection
+            .query_row("SELECT transaction_id FROM leaves ORDER BY position DESC LIMIT 1", [], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        let transaction = match last {
+            Some(id) => Storage::get_transaction(self, &id)?,
+            None => None,
+        };
+        Ok(transaction.map_or_else(|| GENESIS_HASH.to_string(), |transaction| transaction.chain_hash()))
+    }
+
+    /// Write a transaction, its log entry, its nullifiers and its spent inputs in one
+    /// database transaction
+    ///
+    /// SQLite integers are signed, so a sequence past `i64::MAX` is refused rather
+    /// than stored wrapped.
+    fn insert(&mut self, transaction: &ShieldedTransaction, root: &str) -> Result<()> {
+        let data = serde_json::to_string(transaction)?;
+        let sequence = transaction.sequence
+            .map(|sequence| {
+                i64::try_from(sequence).map_err(|_| ShieldedError::InvalidTransaction(
+                    format!("Transaction {} has sequence {}, too large to store", transaction.id, sequence)
+                ))
+            })
+            .transpose()?;
+        let sql = self.connection.transaction().map_err(sql_error)?;
+        sql.execute(
+            "INSERT INTO transactions (id, sender, sequence, data) VALUES (?1, ?2, ?3, ?4)",
+            params![transaction.id, transaction.from, sequence, data],
+        )
+        .map_err(sql_error)?;
+        sql.execute(
+            "INSERT INTO leaves (transaction_id, root) VALUES (?1, ?2)",
+            params![transaction.id, root],
+        )
+        .map_err(sql_error)?;
+        for nullifier in &transaction.nullifiers {
+            sql.execute(
+                "INSERT INTO nullifiers (nullifier, transaction_id) VALUES (?1, ?2)",
+                params![nullifier, transaction.id],
+            )
+            .map_err(sql_error)?;
+        }
+        for commitment in spent_inputs(transaction) {
+            sql.execute(
+                "INSERT INTO spent_commitments (commitment, transaction_id) VALUES (?1, ?2)",
+                params![commitment, transaction.id],
+            )
+            .map_err(sql_error)?;
+        }
+        sql.commit().map_err(sql_error)
+    }
+}
+
+impl InsertChecks for SqliteStorage {
+    fn policy(&self) -> &TransactionPolicy {
+        &self.policy
+    }
+
+    fn is_stored(&self, id: &str) -> Result<bool> {
+        self.exists("SELECT 1 FROM transactions WHERE id = ?1", id)
+    }
+
+    fn next_sequence(&self, sender: &str) -> Result<u64> {
+        self.expected_sequence(sender)
+    }
+
+    fn known_anchors(&self, anchors: &[&str]) -> Result<HashSet<String>> {
+        let mut known = HashSet::new();
+        for anchor in anchors {
+            if self.is_known_anchor(anchor)? {
+                known.insert(anchor.to_string());
+            }
+        }
+        Ok(known)
+    }
+
+    fn nullifier_spent(&self, nullifier: &str) -> Result<bool> {
+        self.is_spent(nullifier)
+    }
+
+    fn commitment_spent(&self, commitment: &str) -> Result<bool> {
+        self.is_commitment_spent(commitment)
+    }
+
+    fn sent_on(&self, sender: &str, day: NaiveDate) -> Result<u64> {
+        let mut statement = self.connection
+            .prepare("SELECT data FROM transactions WHERE sender = ?1")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map(params![sender], |row| row.get::<_, String>(0))
+            .map_err(sql_error)?;
+        
+        let mut total = 0u64;
+        for row in rows {
+            let transaction = decode(&row.map_err(sql_error)?)?;
+            if transaction.timestamp.date_naive() == day {
+                total = total.saturating_add(transaction.amount);
+            }
+        }
+        Ok(total)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn add_transaction(&mut self, mut transaction: ShieldedTransaction) -> Result<()> {
+        self.check_insert(std::slice::from_ref(&transaction))?;
+        transaction.prev_hash = Some(self.chain_tip()?);
+        
+        self.merkle_tree.add_leaf(&transaction.merkle_leaf())?;
+        let root = self.merkle_tree.root();
+        if let Err(e) = self.insert(&transaction, &root) {
+            // Nothing was written, so put the tree back in step with the database
+            self.merkle_tree = self.rebuild_merkle_tree()?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn get_transaction(&self, id: &str) -> Result<Option<ShieldedTransaction>> {
+        let data: Option<String> = self.connection
+            .query_row("SELECT data FROM transactions WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        data.map(|data| decode(&data)).transpose()
+    }
+
+    fn get_all_transactions(&self) -> Result<HashMap<String, ShieldedTransaction>> {
+        let mut statement = self.connection
+            .prepare("SELECT data FROM transactions")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sql_error)?;
+        
+        let mut transactions = HashMap::new();
+        for row in rows {
+            let transaction = decode(&row.map_err(sql_error)?)?;
+            transactions.insert(transaction.id.clone(), transaction);
+        }
+        Ok(transactions)
+    }
+
+    fn merkle_leaves(&self) -> Result<Vec<String>> {
+        let mut statement = self.connection
+            .prepare("SELECT transaction_id FROM leaves ORDER BY position")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| row.get(0))
+            .map_err(sql_error)?;
+        rows.collect::<rusqlite::Result<Vec<String>>>().map_err(sql_error)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.connection
+            .execute_batch("DELETE FROM transactions; DELETE FROM leaves; DELETE FROM nullifiers; DELETE FROM spent_commitments;")
+            .map_err(sql_error)?;
+        self.merkle_tree = MerkleTree::new();
+        Ok(())
+    }
+}
+
+/// Rows were validated on insert, so they are read back as stored, like the JSON files
+fn decode(data: &str) -> Result<ShieldedTransaction> {
+    serde_json::from_str(data).map_err(ShieldedError::SerializationError)
+}
+
+fn sql_error(e: rusqlite::Error) -> ShieldedError {
+    ShieldedError::StorageError(format!("SQLite error: {}", e))
+}
@@ -1,16 +1,340 @@
-use crate::{error::Result, shielded_transaction::ShieldedTransaction, merkle_tree::MerkleTree};
+use crate::{
+    error::Result,
+    asset::AssetId,
+    shielded_transaction::{ShieldedTransaction, TransactionStatus, TransactionType, VerifyOutcome},
+    merkle_tree::{MerkleProof, MerkleTree},
+    policy::TransactionPolicy,
+    note::Note,
+    wallet::Wallet,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::de::{DeserializeOwned, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
+/// Legacy single-file transaction store, migrated into shards on the next save
 const STORAGE_FILE: &str = "transactions.json";
+/// Directory holding one `<shard>.json` file per leading id byte
+const SHARD_DIR: &str = "transactions";
+/// Transaction log; files from before `TREE_FILE` also hold the Merkle tree
 const MERKLE_FILE: &str = "merkle_tree.json";
+/// Merkle tree as of the last full save, checked and brought up to date on load
+const TREE_FILE: &str = "merkle_nodes.json";
+/// Archived transactions, kept out of the shards so default queries skip them
+const ARCHIVE_FILE: &str = "archived.json";
+/// Spent input commitments, written by older versions; the set is now rebuilt on load
+const SPENT_FILE: &str = "spent.json";
+/// Layout version written into every storage file; files without one are version 1
+pub const CURRENT_VERSION: u32 = 2;
+/// `prev_hash` recorded by the first transaction in the log
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Leading bytes of a gzip stream, used to tell compressed files from plain JSON
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Spend status of a commitment as seen from the stored transactions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommitmentStatus {
+    Unspent,
+    Spent,
+    Unknown,
+}
+
+/// Where a commitment was created and spent, as shown by a block explorer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitmentLifecycle {
+    pub commitment: String,
+    pub created_in: Option<String>,
+    pub spent_in: Option<String>,
+    pub status: CommitmentStatus,
+}
+
+/// A note found by scanning, with the transaction that created it and its spend status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceivedNote {
+    pub transaction_id: String,
+    pub note: Note,
+    pub status: CommitmentStatus,
+}
+
+/// Result of walking the transaction hash chain in log order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainReport {
+    /// Transactions whose link to the previous one checked out
+    pub verified: usize,
+    /// The first transaction with a broken link and every one after it
+    pub broken: Vec<String>,
+}
+
+impl ChainReport {
+    pub fn is_intact(&self) -> bool {
+        self.broken.is_empty()
+    }
+}
+
+/// Aggregate figures over the stored transactions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub total_transactions: usize,
+    pub public_transactions: usize,
+    pub shielded_transactions: usize,
+    /// Transferred amounts summed per asset, since amounts of different assets don't add up
+    pub amount_by_asset: BTreeMap<AssetId, u64>,
+    /// Fees summed per asset; a fee is paid in its transaction's asset
+    pub fees_by_asset: BTreeMap<AssetId, u64>,
+    /// Recipient outputs below the policy's dust limit (change is not counted)
+    pub dust_outputs: usize,
+    /// Encoded bytes of note and value commitments across shielded transactions
+    pub commitment_bytes_total: usize,
+    /// Encoded bytes of zero-knowledge proofs across shielded transactions
+    pub proof_bytes_total: usize,
+    /// Average commitment bytes per shielded transaction
+    pub commitment_bytes_avg: f64,
+    /// Average proof bytes per shielded transaction
+    pub proof_bytes_avg: f64,
+}
+
+/// Transaction ids that differ between two stores, each list sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageDiff {
+    pub only_here: Vec<String>,
+    pub only_there: Vec<String>,
+    /// Ids present in both stores whose content hashes differ
+    pub conflicting: Vec<String>,
+}
+
+/// What `StorageData::merge` did
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// Ids added from the other store, in the order they were appended
+    pub added: Vec<String>,
+    /// Ids in both stores with different content; the local copy was kept
+    pub conflicts: Vec<String>,
+    /// Incoming ids left out because they reveal a nullifier or spend an input
+    /// commitment already spent here
+    pub nullifier_conflicts: Vec<String>,
+    /// Incoming ids refused by the insert checks, with the reason
+    #[serde(default)]
+    pub rejected: Vec<(String, String)>,
+    /// Merkle root after the merge
+    pub merkle_root: String,
+}
+
+/// Outcome of verifying the stored transactions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifySummary {
+    pub checked: usize,
+    pub passed: Vec<String>,
+    /// Failed transaction ids with the reason each one failed
+    pub failed: Vec<(String, String)>,
+    /// Set when `fail_fast` stopped verification before every transaction was checked
+    pub stopped_early: bool,
+}
+
+impl VerifySummary {
+    pub fn all_passed(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Outcome of each check on one stored transaction, from `StorageData::verification_reports`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    /// The id recomputes from the transaction's fields and salt
+    pub id: VerifyOutcome,
+    /// Timestamp window, expiry and commitment sentinels, as checked by `validate`
+    pub validity: VerifyOutcome,
+    /// Amounts, and for shielded transactions the value commitments, balance
+    pub balance: VerifyOutcome,
+    /// The signature, made by the key that owns the sending address
+    pub signature: VerifyOutcome,
+    /// The zero-knowledge proof, for shielded transactions
+    pub proof: VerifyOutcome,
+    /// The transaction's leaf proves into the current Merkle root
+    pub merkle_inclusion: VerifyOutcome,
+    /// Any cited anchor is a root the tree has had
+    pub anchor: VerifyOutcome,
+    /// Proof of work at the policy's difficulty
+    pub pow: VerifyOutcome,
+}
+
+impl VerificationReport {
+    /// Each check's name and outcome, in the order they are reported
+    pub fn checks(&self) -> [(&'static str, &VerifyOutcome); 8] {
+        [
+            ("id", &self.id),
+            ("validity", &self.validity),
+            ("balance", &self.balance),
+            ("signature", &self.signature),
+            ("proof", &self.proof),
+            ("merkle_inclusion", &self.merkle_inclusion),
+            ("anchor", &self.anchor),
+            ("pow", &self.pow),
+        ]
+    }
+    
+    /// Whether no check came out invalid
+    pub fn passed(&self) -> bool {
+        self.checks().iter().all(|(_, outcome)| !outcome.is_invalid())
+    }
+}
+
+/// Criteria for `StorageData::query_transactions`; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionFilter {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub transaction_type: Option<TransactionType>,
+    pub status: Option<TransactionStatus>,
+    /// Earliest timestamp to include
+    pub since: Option<DateTime<Utc>>,
+    /// Timestamps from this one on are left out
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl TransactionFilter {
+    pub fn matches(&self, transaction: &ShieldedTransaction) -> bool {
+        self.from.as_ref().is_none_or(|from| transaction.from == *from)
+            && self.to.as_ref().is_none_or(|to| transaction.to == *to)
+            && self.transaction_type.is_none_or(|transaction_type| transaction.transaction_type == transaction_type)
+            && self.status.is_none_or(|status| transaction.status == status)
+            && self.since.is_none_or(|since| transaction.timestamp >= since)
+            && self.until.is_none_or(|until| transaction.timestamp < until)
+    }
+}
+
+/// When stored transactions are verified after loading
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerifyMode {
+    /// Trust the store as loaded
+    #[default]
+    None,
+    /// Verify everything up front and fail the load on any invalid transaction
+    Eager,
+    /// Verify each transaction the first time it is read, caching the result
+    Lazy,
+}
+
+impl FromStr for VerifyMode {
+    type Err = crate::error::ShieldedError;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(VerifyMode::None),
+            "eager" => Ok(VerifyMode::Eager),
+            "lazy" => Ok(VerifyMode::Lazy),
+            other => Err(crate::error::ShieldedError::StorageError(
+                format!("Unknown verify mode '{}', expected none, eager or lazy", other)
+            )),
+        }
+    }
+}
+
+/// Lazy verification results by transaction id; `Err` holds the failure reason
+#[derive(Debug, Default)]
+struct VerifyCache(Mutex<HashMap<String, std::result::Result<(), String>>>);
+
+impl Clone for VerifyCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()))
+    }
+}
+
+/// `MERKLE_FILE` contents as written on every add
+#[derive(Serialize)]
+struct MerkleFile<'a> {
+    version: u32,
+    transaction_ids: &'a [String],
+    tip_hash: Option<&'a str>,
+}
+
+/// `MERKLE_FILE` contents after migration; only files written between the tree
+/// being persisted and `TREE_FILE` carry a tree
+#[derive(Deserialize)]
+struct StoredMerkleFile {
+    transaction_ids: Vec<String>,
+    #[serde(default)]
+    tree: Option<MerkleTree>,
+    #[serde(default)]
+    tip_hash: Option<String>,
+}
+
+/// `TREE_FILE` contents as written by `save`
+#[derive(Serialize)]
+struct TreeFile<'a> {
+    version: u32,
+    tree: &'a MerkleTree,
+}
+
+/// `TREE_FILE` contents after migration
+#[derive(Deserialize)]
+struct StoredTreeFile {
+    tree: MerkleTree,
+}
+
+/// A shard, archive or legacy transactions file as written by `save`
+#[derive(Serialize)]
+struct TransactionsFile<'a, M: Serialize> {
+    version: u32,
+    transactions: &'a M,
+}
+
+/// A transactions file after migration
+#[derive(Deserialize)]
+struct StoredTransactions {
+    transactions: HashMap<String, ShieldedTransaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageData {
     pub transactions: HashMap<String, ShieldedTransaction>,
+    /// Transaction ids in log order; leaf `i` of the Merkle tree commits to the `i`th.
+    /// Saved as a list, so the order never depends on map iteration
     pub merkle_leaves: Vec<String>,
+    /// Tree over each logged transaction's `merkle_leaf`, updated as transactions are added
+    #[serde(skip)]
+    merkle_tree: MerkleTree,
+    #[serde(skip)]
+    pub policy: TransactionPolicy,
+    /// Gzip storage files on save; loading detects either format
+    #[serde(skip)]
+    pub compress: bool,
+    #[serde(skip)]
+    verify_mode: VerifyMode,
+    /// Accept forgeable legacy-scheme signatures when verifying; see `load_from_allowing_legacy`
+    #[serde(skip)]
+    pub allow_legacy_signatures: bool,
+    #[serde(skip)]
+    verified: VerifyCache,
+    /// Every nullifier revealed by a stored transaction, rebuilt on load
+    #[serde(skip)]
+    nullifiers: HashSet<String>,
+    /// Input commitments consumed by stored shielded transactions, rebuilt on load
+    #[serde(skip)]
+    spent_commitments: HashSet<String>,
+    /// Transactions hidden by `archive_transaction`; their Merkle leaves stay in place
+    #[serde(skip)]
+    archived: HashMap<String, ShieldedTransaction>,
+    /// Directory the storage files live in, relative to the working directory if not absolute
+    #[serde(skip)]
+    dir: PathBuf,
+    /// `chain_hash` of the newest logged transaction as it was appended, saved with
+    /// the log so `verify_chain` covers that transaction too; absent in older logs
+    #[serde(skip)]
+    tip_hash: Option<String>,
+}
+
+impl Default for StorageData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StorageData {
@@ -18,65 +342,1007 @@ impl StorageData {
         Self {
             transactions: HashMap::new(),
             merkle_leaves: Vec::new(),
+            merkle_tree: MerkleTree::new(),
+            policy: TransactionPolicy::default(),
+            compress: false,
+            verify_mode: VerifyMode::None,
+            allow_legacy_signatures: false,
+            verified: VerifyCache::default(),
+            nullifiers: HashSet::new(),
+            spent_commitments: HashSet::new(),
+            archived: HashMap::new(),
+            dir: PathBuf::new(),
+            tip_hash: None,
+        }
+    }
+
+    /// Take an immutable point-in-time copy for consistent multi-read operations
+    pub fn snapshot(&self) -> StorageSnapshot {
+        StorageSnapshot {
+            data: Arc::new(self.clone()),
         }
     }
 
-    /// Load data from storage files
+    /// Load data from storage files in the working directory without verifying it
     pub fn load() -> Result<Self> {
+        Self::load_with(VerifyMode::None)
+    }
+
+    /// Like `load`, verifying transactions as `mode` says
+    pub fn load_with(mode: VerifyMode) -> Result<Self> {
+        Self::load_from_with(Path::new(""), mode)
+    }
+
+    /// Load data from storage files in `dir` without verifying it
+    ///
+    /// Later saves write back to the same directory.
+    pub fn load_from(dir: &Path) -> Result<Self> {
+        Self::load_from_with(dir, VerifyMode::None)
+    }
+
+    /// Load data from storage files in `dir`, verifying transactions as `mode` says
+    pub fn load_from_with(dir: &Path, mode: VerifyMode) -> Result<Self> {
+        Self::load_from_with_options(dir, mode, false)
+    }
+
+    /// Like `load_from_with`, but legacy-scheme signatures verify
+    ///
+    /// Only for stores written before a secure signature scheme was required: anyone
+    /// can forge a legacy signature for any public key.
+    pub fn load_from_allowing_legacy(dir: &Path, mode: VerifyMode) -> Result<Self> {
+        Self::load_from_with_options(dir, mode, true)
+    }
+
+    fn load_from_with_options(dir: &Path, mode: VerifyMode, allow_legacy_signatures: bool) -> Result<Self> {
         let mut data = Self::new();
+        data.dir = dir.to_path_buf();
+        data.verify_mode = mode;
+        data.allow_legacy_signatures = allow_legacy_signatures;
+        
+        // Load transactions from the legacy single file, if it is still around
+        if data.path(STORAGE_FILE).exists() {
+            let file: StoredTransactions = read_versioned(&data.path(STORAGE_FILE), "transactions file")?;
+            data.transactions.extend(file.transactions);
+        }
+        
+        // Load transactions from every shard file
+        if data.path(SHARD_DIR).exists() {
+            let entries = fs::read_dir(data.path(SHARD_DIR))
+                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to read shard directory: {}", e)))?;
+            for entry in entries {
+                let path = entry
+                    .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to read shard directory: {}", e)))?
+                    .path();
+                if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                    let file: StoredTransactions = read_versioned(&path, "transaction shard")?;
+                    data.transactions.extend(file.transactions);
+                }
+            }
+        }
+        
+        // Load the transaction log and the tree from the last full save
+        let mut saved_tree = None;
+        if data.path(MERKLE_FILE).exists() {
+            let file: StoredMerkleFile = read_versioned(&data.path(MERKLE_FILE), "Merkle tree file")?;
+            data.merkle_leaves = file.transaction_ids;
+            data.tip_hash = file.tip_hash;
+            saved_tree = file.tree;
+        }
+        if data.path(TREE_FILE).exists() {
+            let file: StoredTreeFile = read_versioned(&data.path(TREE_FILE), "Merkle tree cache")?;
+            saved_tree = Some(file.tree);
+        }
+        
+        if data.path(ARCHIVE_FILE).exists() {
+            let file: StoredTransactions = read_versioned(&data.path(ARCHIVE_FILE), "archive file")?;
+            data.archived = file.transactions;
+        }
         
-        // Load transactions
-        if Path::new(STORAGE_FILE).exists() {
-            let content = fs::read_to_string(STORAGE_FILE)
-                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to read transactions file: {}", e)))?;
-            data.transactions = serde_json::from_str(&content)
-                .map_err(|e| crate::error::ShieldedError::SerializationError(e))?;
+        // The log is renamed into place last, so a save interrupted after some shards
+        // were moved can leave transactions the log never recorded
+        if data.path(MERKLE_FILE).exists() {
+            let logged: HashSet<&String> = data.merkle_leaves.iter().collect();
+            let unlogged: Vec<String> = data.transactions
+                .keys()
+                .chain(data.archived.keys())
+                .filter(|id| !logged.contains(id))
+                .cloned()
+                .collect();
+            for id in unlogged {
+                tracing::warn!("Dropping transaction {} left over from an interrupted save", id);
+                data.transactions.remove(&id);
+                data.archived.remove(&id);
+            }
         }
         
-        // Load Merkle tree leaves
-        if Path::new(MERKLE_FILE).exists() {
-            let content = fs::read_to_string(MERKLE_FILE)
-                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to read Merkle tree file: {}", e)))?;
-            data.merkle_leaves = serde_json::from_str(&content)
-                .map_err(|e| crate::error::ShieldedError::SerializationError(e))?;
+        // The saved tree is only reused if its leaves and root match the stored
+        // transactions; leaves logged after it was saved are appended
+        let leaf_data = data.merkle_leaf_data();
+        data.merkle_tree = match saved_tree.map(|tree| tree.resume(&leaf_data)) {
+            Some(Some(tree)) => tree,
+            Some(None) => {
+                tracing::warn!("Saved Merkle tree does not match the stored transactions; rebuilding it");
+                data.rebuild_merkle_tree()
+            }
+            None => data.rebuild_merkle_tree(),
+        };
+        
+        data.nullifiers = data.transactions
+            .values()
+            .chain(data.archived.values())
+            .flat_map(|transaction| transaction.nullifiers.iter().cloned())
+            .collect();
+        
+        data.spent_commitments = data.transactions
+            .values()
+            .chain(data.archived.values())
+            .flat_map(spent_inputs)
+            .cloned()
+            .collect();
+        
+        if mode == VerifyMode::Eager {
+            let summary = data.verify_all(true);
+            if let Some((id, reason)) = summary.failed.first() {
+                return Err(crate::error::ShieldedError::InvalidTransaction(
+                    format!("Transaction {} failed verification on load: {}", id, reason)
+                ));
+            }
         }
         
         Ok(data)
     }
 
+    /// Stream a transactions file (plain or gzipped JSON map) from any reader
+    ///
+    /// Entries are deserialized one at a time, so peak memory is the map itself
+    /// plus a small read buffer rather than a copy of the whole file.
+    pub fn read_transactions(reader: impl Read) -> Result<HashMap<String, ShieldedTransaction>> {
+        read_json_from(reader, "transactions")
+    }
+
     /// Save data to storage files
+    ///
+    /// Every file is written to a temporary first and only renamed into place once
+    /// all of them are written, the transaction log last, so a failed save leaves the
+    /// previous files as they were. Shards no longer needed are removed afterwards.
     pub fn save(&self) -> Result<()> {
-        // Save transactions
-        let transactions_json = serde_json::to_string_pretty(&self.transactions)
-            .map_err(|e| crate::error::ShieldedError::SerializationError(e))?;
-        fs::write(STORAGE_FILE, transactions_json)
-            .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to write transactions file: {}", e)))?;
-        
-        // Save Merkle tree leaves
-        let merkle_json = serde_json::to_string_pretty(&self.merkle_leaves)
-            .map_err(|e| crate::error::ShieldedError::SerializationError(e))?;
-        fs::write(MERKLE_FILE, merkle_json)
-            .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to write Merkle tree file: {}", e)))?;
+        let mut shards: BTreeMap<String, HashMap<&String, &ShieldedTransaction>> = BTreeMap::new();
+        for (id, transaction) in &self.transactions {
+            shards.entry(shard_for(id)).or_default().insert(id, transaction);
+        }
+        
+        let mut batch = WriteBatch::default();
+        for (shard, transactions) in &shards {
+            let file = TransactionsFile { version: CURRENT_VERSION, transactions };
+            batch.stage(&self.shard_path(shard), &file, "transaction shard", self.compress)?;
+        }
+        if !self.archived.is_empty() || self.path(ARCHIVE_FILE).exists() {
+            self.stage_archive(&mut batch)?;
+        }
+        self.stage_tree(&mut batch)?;
+        self.stage_merkle_leaves(&mut batch)?;
+        batch.commit()?;
+        
+        // Only now that the new shards are in place can the stale ones go
+        if let Ok(entries) = fs::read_dir(self.path(SHARD_DIR)) {
+            for path in entries.flatten().map(|entry| entry.path()) {
+                let is_stale = path.extension().and_then(|ext| ext.to_str()) == Some("json")
+                    && path.file_stem().and_then(|stem| stem.to_str()).is_some_and(|shard| !shards.contains_key(shard));
+                if is_stale {
+                    fs::remove_file(&path)
+                        .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to remove stale shard: {}", e)))?;
+                }
+            }
+        }
         
+        // Everything now lives in shards, so the legacy file is no longer needed
+        if self.path(STORAGE_FILE).exists() {
+            fs::remove_file(self.path(STORAGE_FILE))
+                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to remove legacy transactions file: {}", e)))?;
+        }
+        if self.path(SPENT_FILE).exists() {
+            fs::remove_file(self.path(SPENT_FILE))
+                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to remove spent commitments file: {}", e)))?;
+        }
         Ok(())
     }
 
-    /// Add a transaction to storage
-    pub fn add_transaction(&mut self, transaction: ShieldedTransaction) -> Result<()> {
+    /// Move the store to `dir` and save everything there; later writes go there too
+    pub fn save_to(&mut self, dir: &Path) -> Result<()> {
+        self.dir = dir.to_path_buf();
+        self.save()
+    }
+
+    /// Directory holding the storage files; empty for the working directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    fn shard_path(&self, shard: &str) -> PathBuf {
+        self.path(SHARD_DIR).join(format!("{}.json", shard))
+    }
+
+    /// Stage only the shard holding `id`, leaving every other shard untouched
+    fn stage_shard(&self, batch: &mut WriteBatch, id: &str) -> Result<()> {
+        let shard = shard_for(id);
+        let transactions: HashMap<&String, &ShieldedTransaction> = self.transactions
+            .iter()
+            .filter(|(tx_id, _)| shard_for(tx_id) == shard)
+            .collect();
+        let file = TransactionsFile { version: CURRENT_VERSION, transactions: &transactions };
+        batch.stage(&self.shard_path(&shard), &file, "transaction shard", self.compress)
+    }
+
+    /// Stage the transaction log
+    fn stage_merkle_leaves(&self, batch: &mut WriteBatch) -> Result<()> {
+        let file = MerkleFile {
+            version: CURRENT_VERSION,
+            transaction_ids: &self.merkle_leaves,
+            tip_hash: self.tip_hash.as_deref(),
+        };
+        batch.stage(&self.path(MERKLE_FILE), &file, "Merkle tree file", self.compress)
+    }
+
+    /// Stage the Merkle tree, which only full saves write
+    fn stage_tree(&self, batch: &mut WriteBatch) -> Result<()> {
+        let file = TreeFile { version: CURRENT_VERSION, tree: &self.merkle_tree };
+        batch.stage(&self.path(TREE_FILE), &file, "Merkle tree cache", self.compress)
+    }
+
+    /// Add a transaction to storage, rewriting only the shard it belongs to
+    ///
+    /// A sequenced transaction must carry exactly the next number in its sender's
+    /// sequence, otherwise `ShieldedError::SequenceGap` is returned.
+    pub fn add_transaction(&mut self, mut transaction: ShieldedTransaction) -> Result<()> {
+        self.check_insert(std::slice::from_ref(&transaction))?;
+        transaction.prev_hash = Some(self.chain_tip());
         let id = transaction.id.clone();
+        self.forget_verification(&id);
+        self.nullifiers.extend(transaction.nullifiers.iter().cloned());
+        self.spent_commitments.extend(spent_inputs(&transaction).cloned());
+        self.merkle_tree.add_leaf(&transaction.merkle_leaf())?;
+        self.tip_hash = Some(transaction.chain_hash());
         self.transactions.insert(id.clone(), transaction);
-        self.merkle_leaves.push(id);
+        self.merkle_leaves.push(id.clone());
+        
+        // Transactions still in the legacy file have not been sharded yet
+        if self.path(STORAGE_FILE).exists() {
+            return self.save();
+        }
+        
+        let mut batch = WriteBatch::default();
+        self.stage_shard(&mut batch, &id)?;
+        self.stage_merkle_leaves(&mut batch)?;
+        batch.commit()
+    }
+
+    /// Add many transactions at once, writing the storage files a single time
+    ///
+    /// Sequences are checked in batch order, and nothing is added if any is out of order.
+    pub fn add_transactions(&mut self, transactions: Vec<ShieldedTransaction>) -> Result<()> {
+        self.check_insert(&transactions)?;
+        for mut transaction in transactions {
+            transaction.prev_hash = Some(self.chain_tip());
+            self.forget_verification(&transaction.id);
+            self.nullifiers.extend(transaction.nullifiers.iter().cloned());
+            self.spent_commitments.extend(spent_inputs(&transaction).cloned());
+            self.merkle_tree.add_leaf(&transaction.merkle_leaf())?;
+            self.tip_hash = Some(transaction.chain_hash());
+            self.merkle_leaves.push(transaction.id.clone());
+            self.transactions.insert(transaction.id.clone(), transaction);
+        }
         self.save()
     }
 
+    /// `chain_hash` of the most recently stored transaction, or `GENESIS_HASH`
+    ///
+    /// Taken from the recorded tip when there is one, so a new transaction links to
+    /// the newest one as it was stored rather than as it is now.
+    fn chain_tip(&self) -> String {
+        if let Some(tip) = &self.tip_hash {
+            return tip.clone();
+        }
+        self.merkle_leaves
+            .last()
+            .and_then(|id| self.logged_transaction(id))
+            .map_or_else(|| GENESIS_HASH.to_string(), ShieldedTransaction::chain_hash)
+    }
+
+    /// Walk the transaction log in order, checking each `prev_hash` link
+    ///
+    /// A link that doesn't match means either side may have changed, so the broken
+    /// part starts at the transaction before it and runs to the end of the log, since
+    /// none of it can be trusted. The newest transaction has no link after it, so it is
+    /// checked against the tip recorded when it was stored; logs saved before the tip
+    /// was recorded only cover it once another transaction follows.
+    pub fn verify_chain(&self) -> ChainReport {
+        let mut report = ChainReport::default();
+        let mut expected = GENESIS_HASH.to_string();
+        
+        for (position, id) in self.merkle_leaves.iter().enumerate() {
+            let Some(transaction) = self.logged_transaction(id) else {
+                report.broken = self.merkle_leaves[position..].to_vec();
+                break;
+            };
+            if transaction.prev_hash.as_deref() != Some(expected.as_str()) {
+                let start = position.saturating_sub(1);
+                report.verified = start;
+                report.broken = self.merkle_leaves[start..].to_vec();
+                break;
+            }
+            report.verified += 1;
+            expected = transaction.chain_hash();
+        }
+        
+        if let (true, Some(tip)) = (report.is_intact(), &self.tip_hash) {
+            if *tip != expected {
+                report.verified = self.merkle_leaves.len().saturating_sub(1);
+                report.broken = self.merkle_leaves[report.verified..].to_vec();
+            }
+        }
+        
+        report
+    }
+
+    /// Merkle root after each stored transaction, starting with the empty root
+    pub fn anchor_history(&self) -> Vec<String> {
+        MerkleTree::root_history(&self.merkle_leaf_data())
+    }
+
+    /// Root of the current tree, to record as a new transaction's anchor
+    pub fn current_anchor(&self) -> String {
+        self.merkle_tree.root()
+    }
+
+    /// Check that a transaction's anchor is a root the tree has actually had
+    pub fn verify_anchor(&self, transaction: &ShieldedTransaction) -> VerifyOutcome {
+        match &transaction.anchor {
+            None => VerifyOutcome::NotApplicable,
+            Some(anchor) if self.anchor_history().contains(anchor) => VerifyOutcome::Valid,
+            Some(anchor) => VerifyOutcome::Invalid(format!("unknown anchor {}", anchor)),
+        }
+    }
+
+    /// A transaction in the log whether or not it is archived
+    fn logged_transaction(&self, id: &str) -> Option<&ShieldedTransaction> {
+        self.transactions.get(id).or_else(|| self.archived.get(id))
+    }
+
+    /// Hide a transaction from default queries without deleting it
+    ///
+    /// Its Merkle leaf, nullifiers and sequence number stay in effect, so the
+    /// root and the hash chain are unchanged; `restore_archived` brings it back.
+    pub fn archive_transaction(&mut self, id: &str) -> Result<()> {
+        let transaction = self.transactions
+            .remove(id)
+            .ok_or_else(|| crate::error::ShieldedError::TransactionNotFound(id.to_string()))?;
+        self.archived.insert(id.to_string(), transaction);
+        self.forget_verification(id);
+        
+        if self.path(STORAGE_FILE).exists() {
+            return self.save();
+        }
+        let mut batch = WriteBatch::default();
+        self.stage_archive(&mut batch)?;
+        self.stage_shard(&mut batch, id)?;
+        batch.commit()
+    }
+
+    /// Return an archived transaction to the default queries
+    pub fn restore_archived(&mut self, id: &str) -> Result<()> {
+        let transaction = self.archived
+            .remove(id)
+            .ok_or_else(|| crate::error::ShieldedError::TransactionNotFound(id.to_string()))?;
+        self.transactions.insert(id.to_string(), transaction);
+        
+        if self.path(STORAGE_FILE).exists() {
+            return self.save();
+        }
+        let mut batch = WriteBatch::default();
+        self.stage_shard(&mut batch, id)?;
+        self.stage_archive(&mut batch)?;
+        batch.commit()
+    }
+
+    /// Confirm a pending transaction and save it
+    pub fn confirm_transaction(&mut self, id: &str) -> Result<()> {
+        self.update_transaction(id, ShieldedTransaction::confirm)
+    }
+
+    /// Mark a pending transaction as failed with a reason and save it
+    pub fn fail_transaction(&mut self, id: &str, reason: &str) -> Result<()> {
+        self.update_transaction(id, |transaction| transaction.fail(reason))
+    }
+
+    /// Apply a change to a stored transaction and save its shard
+    ///
+    /// Only for fields outside the content hash, such as the status, so the
+    /// Merkle leaf and the hash chain stay valid.
+    fn update_transaction(
+        &mut self,
+        id: &str,
+        change: impl FnOnce(&mut ShieldedTransaction) -> Result<()>,
+    ) -> Result<()> {
+        let transaction = self.transactions
+            .get_mut(id)
+            .ok_or_else(|| crate::error::ShieldedError::TransactionNotFound(id.to_string()))?;
+        change(transaction)?;
+        self.forget_verification(id);
+        
+        if self.path(STORAGE_FILE).exists() {
+            return self.save();
+        }
+        let mut batch = WriteBatch::default();
+        self.stage_shard(&mut batch, id)?;
+        batch.commit()
+    }
+
+    /// Every stored transaction except archived ones, ordered by timestamp and then id
+    ///
+    /// Unlike iterating `get_all_transactions`, the order is the same on every run.
+    pub fn transactions_by_time(&self) -> Vec<&ShieldedTransaction> {
+        let mut transactions: Vec<&ShieldedTransaction> = self.transactions.values().collect();
+        sort_by_time(&mut transactions);
+        transactions
+    }
+
+    /// One page of the stored transactions matching `filter`, ordered by timestamp and then id
+    ///
+    /// Skips the first `offset` matches and returns at most `limit`, or every
+    /// remaining match when `limit` is `None`. Archived transactions are left out.
+    pub fn query_transactions(
+        &self,
+        filter: &TransactionFilter,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Vec<&ShieldedTransaction> {
+        self.transactions_by_time()
+            .into_iter()
+            .filter(|transaction| filter.matches(transaction))
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Archived transactions, ordered by timestamp and then id
+    pub fn list_archived(&self) -> Vec<&ShieldedTransaction> {
+        let mut archived: Vec<&ShieldedTransaction> = self.archived.values().collect();
+        sort_by_time(&mut archived);
+        archived
+    }
+
+    fn stage_archive(&self, batch: &mut WriteBatch) -> Result<()> {
+        let file = TransactionsFile { version: CURRENT_VERSION, transactions: &self.archived };
+        batch.stage(&self.path(ARCHIVE_FILE), &file, "archive file", self.compress)
+    }
+
+    /// Total amount `sender` has sent in stored transactions dated on `day` (UTC)
+    pub fn sent_on(&self, sender: &str, day: NaiveDate) -> u64 {
+        self.transactions
+            .values()
+            .chain(self.archived.values())
+            .filter(|transaction| transaction.from == sender && transaction.timestamp.date_naive() == day)
+            .fold(0u64, |total, transaction| total.saturating_add(transaction.amount))
+    }
+
+    /// Check a new transaction from `sender` against the policy's daily cap
+    ///
+    /// `add_transaction` enforces the store's own policy; this lets a caller fail
+    /// before building a transaction.
+    pub fn check_daily_limit(&self, policy: &TransactionPolicy, sender: &str, amount: u64) -> Result<()> {
+        policy.check_daily(amount, self.sent_on(sender, Utc::now().date_naive()))
+    }
+
+    /// Sequence number the next transaction from `sender` must carry
+    pub fn expected_sequence(&self, sender: &str) -> u64 {
+        self.transactions
+            .values()
+            .chain(self.archived.values())
+            .filter(|transaction| transaction.from == sender)
+            .filter_map(|transaction| transaction.sequence)
+            .max()
+            .map_or(0, |last| last + 1)
+    }
+
+    /// Total size in bytes of the storage files on disk
+    pub fn disk_usage(&self) -> Result<u64> {
+        let mut paths = vec![self.path(STORAGE_FILE), self.path(MERKLE_FILE), self.path(TREE_FILE)];
+        if self.path(SHARD_DIR).exists() {
+            let entries = fs::read_dir(self.path(SHARD_DIR))
+                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to read shard directory: {}", e)))?;
+            for entry in entries.flatten() {
+                paths.push(entry.path());
+            }
+        }
+        
+        Ok(paths
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum())
+    }
+
     /// Get a transaction by ID
+    ///
+    /// Under `VerifyMode::Lazy` the transaction is verified on its first read and
+    /// withheld if it fails.
     pub fn get_transaction(&self, id: &str) -> Option<&ShieldedTransaction> {
-        self.transactions.get(id)
+        let transaction = self.transactions.get(id)?;
+        if self.verify_mode != VerifyMode::Lazy {
+            return Some(transaction);
+        }
+        
+        let mut verified = self.verified.0.lock().unwrap_or_else(|e| e.into_inner());
+        let result = verified
+            .entry(id.to_string())
+            .or_insert_with(|| Self::check_transaction(transaction, self.allow_legacy_signatures));
+        match result {
+            Ok(()) => Some(transaction),
+            Err(reason) => {
+                tracing::warn!("Withholding transaction {} that failed verification: {}", id, reason);
+                None
+            }
+        }
+    }
+
+    /// Number of transactions verified so far under `VerifyMode::Lazy`
+    pub fn verified_count(&self) -> usize {
+        self.verified.0.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn forget_verification(&self, id: &str) {
+        self.verified.0.lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+    }
+
+    /// Run the full verification on one transaction, returning the failure reason
+    fn check_transaction(transaction: &ShieldedTransaction, allow_legacy_signatures: bool) -> std::result::Result<(), String> {
+        match transaction.verify_full_with(allow_legacy_signatures) {
+            Ok(outcome) if !outcome.is_invalid() => Ok(()),
+            Ok(outcome) => Err(outcome.to_string()),
+            Err(e) => Err(format!("could not verify: {}", e)),
+        }
+    }
+
+    /// Get all transactions, keyed by id
+    ///
+    /// Reads each one through `get_transaction`, so under `VerifyMode::Lazy` every
+    /// transaction is verified and those that fail are withheld.
+    pub fn get_all_transactions(&self) -> HashMap<&str, &ShieldedTransaction> {
+        self.transactions
+            .keys()
+            .filter_map(|id| self.get_transaction(id).map(|transaction| (id.as_str(), transaction)))
+            .collect()
+    }
+
+    /// Merkle leaf indices recorded for a transaction id, in ascending order
+    pub fn leaf_indices(&self, id: &str) -> Vec<usize> {
+        self.merkle_leaves
+            .iter()
+            .enumerate()
+            .filter(|(_, leaf)| *leaf == id)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Whether a stored transaction has revealed this nullifier; unknown nullifiers are unspent
+    pub fn is_spent(&self, nullifier: &str) -> bool {
+        self.nullifiers.contains(nullifier)
+    }
+
+    /// Whether a stored shielded transaction has spent this input commitment
+    pub fn is_commitment_spent(&self, commitment: &str) -> bool {
+        self.spent_commitments.contains(commitment)
+    }
+
+    /// Every spent input commitment, sorted
+    pub fn spent_commitments(&self) -> Vec<&String> {
+        let mut commitments: Vec<&String> = self.spent_commitments.iter().collect();
+        commitments.sort();
+        commitments
+    }
+
+    /// Find all transactions that reference a commitment as an input or output,
+    /// ordered by timestamp and then id
+    pub fn find_by_commitment(&self, commitment: &str) -> Vec<&ShieldedTransaction> {
+        let mut matches: Vec<&ShieldedTransaction> = self.transactions
+            .values()
+            .filter(|tx| {
+                tx.input_commitments.iter().any(|c| c == commitment)
+                    || tx.output_commitments.iter().any(|c| c == commitment)
+            })
+            .collect();
+        sort_by_time(&mut matches);
+        matches
+    }
+
+    /// Trace a commitment from the transaction that created it (as an output)
+    /// to the transaction that spent it (as an input), if any
+    pub fn commitment_lifecycle(&self, commitment: &str) -> CommitmentLifecycle {
+        let transactions = self.find_by_commitment(commitment);
+        
+        let created_in = transactions
+            .iter()
+            .find(|tx| tx.output_commitments.iter().any(|c| c == commitment))
+            .map(|tx| tx.id.clone());
+        let spent_in = transactions
+            .iter()
+            .find(|tx| tx.input_commitments.iter().any(|c| c == commitment))
+            .map(|tx| tx.id.clone());
+        
+        let status = match (&created_in, &spent_in) {
+            (_, Some(_)) => CommitmentStatus::Spent,
+            (Some(_), None) => CommitmentStatus::Unspent,
+            (None, None) => CommitmentStatus::Unknown,
+        };
+        
+        CommitmentLifecycle {
+            commitment: commitment.to_string(),
+            created_in,
+            spent_in,
+            status,
+        }
+    }
+
+    /// Estimate how many earlier outputs could plausibly be the input a shielded
+    /// transaction spends, as a privacy metric (larger is better)
+    ///
+    /// Counts output commitments of shielded transactions added to the tree before it
+    /// that were still unspent at that point and are in the asset of one of its
+    /// inputs; a note of another asset can't be what it spends. Public and unknown
+    /// transactions have a set of 0.
+    pub fn anonymity_set(&self, tx_id: &str) -> usize {
+        let Some(transaction) = self.transactions.get(tx_id) else {
+            return 0;
+        };
+        if matches!(transaction.transaction_type, TransactionType::Public) {
+            return 0;
+        }
+        let Some(position) = self.merkle_leaves.iter().position(|leaf| leaf == tx_id) else {
+            return 0;
+        };
+        
+        let earlier: Vec<&ShieldedTransaction> = self.merkle_leaves[..position]
+            .iter()
+            .filter_map(|id| self.transactions.get(id))
+            .filter(|earlier| matches!(earlier.transaction_type, TransactionType::Shielded))
+            .collect();
+        let spent: HashSet<&String> = earlier
+            .iter()
+            .flat_map(|earlier| &earlier.input_commitments)
+            .collect();
+        
+        let assets: HashSet<&AssetId> = transaction.input_assets().into_iter().collect();
+        
+        earlier
+            .iter()
+            .flat_map(|earlier| earlier.output_commitments.iter().zip(earlier.output_assets()))
+            .filter(|(commitment, asset)| assets.contains(asset) && !spent.contains(commitment))
+            .count()
+    }
+
+    /// Net public balance change per address across stored public transactions
+    ///
+    /// Each sender is debited the amount plus fee and each recipient credited its
+    /// output. Shielded transactions hide their amounts and are left out.
+    pub fn reconcile(&self) -> BTreeMap<String, i128> {
+        let mut balances: BTreeMap<String, i128> = BTreeMap::new();
+        for transaction in self.transactions.values() {
+            if matches!(transaction.transaction_type, TransactionType::Shielded) {
+                continue;
+            }
+            *balances.entry(transaction.from.clone()).or_default() -= transaction.amount as i128 + transaction.fee as i128;
+            for (to, amount) in transaction.credits() {
+                *balances.entry(to).or_default() += amount as i128;
+            }
+        }
+        balances
+    }
+
+    /// Summarize the stored transactions
+    pub fn stats(&self) -> StorageStats {
+        let mut stats = StorageStats::default();
+        
+        for transaction in self.transactions.values() {
+            stats.total_transactions += 1;
+            match transaction.transaction_type {
+                TransactionType::Public => stats.public_transactions += 1,
+                TransactionType::Shielded => stats.shielded_transactions += 1,
+            }
+            let amount = stats.amount_by_asset.entry(transaction.asset.clone()).or_default();
+            *amount = amount.saturating_add(transaction.amount);
+            let fees = stats.fees_by_asset.entry(transaction.asset.clone()).or_default();
+            *fees = fees.saturating_add(transaction.fee);
+            if self.policy.is_dust(transaction.amount) {
+                stats.dust_outputs += 1;
+            }
+            
+            // Public transactions carry no commitments or proofs
+            if let TransactionType::Shielded = transaction.transaction_type {
+                stats.commitment_bytes_total += transaction.input_commitments
+                    .iter()
+                    .chain(&transaction.output_commitments)
+                    .chain(&transaction.input_value_commitments)
+                    .chain(&transaction.output_value_commitments)
+                    .map(String::len)
+                    .sum::<usize>();
+                stats.proof_bytes_total += transaction.zk_proof.as_ref().map_or(0, String::len);
+            }
+        }
+        
+        if stats.shielded_transactions > 0 {
+            stats.commitment_bytes_avg = stats.commitment_bytes_total as f64 / stats.shielded_transactions as f64;
+            stats.proof_bytes_avg = stats.proof_bytes_total as f64 / stats.shielded_transactions as f64;
+        }
+        
+        stats
+    }
+
+    /// Verify every stored transaction, oldest first
+    ///
+    /// With `fail_fast`, verification stops at the first failing transaction.
+    pub fn verify_all(&self, fail_fast: bool) -> VerifySummary {
+        self.verify_all_with(fail_fast, cfg!(feature = "parallel"))
+    }
+
+    /// `verify_all`, checking on the rayon pool when `parallel` is set and the
+    /// feature is enabled
+    fn verify_all_with(&self, fail_fast: bool, parallel: bool) -> VerifySummary {
+        let transactions = self.transactions_by_time();
+        
+        // Only build the anchor history if something cites an anchor
+        let anchors: HashSet<String> = if transactions.iter().any(|transaction| transaction.anchor.is_some()) {
+            self.anchor_history().into_iter().collect()
+        } else {
+            HashSet::new()
+        };
+        
+        let check = |transaction: &ShieldedTransaction| {
+            Self::check_transaction(transaction, self.allow_legacy_signatures)
+                .and_then(|()| match &transaction.anchor {
+                    Some(anchor) if !anchors.contains(anchor) => Err(format!("unknown anchor {}", anchor)),
+                    _ => Ok(()),
+                })
+                .and_then(|()| match transaction.verify_pow(self.policy.pow_difficulty) {
+                    VerifyOutcome::Invalid(reason) => Err(reason),
+                    _ => Ok(()),
+                })
+        };
+        
+        // In parallel, every transaction is checked up front on the rayon pool.
+        // Results are folded in log order either way, so the summary is the same.
+        let results: Box<dyn Iterator<Item = std::result::Result<(), String>>> = if parallel {
+            Box::new(check_in_parallel(&transactions, &check).into_iter())
+        } else {
+            Box::new(transactions.iter().map(|transaction| check(transaction)))
+        };
+        
+        let mut summary = VerifySummary::default();
+        for (transaction, result) in transactions.iter().zip(results) {
+            summary.checked += 1;
+            match result {
+                Ok(()) => summary.passed.push(transaction.id.clone()),
+                Err(reason) => summary.failed.push((transaction.id.clone(), reason)),
+            }
+            
+            if fail_fast && !summary.all_passed() {
+                summary.stopped_early = summary.checked < transactions.len();
+                break;
+            }
+        }
+        
+        summary
+    }
+
+    /// Run every check on each stored transaction, oldest first, and report each
+    /// check's outcome instead of stopping at the first failure
+    pub fn verification_reports(&self) -> Vec<(String, VerificationReport)> {
+        let transactions = self.transactions_by_time();
+        let anchors: HashSet<String> = if transactions.iter().any(|transaction| transaction.anchor.is_some()) {
+            self.anchor_history().into_iter().collect()
+        } else {
+            HashSet::new()
+        };
+        // Latest log position of each id, so inclusion checks don't rescan the log
+        let positions: HashMap<&str, usize> = self.merkle_leaves
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (id.as_str(), index))
+            .collect();
+        
+        transactions
+            .into_iter()
+            .map(|transaction| {
+                let position = positions.get(transaction.id.as_str()).copied();
+                (transaction.id.clone(), self.verification_report(transaction, position, &anchors))
+            })
+            .collect()
     }
 
-    /// Get all transactions
-    pub fn get_all_transactions(&self) -> &HashMap<String, ShieldedTransaction> {
-        &self.transactions
+    fn verification_report(
+        &self,
+        transaction: &ShieldedTransaction,
+        position: Option<usize>,
+        anchors: &HashSet<String>,
+    ) -> VerificationReport {
+        let outcome = |result: Result<VerifyOutcome>| {
+            result.unwrap_or_else(|e| VerifyOutcome::Invalid(format!("could not verify: {}", e)))
+        };
+        
+        let validity = match transaction.validate() {
+            Ok(()) => VerifyOutcome::Valid,
+            Err(crate::error::ShieldedError::InvalidTransaction(reason)) => VerifyOutcome::Invalid(reason),
+            Err(e) => VerifyOutcome::Invalid(e.to_string()),
+        };
+        let balance = if !transaction.is_balanced() {
+            VerifyOutcome::Invalid("inputs do not equal outputs plus fee".to_string())
+        } else if let TransactionType::Shielded = transaction.transaction_type {
+            outcome(transaction.verify_commitment_balance())
+        } else {
+            VerifyOutcome::Valid
+        };
+        let signature = transaction.signature_outcome(self.allow_legacy_signatures);
+        let anchor = match &transaction.anchor {
+            Some(anchor) if anchors.contains(anchor) => VerifyOutcome::Valid,
+            Some(anchor) => VerifyOutcome::Invalid(format!("unknown anchor {}", anchor)),
+            None => VerifyOutcome::NotApplicable,
+        };
+        
+        VerificationReport {
+            id: transaction.verify_id(),
+            validity,
+            balance,
+            signature,
+            proof: outcome(transaction.verify_proof()),
+            merkle_inclusion: outcome(self.verify_inclusion(transaction, position)),
+            anchor,
+            pow: transaction.verify_pow(self.policy.pow_difficulty),
+        }
+    }
+
+    /// Check that the transaction's leaf at a log position proves into the Merkle root
+    fn verify_inclusion(&self, transaction: &ShieldedTransaction, position: Option<usize>) -> Result<VerifyOutcome> {
+        let index = match position {
+            Some(index) => index,
+            None => return Ok(VerifyOutcome::Invalid("not in the Merkle tree".to_string())),
+        };
+        let proof = self.merkle_tree.generate_proof(index)?;
+        Ok(if self.merkle_tree.verify_proof(&transaction.merkle_leaf(), &proof, index)? {
+            VerifyOutcome::Valid
+        } else {
+            VerifyOutcome::Invalid(format!("leaf {} does not prove into the Merkle root", index))
+        })
+    }
+
+    /// Compare the (non-archived) transactions of two stores by id and content
+    pub fn diff(&self, other: &StorageData) -> StorageDiff {
+        let mut diff = StorageDiff::default();
+        for (id, transaction) in &self.transactions {
+            match other.transactions.get(id) {
+                None => diff.only_here.push(id.clone()),
+                Some(theirs) if theirs.content_hash() != transaction.content_hash() => diff.conflicting.push(id.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.only_there = other.transactions
+            .keys()
+            .filter(|id| !self.transactions.contains_key(*id))
+            .cloned()
+            .collect();
+        
+        diff.only_here.sort();
+        diff.only_there.sort();
+        diff.conflicting.sort();
+        diff
+    }
+
+    /// Append the other store's transactions that are missing here, in its log order
+    ///
+    /// Archived transactions come across too and stay archived. Id collisions with
+    /// different content keep the local copy, and transactions revealing a nullifier
+    /// already spent here are left out; both are reported. Every other incoming
+    /// transaction must pass the insert checks, with anchors from either store's
+    /// tree accepted, and is reported as rejected otherwise. Added transactions are
+    /// re-linked onto this store's hash chain and Merkle tree.
+    pub fn merge(&mut self, other: StorageData) -> Result<MergeReport> {
+        let mut report = MergeReport::default();
+        let mut anchors: HashSet<String> = self.anchor_history().into_iter().chain(other.anchor_history()).collect();
+        
+        let StorageData { mut transactions, mut archived, merkle_leaves, .. } = other;
+        let mut incoming: Vec<(ShieldedTransaction, bool)> = merkle_leaves
+            .iter()
+            .filter_map(|id| {
+                transactions.remove(id)
+                    .map(|transaction| (transaction, false))
+                    .or_else(|| archived.remove(id).map(|transaction| (transaction, true)))
+            })
+            .collect();
+        // Anything the other store never put in its log goes last, oldest first
+        let mut unlogged: Vec<(ShieldedTransaction, bool)> = transactions
+            .into_values()
+            .map(|transaction| (transaction, false))
+            .chain(archived.into_values().map(|transaction| (transaction, true)))
+            .collect();
+        unlogged.sort_by(|(a, _), (b, _)| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        incoming.extend(unlogged);
+        
+        for (mut transaction, is_archived) in incoming {
+            if let Some(local) = self.logged_transaction(&transaction.id) {
+                if local.content_hash() != transaction.content_hash() {
+                    report.conflicts.push(transaction.id);
+                }
+                continue;
+            }
+            if transaction.nullifiers.iter().any(|nullifier| self.nullifiers.contains(nullifier))
+                || spent_inputs(&transaction).any(|commitment| self.spent_commitments.contains(commitment))
+            {
+                report.nullifier_conflicts.push(transaction.id);
+                continue;
+            }
+            let checks = MergeChecks { local: self, anchors: &anchors };
+            if let Err(e) = checks.check_insert(std::slice::from_ref(&transaction)) {
+                report.rejected.push((transaction.id, e.to_string()));
+                continue;
+            }
+            
+            transaction.prev_hash = Some(self.chain_tip());
+            self.forget_verification(&transaction.id);
+            self.nullifiers.extend(transaction.nullifiers.iter().cloned());
+            self.spent_commitments.extend(spent_inputs(&transaction).cloned());
+            self.merkle_tree.add_leaf(&transaction.merkle_leaf())?;
+            self.tip_hash = Some(transaction.chain_hash());
+            anchors.insert(self.merkle_tree.root());
+            self.merkle_leaves.push(transaction.id.clone());
+            report.added.push(transaction.id.clone());
+            if is_archived {
+                self.archived.insert(transaction.id.clone(), transaction);
+            } else {
+                self.transactions.insert(transaction.id.clone(), transaction);
+            }
+        }
+        
+        report.conflicts.sort();
+        report.merkle_root = self.merkle_tree.root();
+        self.save()?;
+        Ok(report)
+    }
+
+    /// Rebuild a wallet's note set by trial-decrypting every stored transaction
+    /// with its viewing key, oldest first, returning how many notes it now holds
+    ///
+    /// The wallet's `notes` are replaced by the unspent ones found. Empty
+    /// (zero-value) notes are skipped since there is nothing to spend.
+    pub fn rescan(&self, wallet: &mut Wallet) -> usize {
+        wallet.notes = self.received_notes(wallet)
+            .into_iter()
+            .filter(|received| received.status == CommitmentStatus::Unspent)
+            .map(|received| received.note)
+            .collect();
+        wallet.notes.len()
+    }
+    
+    /// Decrypt every non-empty note addressed to a wallet, oldest first, along
+    /// with the transaction that created it and whether it has been spent
+    pub fn received_notes(&self, wallet: &Wallet) -> Vec<ReceivedNote> {
+        let transactions = self.transactions_by_time();
+        
+        transactions
+            .into_iter()
+            .flat_map(|transaction| {
+                transaction
+                    .decrypt_notes(&wallet.viewing_key)
+                    .into_iter()
+                    .filter(|note| !note.is_empty())
+                    .map(|note| ReceivedNote {
+                        transaction_id: transaction.id.clone(),
+                        status: self.commitment_lifecycle(&note.commitment).status,
+                        note,
+                    })
+            })
+            .collect()
     }
 
     /// Get Merkle tree leaves
@@ -84,20 +1350,1694 @@ impl StorageData {
         &self.merkle_leaves
     }
 
-    /// Rebuild Merkle tree from stored leaves
+    /// Every output commitment with the Merkle leaf index of the transaction that created it
+    ///
+    /// Each transaction is one leaf, so all of its outputs share that index. Ordered
+    /// by leaf index, then by position among the transaction's outputs.
+    pub fn all_commitments(&self) -> Vec<(String, usize)> {
+        self.merkle_leaves
+            .iter()
+            .enumerate()
+            .filter_map(|(index, id)| self.transactions.get(id).map(|transaction| (index, transaction)))
+            .flat_map(|(index, transaction)| {
+                transaction
+                    .output_commitments
+                    .iter()
+                    .map(move |commitment| (commitment.clone(), index))
+            })
+            .collect()
+    }
+
+    /// The Merkle tree kept up to date as transactions are added
+    pub fn merkle_tree(&self) -> &MerkleTree {
+        &self.merkle_tree
+    }
+
+    /// Rebuild the Merkle tree from scratch over the logged transactions
     pub fn rebuild_merkle_tree(&self) -> MerkleTree {
-        let mut tree = MerkleTree::new();
-        for leaf in &self.merkle_leaves {
-            let _ = tree.add_leaf(leaf);
-        }
-        tree
+        MerkleTree::from_leaves(&self.merkle_leaf_data()).unwrap_or_default()
+    }
+
+    /// Portable proof that a transaction's latest log entry is in the current Merkle root
+    pub fn inclusion_proof(&self, transaction_id: &str) -> Result<MerkleProof> {
+        let index = self.merkle_leaves
+            .iter()
+            .rposition(|id| id == transaction_id)
+            .ok_or_else(|| crate::error::ShieldedError::TransactionNotFound(transaction_id.to_string()))?;
+        self.merkle_tree.inclusion_proof(index)
+    }
+
+    /// Leaf data of every logged transaction, in log order
+    ///
+    /// An id whose transaction is missing is committed as-is, keeping later
+    /// indices in place; `verify_chain` reports the gap.
+    fn merkle_leaf_data(&self) -> Vec<String> {
+        self.merkle_leaves
+            .iter()
+            .map(|id| self.logged_transaction(id).map_or_else(|| id.clone(), ShieldedTransaction::merkle_leaf))
+            .collect()
     }
 
     /// Clear all stored data
     pub fn clear(&mut self) -> Result<()> {
         self.transactions.clear();
         self.merkle_leaves.clear();
+        self.tip_hash = None;
+        self.merkle_tree = MerkleTree::new();
+        self.nullifiers.clear();
+        self.spent_commitments.clear();
+        self.archived.clear();
+        self.verified.0.lock().unwrap_or_else(|e| e.into_inner()).clear();
         self.save()
     }
 }
 
+/// Operations every transaction storage backend provides
+///
+/// Backends check the same rules on insert (proof of work, sender sequences,
+/// anchors, nullifiers and spent input commitments) and link each transaction to
+/// the one before it.
+pub trait Storage {
+    /// Check, chain and persist a transaction, appending it to the log
+    fn add_transaction(&mut self, transaction: ShieldedTransaction) -> Result<()>;
+    /// A stored transaction by id, as it was saved (including its `prev_hash`)
+    fn get_transaction(&self, id: &str) -> Result<Option<ShieldedTransaction>>;
+    /// Every stored transaction, keyed by id
+    fn get_all_transactions(&self) -> Result<HashMap<String, ShieldedTransaction>>;
+    /// Transaction ids in log order
+    fn merkle_leaves(&self) -> Result<Vec<String>>;
+    /// Remove every transaction and the log
+    fn clear(&mut self) -> Result<()>;
+}
+
+/// Lookups the insert rules need from a store, shared by every `Storage` backend
+/// so they accept and refuse exactly the same transactions
+pub(crate) trait InsertChecks {
+    /// Policy new transactions must meet: proof of work and amount caps
+    fn policy(&self) -> &TransactionPolicy;
+    /// Whether a transaction with this id is stored, archived ones included
+    fn is_stored(&self, id: &str) -> Result<bool>;
+    /// Sequence number the sender's next transaction must carry
+    fn next_sequence(&self, sender: &str) -> Result<u64>;
+    /// The subset of `anchors` that are roots the tree has had
+    fn known_anchors(&self, anchors: &[&str]) -> Result<HashSet<String>>;
+    /// Whether a stored transaction revealed this nullifier
+    fn nullifier_spent(&self, nullifier: &str) -> Result<bool>;
+    /// Whether a stored shielded transaction spent this input commitment
+    fn commitment_spent(&self, commitment: &str) -> Result<bool>;
+    /// Total `sender` has sent in stored transactions dated on `day` (UTC)
+    fn sent_on(&self, sender: &str, day: NaiveDate) -> Result<u64>;
+    
+    /// Reject a batch that can't be appended as a whole: an id already stored or
+    /// repeated, proof of work below the difficulty, an amount over the policy's
+    /// per-transaction or daily cap, a sequence that skips or repeats a number, an
+    /// anchor the tree never had, or a nullifier or input commitment spent in
+    /// storage or earlier in the batch
+    ///
+    /// The daily cap counts each transaction on its own timestamp's day, together
+    /// with what its sender sent that day in storage and earlier in the batch.
+    /// Input commitments back up nullifiers for a spend replayed without its
+    /// nullifier, whichever constructor built it. Anchors must predate the batch.
+    fn check_insert(&self, transactions: &[ShieldedTransaction]) -> Result<()> {
+        let invalid = |transaction: &ShieldedTransaction, reason: String| {
+            crate::error::ShieldedError::InvalidTransaction(format!("Transaction {}{}", transaction.id, reason))
+        };
+        
+        let mut ids = HashSet::new();
+        for transaction in transactions {
+            if self.is_stored(&transaction.id)? || !ids.insert(transaction.id.as_str()) {
+                return Err(invalid(transaction, " is already stored".to_string()));
+            }
+            if let VerifyOutcome::Invalid(reason) = transaction.verify_pow(self.policy().pow_difficulty) {
+                return Err(invalid(transaction, format!(": {}", reason)));
+            }
+            self.policy().check_amount(transaction.amount)?;
+        }
+        
+        if self.policy().daily_limit.is_some() {
+            let mut sent: HashMap<(&str, NaiveDate), u64> = HashMap::new();
+            for transaction in transactions {
+                let day = transaction.timestamp.date_naive();
+                let sent_today = match sent.entry((&transaction.from, day)) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(self.sent_on(&transaction.from, day)?),
+                };
+                self.policy().check_daily(transaction.amount, *sent_today)?;
+                *sent_today = sent_today.saturating_add(transaction.amount);
+            }
+        }
+        
+        let mut expected: HashMap<&str, u64> = HashMap::new();
+        for transaction in transactions {
+            let Some(found) = transaction.sequence else {
+                continue;
+            };
+            let next = match expected.entry(&transaction.from) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.next_sequence(&transaction.from)?),
+            };
+            if found != *next {
+                return Err(crate::error::ShieldedError::SequenceGap {
+                    sender: transaction.from.clone(),
+                    expected: *next,
+                    found,
+                });
+            }
+            *next += 1;
+        }
+        
+        let cited: Vec<&str> = transactions.iter().filter_map(|transaction| transaction.anchor.as_deref()).collect();
+        if !cited.is_empty() {
+            let known = self.known_anchors(&cited)?;
+            for transaction in transactions {
+                if let Some(anchor) = transaction.anchor.as_ref().filter(|anchor| !known.contains(*anchor)) {
+                    return Err(invalid(transaction, format!(" cites unknown anchor {}", anchor)));
+                }
+            }
+        }
+        
+        let mut nullifiers = HashSet::new();
+        let mut commitments = HashSet::new();
+        for transaction in transactions {
+            for nullifier in &transaction.nullifiers {
+                if self.nullifier_spent(nullifier)? || !nullifiers.insert(nullifier) {
+                    return Err(invalid(transaction, format!(" reveals spent nullifier {}", nullifier)));
+                }
+            }
+            for commitment in spent_inputs(transaction) {
+                if self.commitment_spent(commitment)? || !commitments.insert(commitment) {
+                    return Err(invalid(transaction, format!(" spends input commitment {}, which is already spent", commitment)));
+                }
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+impl InsertChecks for StorageData {
+    fn policy(&self) -> &TransactionPolicy {
+        &self.policy
+    }
+
+    fn is_stored(&self, id: &str) -> Result<bool> {
+        Ok(self.logged_transaction(id).is_some())
+    }
+
+    fn next_sequence(&self, sender: &str) -> Result<u64> {
+        Ok(self.expected_sequence(sender))
+    }
+
+    fn known_anchors(&self, anchors: &[&str]) -> Result<HashSet<String>> {
+        let history: HashSet<String> = self.anchor_history().into_iter().collect();
+        Ok(anchors.iter().filter(|anchor| history.contains(**anchor)).map(|anchor| anchor.to_string()).collect())
+    }
+
+    fn nullifier_spent(&self, nullifier: &str) -> Result<bool> {
+        Ok(self.is_spent(nullifier))
+    }
+
+    fn commitment_spent(&self, commitment: &str) -> Result<bool> {
+        Ok(self.is_commitment_spent(commitment))
+    }
+
+    fn sent_on(&self, sender: &str, day: NaiveDate) -> Result<u64> {
+        Ok(StorageData::sent_on(self, sender, day))
+    }
+}
+
+/// Insert checks for `StorageData::merge`, which also accepts anchors from the
+/// other store's tree
+struct MergeChecks<'a> {
+    local: &'a StorageData,
+    anchors: &'a HashSet<String>,
+}
+
+impl InsertChecks for MergeChecks<'_> {
+    fn policy(&self) -> &TransactionPolicy {
+        &self.local.policy
+    }
+
+    fn is_stored(&self, id: &str) -> Result<bool> {
+        self.local.is_stored(id)
+    }
+
+    fn next_sequence(&self, sender: &str) -> Result<u64> {
+        self.local.next_sequence(sender)
+    }
+
+    fn known_anchors(&self, anchors: &[&str]) -> Result<HashSet<String>> {
+        Ok(anchors.iter().filter(|anchor| self.anchors.contains(**anchor)).map(|anchor| anchor.to_string()).collect())
+    }
+
+    fn nullifier_spent(&self, nullifier: &str) -> Result<bool> {
+        self.local.nullifier_spent(nullifier)
+    }
+
+    fn commitment_spent(&self, commitment: &str) -> Result<bool> {
+        self.local.commitment_spent(commitment)
+    }
+
+    fn sent_on(&self, sender: &str, day: NaiveDate) -> Result<u64> {
+        InsertChecks::sent_on(self.local, sender, day)
+    }
+}
+
+impl Storage for StorageData {
+    fn add_transaction(&mut self, transaction: ShieldedTransaction) -> Result<()> {
+        StorageData::add_transaction(self, transaction)
+    }
+
+    fn get_transaction(&self, id: &str) -> Result<Option<ShieldedTransaction>> {
+        Ok(StorageData::get_transaction(self, id).cloned())
+    }
+
+    fn get_all_transactions(&self) -> Result<HashMap<String, ShieldedTransaction>> {
+        Ok(StorageData::get_all_transactions(self)
+            .into_iter()
+            .map(|(id, transaction)| (id.to_string(), transaction.clone()))
+            .collect())
+    }
+
+    fn merkle_leaves(&self) -> Result<Vec<String>> {
+        Ok(self.merkle_leaves.clone())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        StorageData::clear(self)
+    }
+}
+
+/// Read-only view of the store as it was when `StorageData::snapshot` was called
+///
+/// Later writes to the live store are not reflected, so several reads made over
+/// one snapshot always agree with each other. Take it under whatever lock guards
+/// the store, then release the lock before reading. Cloning a snapshot is cheap.
+#[derive(Debug, Clone)]
+pub struct StorageSnapshot {
+    data: Arc<StorageData>,
+}
+
+impl StorageSnapshot {
+    /// Get a transaction by ID
+    pub fn get_transaction(&self, id: &str) -> Option<&ShieldedTransaction> {
+        self.data.get_transaction(id)
+    }
+
+    /// Get all transactions, verified as `StorageData::get_all_transactions` does
+    pub fn get_all_transactions(&self) -> HashMap<&str, &ShieldedTransaction> {
+        self.data.get_all_transactions()
+    }
+
+    /// Transactions ordered by timestamp and then id
+    pub fn transactions_by_time(&self) -> Vec<&ShieldedTransaction> {
+        self.data.transactions_by_time()
+    }
+
+    /// One page of the transactions matching `filter`; see `StorageData::query_transactions`
+    pub fn query_transactions(
+        &self,
+        filter: &TransactionFilter,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Vec<&ShieldedTransaction> {
+        self.data.query_transactions(filter, offset, limit)
+    }
+
+    /// Whether a stored transaction has revealed this nullifier
+    pub fn is_spent(&self, nullifier: &str) -> bool {
+        self.data.is_spent(nullifier)
+    }
+
+    /// Whether a stored shielded transaction has spent this input commitment
+    pub fn is_commitment_spent(&self, commitment: &str) -> bool {
+        self.data.is_commitment_spent(commitment)
+    }
+
+    /// Find transactions with a commitment among their inputs or outputs
+    pub fn find_by_commitment(&self, commitment: &str) -> Vec<&ShieldedTransaction> {
+        self.data.find_by_commitment(commitment)
+    }
+
+    /// Trace where a commitment was created and spent
+    pub fn commitment_lifecycle(&self, commitment: &str) -> CommitmentLifecycle {
+        self.data.commitment_lifecycle(commitment)
+    }
+
+    /// Summarize the transactions in this snapshot
+    pub fn stats(&self) -> StorageStats {
+        self.data.stats()
+    }
+
+    /// Verify every transaction in this snapshot
+    pub fn verify_all(&self, fail_fast: bool) -> VerifySummary {
+        self.data.verify_all(fail_fast)
+    }
+
+    /// Report each check on every transaction in this snapshot
+    pub fn verification_reports(&self) -> Vec<(String, VerificationReport)> {
+        self.data.verification_reports()
+    }
+
+    /// Decrypt the notes addressed to a wallet
+    pub fn received_notes(&self, wallet: &Wallet) -> Vec<ReceivedNote> {
+        self.data.received_notes(wallet)
+    }
+
+    /// Get Merkle tree leaves
+    pub fn get_merkle_leaves(&self) -> &Vec<String> {
+        self.data.get_merkle_leaves()
+    }
+
+    /// Every output commitment with its Merkle leaf index
+    pub fn all_commitments(&self) -> Vec<(String, usize)> {
+        self.data.all_commitments()
+    }
+
+    /// The Merkle tree as of this snapshot
+    pub fn merkle_tree(&self) -> &MerkleTree {
+        self.data.merkle_tree()
+    }
+
+    /// Rebuild the Merkle tree over this snapshot's leaves
+    pub fn rebuild_merkle_tree(&self) -> MerkleTree {
+        self.data.rebuild_merkle_tree()
+    }
+}
+
+/// Run `check` over every transaction on the rayon pool, keeping their order
+#[cfg(feature = "parallel")]
+fn check_in_parallel<T: Send>(transactions: &[&ShieldedTransaction], check: &(impl Fn(&ShieldedTransaction) -> T + Sync)) -> Vec<T> {
+    use rayon::prelude::*;
+    transactions.par_iter().map(|transaction| check(transaction)).collect()
+}
+
+/// Without the `parallel` feature there is no pool, so `check` runs in order
+#[cfg(not(feature = "parallel"))]
+fn check_in_parallel<T>(transactions: &[&ShieldedTransaction], check: &impl Fn(&ShieldedTransaction) -> T) -> Vec<T> {
+    transactions.iter().map(|transaction| check(transaction)).collect()
+}
+
+/// Order transactions by timestamp, breaking ties by id so the order is total
+fn sort_by_time(transactions: &mut [&ShieldedTransaction]) {
+    transactions.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+}
+
+/// Input commitments a transaction consumes; public transactions spend none
+pub(crate) fn spent_inputs(transaction: &ShieldedTransaction) -> impl Iterator<Item = &String> {
+    let inputs: &[String] = match transaction.transaction_type {
+        TransactionType::Shielded => &transaction.input_commitments,
+        TransactionType::Public => &[],
+    };
+    inputs.iter()
+}
+
+/// Shard key for a transaction id: its first byte, as two lowercase hex characters
+fn shard_for(id: &str) -> String {
+    match id.get(..2) {
+        Some(prefix) if prefix.chars().all(|c| c.is_ascii_hexdigit()) => prefix.to_ascii_lowercase(),
+        _ => hex::encode(&crate::crypto::hash(id.as_bytes())[..1]),
+    }
+}
+
+/// Read a JSON file, transparently decompressing it if it is gzipped
+pub(crate) fn read_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let file = fs::File::open(path)
+        .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to read {}: {}", what, e)))?;
+    read_json_from(file, what)
+}
+
+/// Read a storage file, migrating it to `CURRENT_VERSION` first if it is older
+///
+/// The version is read by a first pass that skips every other value without
+/// building it, so current files deserialize straight into `T`; only older ones go
+/// through a `Value` for `migrate`.
+fn read_versioned<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let StoredVersion(version) = read_json(path, what)?;
+    if version == CURRENT_VERSION {
+        return read_json(path, what);
+    }
+    let value: Value = read_json(path, what)?;
+    serde_json::from_value(migrate(version, value)?)
+        .map_err(crate::error::ShieldedError::SerializationError)
+}
+
+/// A storage file's top-level `version`, 1 when it has none
+struct StoredVersion(u32);
+
+impl<'de> Deserialize<'de> for StoredVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct VersionVisitor;
+        
+        impl<'de> Visitor<'de> for VersionVisitor {
+            type Value = StoredVersion;
+            
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a storage file object or a version 1 list")
+            }
+            
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+                let mut version = None;
+                while let Some(key) = map.next_key::<Cow<str>>()? {
+                    if key == "version" && version.is_none() {
+                        version = Some(map.next_value::<u64>()?);
+                    } else {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+                Ok(StoredVersion(version.map_or(1, |version| u32::try_from(version).unwrap_or(u32::MAX))))
+            }
+            
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(StoredVersion(1))
+            }
+        }
+        
+        deserializer.deserialize_any(VersionVisitor)
+    }
+}
+
+/// Upgrade a storage file's JSON from `from_version` to `CURRENT_VERSION`, one
+/// version at a time
+///
+/// Version 1 files carry no version: transaction files are a bare map from id to
+/// transaction, and the Merkle file is a bare list of ids or `{transaction_ids, tree}`.
+/// Transaction fields added since then are filled in by their serde defaults.
+pub fn migrate(from_version: u32, mut value: Value) -> Result<Value> {
+    if from_version > CURRENT_VERSION {
+        return Err(crate::error::ShieldedError::StorageError(format!(
+            "Storage file has version {}, newer than the supported version {}",
+            from_version, CURRENT_VERSION
+        )));
+    }
+    
+    for version in from_version..CURRENT_VERSION {
+        value = match version {
+            1 => match value {
+                Value::Array(transaction_ids) => json!({ "transaction_ids": transaction_ids }),
+                Value::Object(file) if file.contains_key("transaction_ids") => Value::Object(file),
+                transactions => json!({ "transactions": transactions }),
+            },
+            other => {
+                return Err(crate::error::ShieldedError::StorageError(
+                    format!("Unknown storage file version {}", other)
+                ))
+            }
+        };
+    }
+    
+    if let Value::Object(file) = &mut value {
+        file.insert("version".to_string(), json!(CURRENT_VERSION));
+    }
+    Ok(value)
+}
+
+/// Deserialize JSON straight from a reader, gzipped or not, without buffering the
+/// whole document; maps are built entry by entry as they are parsed
+fn read_json_from<T: DeserializeOwned>(reader: impl Read, what: &str) -> Result<T> {
+    let mut reader = BufReader::new(reader);
+    let is_gzip = reader
+        .fill_buf()
+        .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to read {}: {}", what, e)))?
+        .starts_with(&GZIP_MAGIC);
+    
+    if is_gzip {
+        serde_json::from_reader(BufReader::new(GzDecoder::new(reader)))
+            .map_err(crate::error::ShieldedError::SerializationError)
+    } else {
+        serde_json::from_reader(reader)
+            .map_err(crate::error::ShieldedError::SerializationError)
+    }
+}
+
+pub(crate) fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T, what: &str) -> Result<()> {
+    write_json_with(path, value, what, false)
+}
+
+/// Write pretty-printed JSON atomically, gzipped when `compress` is set
+fn write_json_with<T: Serialize + ?Sized>(path: &Path, value: &T, what: &str, compress: bool) -> Result<()> {
+    let mut batch = WriteBatch::default();
+    batch.stage(path, value, what, compress)?;
+    batch.commit()
+}
+
+/// Storage files written by one save, each held in a temporary file next to its
+/// destination until `commit` renames them all into place
+///
+/// A crash or error before `commit` leaves every live file untouched; temporaries
+/// not renamed are removed when the batch is dropped.
+#[derive(Default)]
+struct WriteBatch {
+    /// `(temporary, destination)` pairs, renamed in the order they were staged
+    staged: Vec<(PathBuf, PathBuf)>,
+}
+
+impl WriteBatch {
+    /// Encode `value` as pretty-printed JSON, gzipped when `compress` is set, and
+    /// write it to a synced temporary file
+    fn stage<T: Serialize + ?Sized>(&mut self, path: &Path, value: &T, what: &str, compress: bool) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to create directory for {}: {}", what, e)))?;
+        }
+        let json = serde_json::to_string_pretty(value)
+            .map_err(crate::error::ShieldedError::SerializationError)?;
+        let bytes = if compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(json.as_bytes())
+                .and_then(|_| encoder.finish())
+                .map_err(|e| crate::error::ShieldedError::StorageError(format!("Failed to compress {}: {}", what, e)))?
+        } else {
+            json.into_bytes()
+        };
+        
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+        let written = fs::File::create(&temp_path).and_then(|mut file| {
+            file.write_all(&bytes)?;
+            file.sync_all()
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(crate::error::ShieldedError::StorageError(format!("Failed to write {}: {}", what, e)));
+        }
+        self.staged.push((temp_path, path.to_path_buf()));
+        Ok(())
+    }
+    
+    /// Rename every staged file into place, in staging order
+    fn commit(mut self) -> Result<()> {
+        while !self.staged.is_empty() {
+            let (temp_path, path) = self.staged.remove(0);
+            if let Err(e) = fs::rename(&temp_path, &path) {
+                let _ = fs::remove_file(&temp_path);
+                return Err(crate::error::ShieldedError::StorageError(
+                    format!("Failed to move {} into place: {}", path.display(), e)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WriteBatch {
+    fn drop(&mut self) {
+        for (temp_path, _) in &self.staged {
+            let _ = fs::remove_file(temp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::fixtures::{address, test_wallet};
+    
+    fn public_transaction(amount: u64) -> ShieldedTransaction {
+        ShieldedTransaction::create_public(&test_wallet('a'), &address('b'), amount).unwrap()
+    }
+    
+    #[test]
+    fn failed_save_leaves_previous_files_loadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let first = public_transaction(1_000);
+        storage.add_transaction(first.clone()).unwrap();
+        
+        // A directory where the log's temporary file goes fails the last write of the
+        // batch, after the shards have been written
+        let blocker = dir.path().join(format!(".{}.{}.tmp", MERKLE_FILE, std::process::id()));
+        fs::create_dir(&blocker).unwrap();
+        assert!(storage.add_transaction(public_transaction(2_000)).is_err());
+        assert!(storage.save().is_err());
+        fs::remove_dir(&blocker).unwrap();
+        
+        let reloaded = StorageData::load_from(dir.path()).unwrap();
+        assert_eq!(reloaded.merkle_leaves, vec![first.id.clone()]);
+        assert_eq!(reloaded.transactions.keys().collect::<Vec<_>>(), vec![&first.id]);
+        assert_eq!(reloaded.merkle_tree().root(), reloaded.rebuild_merkle_tree().root());
+        
+        let leftovers: Vec<_> = fs::read_dir(dir.path().join(SHARD_DIR))
+            .unwrap()
+            .chain(fs::read_dir(dir.path()).unwrap())
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+    
+    #[test]
+    fn saved_tree_that_does_not_match_the_transactions_is_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        storage.add_transactions(vec![public_transaction(1_000), public_transaction(2_000)]).unwrap();
+        let expected = storage.merkle_tree().root();
+        
+        // Same leaf count, different leaves
+        let stale = MerkleTree::from_leaves(&["a".to_string(), "b".to_string()]).unwrap();
+        fs::write(dir.path().join(TREE_FILE), json!({ "version": CURRENT_VERSION, "tree": &stale }).to_string()).unwrap();
+        assert_eq!(StorageData::load_from(dir.path()).unwrap().merkle_tree().root(), expected);
+        
+        // Matching leaves under a forged root
+        let mut forged = storage.merkle_tree().clone();
+        forged.root = stale.root();
+        fs::write(dir.path().join(TREE_FILE), json!({ "version": CURRENT_VERSION, "tree": &forged }).to_string()).unwrap();
+        assert_eq!(StorageData::load_from(dir.path()).unwrap().merkle_tree().root(), expected);
+    }
+    
+    #[test]
+    fn adding_a_transaction_writes_only_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        storage.add_transactions(vec![public_transaction(1_000)]).unwrap();
+        let saved_tree = fs::read(dir.path().join(TREE_FILE)).unwrap();
+        
+        storage.add_transaction(public_transaction(2_000)).unwrap();
+        assert_eq!(fs::read(dir.path().join(TREE_FILE)).unwrap(), saved_tree);
+        let log: serde_json::Value = serde_json::from_slice(&fs::read(dir.path().join(MERKLE_FILE)).unwrap()).unwrap();
+        assert!(log.get("tree").is_none());
+        assert!(!String::from_utf8(saved_tree).unwrap().contains("nodes"));
+        
+        // The saved tree is brought up to date with the leaf logged after it
+        let reloaded = StorageData::load_from(dir.path()).unwrap();
+        assert_eq!(reloaded.merkle_tree().leaf_count(), 2);
+        assert_eq!(reloaded.merkle_tree().root(), storage.merkle_tree().root());
+    }
+    
+    fn chained_store(dir: &Path) -> (StorageData, Vec<String>) {
+        let mut storage = StorageData::load_from(dir).unwrap();
+        let transactions: Vec<ShieldedTransaction> = (1..=4).map(|amount| public_transaction(amount * 1_000)).collect();
+        let ids = transactions.iter().map(|transaction| transaction.id.clone()).collect();
+        for transaction in transactions {
+            storage.add_transaction(transaction).unwrap();
+        }
+        (storage, ids)
+    }
+    
+    #[test]
+    fn clean_chain_verifies_and_an_edit_breaks_it_from_there_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut storage, ids) = chained_store(dir.path());
+        let report = storage.verify_chain();
+        assert!(report.is_intact());
+        assert_eq!(report.verified, 4);
+        
+        storage.transactions.get_mut(&ids[1]).unwrap().amount += 1;
+        let report = storage.verify_chain();
+        assert_eq!(report.verified, 1);
+        assert_eq!(report.broken, ids[1..].to_vec());
+    }
+    
+    #[test]
+    fn edit_to_the_newest_transaction_breaks_the_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut storage, ids) = chained_store(dir.path());
+        storage.transactions.get_mut(&ids[3]).unwrap().amount += 1;
+        storage.save().unwrap();
+        
+        let mut reloaded = StorageData::load_from(dir.path()).unwrap();
+        let report = reloaded.verify_chain();
+        assert_eq!(report.verified, 3);
+        assert_eq!(report.broken, vec![ids[3].clone()]);
+        
+        // Storing another transaction links it to the newest one as first stored
+        reloaded.add_transaction(public_transaction(5_000)).unwrap();
+        let report = reloaded.verify_chain();
+        assert_eq!((report.verified, report.broken.len()), (3, 2));
+        assert_eq!(report.broken[0], ids[3]);
+    }
+    
+    #[test]
+    fn load_drops_transactions_missing_from_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let logged = public_transaction(1_000);
+        storage.add_transaction(logged.clone()).unwrap();
+        
+        // As if a save was interrupted after moving a shard but before the log
+        let mut shard_only = storage.clone();
+        let unlogged = public_transaction(2_000);
+        shard_only.transactions.insert(unlogged.id.clone(), unlogged.clone());
+        let mut batch = WriteBatch::default();
+        shard_only.stage_shard(&mut batch, &unlogged.id).unwrap();
+        batch.commit().unwrap();
+        
+        let reloaded = StorageData::load_from(dir.path()).unwrap();
+        assert!(reloaded.get_transaction(&logged.id).is_some());
+        assert!(reloaded.get_transaction(&unlogged.id).is_none());
+    }
+    
+    #[test]
+    fn save_removes_stale_shards_only_after_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        for amount in 1..=5 {
+            storage.add_transaction(public_transaction(amount * 1_000)).unwrap();
+        }
+        storage.clear().unwrap();
+        
+        let shards = fs::read_dir(dir.path().join(SHARD_DIR)).unwrap().count();
+        assert_eq!(shards, 0);
+        assert!(StorageData::load_from(dir.path()).unwrap().transactions.is_empty());
+    }
+    
+    #[test]
+    fn shielded_input_note_cannot_be_spent_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let sender = Wallet::new("alice").unwrap();
+        let first = ShieldedTransaction::create_shielded(&sender, &address('b'), 1_000).unwrap();
+        
+        // The note `create_shielded_signed` spent, re-derived from the sender's key
+        let nonce = crate::crypto::derive_opening(sender.spending_key().unwrap(), &first.id, "input", 0);
+        let note = Note::with_nonce(first.get_input_total().unwrap(), &sender.address, nonce).unwrap();
+        assert_eq!(first.input_commitments, vec![note.commitment.clone()]);
+        assert_eq!(first.nullifiers, vec![note.nullifier()]);
+        storage.add_transaction(first).unwrap();
+        
+        let second = ShieldedTransaction::create_shielded_multi(&sender, &[(address('c'), 500)], &[note]).unwrap();
+        let error = storage.add_transaction(second).unwrap_err();
+        assert!(matches!(&error, crate::error::ShieldedError::InvalidTransaction(reason) if reason.contains("nullifier")));
+    }
+    
+    #[test]
+    fn second_spend_of_a_create_shielded_input_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let first = ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 1_000).unwrap();
+        storage.add_transaction(first.clone()).unwrap();
+        
+        // The same input under a new id, without the nullifier that would give it away
+        let mut second = first.clone();
+        second.id = hex::encode(crate::crypto::hash(b"replayed spend"));
+        second.nullifiers.clear();
+        
+        // The spent set is rebuilt from the stored transactions, not read from a file
+        let mut reloaded = StorageData::load_from(dir.path()).unwrap();
+        assert!(!dir.path().join(SPENT_FILE).exists());
+        assert!(reloaded.is_commitment_spent(&first.input_commitments[0]));
+        let error = reloaded.add_transaction(second).unwrap_err();
+        assert!(matches!(&error, crate::error::ShieldedError::InvalidTransaction(reason) if reason.contains("already spent")));
+    }
+    
+    #[test]
+    fn version_one_files_are_migrated_with_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let transaction = public_transaction(1_000);
+        let mut legacy = serde_json::to_value(&transaction).unwrap();
+        let fields = legacy.as_object_mut().unwrap();
+        for added in ["sig_scheme", "label", "sequence", "prev_hash", "nullifiers", "anchor", "salt", "asset", "input_values", "output_values"] {
+            fields.remove(added);
+        }
+        fs::write(dir.path().join(STORAGE_FILE), json!({ &transaction.id: legacy }).to_string()).unwrap();
+        fs::write(dir.path().join(MERKLE_FILE), json!([&transaction.id]).to_string()).unwrap();
+        
+        let storage = StorageData::load_from(dir.path()).unwrap();
+        let loaded = storage.get_transaction(&transaction.id).unwrap();
+        assert_eq!(storage.merkle_leaves, vec![transaction.id.clone()]);
+        assert_eq!(loaded.amount, 1_000);
+        assert_eq!(loaded.sig_scheme, crate::crypto::KeyScheme::legacy());
+        assert!(loaded.nullifiers.is_empty() && loaded.salt.is_none() && loaded.sequence.is_none());
+        assert!(loaded.asset.is_native());
+    }
+    
+    #[test]
+    fn newer_storage_version_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(STORAGE_FILE), json!({ "version": CURRENT_VERSION + 1, "transactions": {} }).to_string()).unwrap();
+        
+        assert!(StorageData::load_from(dir.path()).is_err());
+    }
+    
+    #[test]
+    fn version_is_read_without_the_rest_of_the_file() {
+        let read = |json: &str| serde_json::from_str::<StoredVersion>(json).unwrap().0;
+        
+        assert_eq!(read(r#"{"transactions": {"a": {"nested": [1, 2]}}, "version": 2}"#), 2);
+        assert_eq!(read(r#"{"transactions": {}}"#), 1);
+        assert_eq!(read(r#"["id_a", "id_b"]"#), 1);
+    }
+    
+    #[test]
+    fn verification_flags_exactly_the_corrupted_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let sender = Wallet::new("alice").unwrap();
+        let mut ids = Vec::new();
+        for amount in [100, 200, 300] {
+            let transaction = ShieldedTransaction::create_shielded(&sender, &address('b'), amount).unwrap();
+            ids.push(transaction.id.clone());
+            storage.add_transaction(transaction).unwrap();
+        }
+        storage.transactions.get_mut(&ids[1]).unwrap().amount += 1;
+        
+        let summary = storage.verify_all(false);
+        assert_eq!(summary.failed.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![&ids[1]]);
+        assert_eq!(summary.passed.len(), 2);
+        
+        let failed: Vec<String> = storage.verification_reports()
+            .into_iter()
+            .filter(|(_, report)| !report.passed())
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(failed, vec![ids[1].clone()]);
+    }
+    
+    /// Run `check` against a fresh store of every backend, so they behave the same
+    fn for_each_backend(check: impl Fn(&mut dyn Storage)) {
+        for_each_backend_with(&TransactionPolicy::default(), check);
+    }
+    
+    /// Like `for_each_backend`, with each store enforcing `policy`
+    fn for_each_backend_with(policy: &TransactionPolicy, check: impl Fn(&mut dyn Storage)) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        storage.policy = policy.clone();
+        check(&mut storage);
+        #[cfg(feature = "sqlite")]
+        {
+            let mut storage = crate::sqlite_storage::SqliteStorage::open_in_memory().unwrap();
+            storage.policy = policy.clone();
+            check(&mut storage);
+        }
+    }
+    
+    #[test]
+    fn backends_store_and_chain_transactions() {
+        for_each_backend(|storage| {
+            let first = public_transaction(1_000);
+            let second = public_transaction(2_000);
+            storage.add_transaction(first.clone()).unwrap();
+            storage.add_transaction(second.clone()).unwrap();
+            
+            let stored = storage.get_transaction(&second.id).unwrap().unwrap();
+            assert_eq!(stored.amount, 2_000);
+            assert_eq!(stored.prev_hash, Some(storage.get_transaction(&first.id).unwrap().unwrap().chain_hash()));
+            assert_eq!(storage.get_transaction(&first.id).unwrap().unwrap().prev_hash.as_deref(), Some(GENESIS_HASH));
+            assert!(storage.get_transaction("missing").unwrap().is_none());
+            assert_eq!(storage.get_all_transactions().unwrap().len(), 2);
+            assert_eq!(storage.merkle_leaves().unwrap(), vec![first.id.clone(), second.id.clone()]);
+        });
+    }
+    
+    #[test]
+    fn backends_refuse_a_transaction_added_twice() {
+        for_each_backend(|storage| {
+            let transaction = public_transaction(1_000);
+            storage.add_transaction(transaction.clone()).unwrap();
+            
+            assert!(storage.add_transaction(transaction.clone()).is_err());
+            assert_eq!(storage.merkle_leaves().unwrap(), vec![transaction.id.clone()]);
+        });
+    }
+    
+    #[test]
+    fn backends_enforce_sequences() {
+        for_each_backend(|storage| {
+            let error = storage.add_transaction(public_transaction(1_000).with_sequence(1)).unwrap_err();
+            assert!(matches!(error, crate::error::ShieldedError::SequenceGap { expected: 0, found: 1, .. }));
+            
+            storage.add_transaction(public_transaction(1_000).with_sequence(0)).unwrap();
+            storage.add_transaction(public_transaction(2_000).with_sequence(1)).unwrap();
+            assert!(storage.add_transaction(public_transaction(3_000).with_sequence(1)).is_err());
+        });
+    }
+    
+    #[test]
+    fn backends_refuse_unknown_anchors_and_spent_notes() {
+        for_each_backend(|storage| {
+            let bogus = public_transaction(1_000).with_anchor("not a root".to_string());
+            assert!(storage.add_transaction(bogus).is_err());
+            storage.add_transaction(public_transaction(1_000).with_anchor(crate::merkle_tree::EMPTY_ROOT.to_string())).unwrap();
+            
+            let note = Note::new(1_000, &address('a')).unwrap();
+            let spend = |to| ShieldedTransaction::create_shielded_multi(&test_wallet('a'), &[(address(to), 500)], std::slice::from_ref(&note)).unwrap();
+            storage.add_transaction(spend('b')).unwrap();
+            assert!(storage.add_transaction(spend('c')).is_err());
+            assert_eq!(storage.merkle_leaves().unwrap().len(), 2);
+        });
+    }
+    
+    #[test]
+    fn backends_clear_everything() {
+        for_each_backend(|storage| {
+            storage.add_transaction(public_transaction(1_000)).unwrap();
+            storage.clear().unwrap();
+            
+            assert!(storage.get_all_transactions().unwrap().is_empty());
+            assert!(storage.merkle_leaves().unwrap().is_empty());
+        });
+    }
+    
+    /// A store in `dir` holding three transactions, the second of them tampered with
+    fn store_with_one_invalid(dir: &Path) -> Vec<String> {
+        let mut storage = StorageData::load_from(dir).unwrap();
+        let sender = Wallet::new("alice").unwrap();
+        let mut ids = Vec::new();
+        for amount in [100, 200, 300] {
+            let transaction = ShieldedTransaction::create_shielded(&sender, &address('b'), amount).unwrap();
+            ids.push(transaction.id.clone());
+            storage.add_transaction(transaction).unwrap();
+        }
+        storage.transactions.get_mut(&ids[1]).unwrap().amount += 1;
+        storage.save().unwrap();
+        ids
+    }
+    
+    #[test]
+    fn fail_fast_stops_at_the_first_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let ids = store_with_one_invalid(dir.path());
+        let storage = StorageData::load_from(dir.path()).unwrap();
+        
+        let summary = storage.verify_all(true);
+        assert_eq!((summary.checked, summary.stopped_early), (2, true));
+        assert_eq!(summary.passed, vec![ids[0].clone()]);
+        assert_eq!(summary.failed.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![&ids[1]]);
+        
+        let summary = storage.verify_all(false);
+        assert_eq!((summary.checked, summary.stopped_early), (3, false));
+        assert_eq!(summary.passed, vec![ids[0].clone(), ids[2].clone()]);
+        assert_eq!(summary.failed.len(), 1);
+    }
+    
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn parallel_verification_matches_serial() {
+        let dir = tempfile::tempdir().unwrap();
+        store_with_one_invalid(dir.path());
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        for amount in [1_000, 2_000] {
+            storage.add_transaction(public_transaction(amount)).unwrap();
+        }
+        let mut unanchored = public_transaction(3_000);
+        unanchored.anchor = Some("not a root".to_string());
+        storage.transactions.insert(unanchored.id.clone(), unanchored);
+        
+        for fail_fast in [false, true] {
+            let serial = storage.verify_all_with(fail_fast, false);
+            let parallel = storage.verify_all_with(fail_fast, true);
+            assert_eq!(parallel.checked, serial.checked);
+            assert_eq!(parallel.passed, serial.passed);
+            assert_eq!(parallel.failed, serial.failed);
+            assert_eq!(parallel.stopped_early, serial.stopped_early);
+        }
+        assert_eq!(storage.verify_all_with(false, true).failed.len(), 2);
+    }
+    
+    #[test]
+    fn eager_load_fails_on_an_invalid_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        store_with_one_invalid(dir.path());
+        
+        assert!(StorageData::load_from_with(dir.path(), VerifyMode::Eager).is_err());
+        assert!(StorageData::load_from_with(dir.path(), VerifyMode::None).is_ok());
+    }
+    
+    #[test]
+    fn lazy_load_verifies_only_what_is_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let ids = store_with_one_invalid(dir.path());
+        let storage = StorageData::load_from_with(dir.path(), VerifyMode::Lazy).unwrap();
+        assert_eq!(storage.verified_count(), 0);
+        
+        assert!(storage.get_transaction(&ids[0]).is_some());
+        assert!(storage.get_transaction(&ids[0]).is_some());
+        assert_eq!(storage.verified_count(), 1);
+        assert!(storage.get_transaction(&ids[1]).is_none());
+        assert_eq!(storage.verified_count(), 2);
+    }
+    
+    #[test]
+    fn lazy_get_all_transactions_withholds_invalid_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let ids = store_with_one_invalid(dir.path());
+        let storage = StorageData::load_from_with(dir.path(), VerifyMode::Lazy).unwrap();
+        
+        let all = storage.get_all_transactions();
+        assert_eq!(all.len(), 2);
+        assert!(!all.contains_key(ids[1].as_str()));
+        assert_eq!(storage.verified_count(), 3);
+        assert_eq!(Storage::get_all_transactions(&storage).unwrap().len(), 2);
+    }
+    
+    #[test]
+    fn recovered_wallet_rediscovers_its_notes_by_scanning() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let mnemonic = Wallet::generate_mnemonic();
+        let mut original = Wallet::recover("alice", &mnemonic, "").unwrap();
+        let sender = Wallet::new("bob").unwrap();
+        
+        for amount in [300, 500] {
+            let transaction = ShieldedTransaction::create_shielded_to(&sender, &original.address, &original.viewing_public_key, amount).unwrap();
+            original.track(&transaction);
+            storage.add_transaction(transaction).unwrap();
+        }
+        let spend = ShieldedTransaction::create_shielded_from_notes(&original, &address('c'), &sender.viewing_public_key, 100).unwrap();
+        original.track(&spend);
+        storage.add_transaction(spend).unwrap();
+        
+        let mut recovered = Wallet::recover("alice", &mnemonic, "").unwrap();
+        assert_eq!(storage.rescan(&mut recovered), original.notes.len());
+        
+        let commitments = |wallet: &Wallet| {
+            let mut commitments: Vec<String> = wallet.notes.iter().map(|note| note.commitment.clone()).collect();
+            commitments.sort();
+            commitments
+        };
+        assert_eq!(commitments(&recovered), commitments(&original));
+        assert_eq!(recovered.note_balance(), original.note_balance());
+        assert_eq!(recovered.note_balance(), 800 - 100 - 1);
+    }
+    
+    #[test]
+    fn backends_refuse_a_transaction_over_the_cap() {
+        let policy = TransactionPolicy { max_amount: Some(1_000), ..TransactionPolicy::default() };
+        for_each_backend_with(&policy, |storage| {
+            let error = storage.add_transaction(public_transaction(1_001)).unwrap_err();
+            assert!(matches!(error, crate::error::ShieldedError::InvalidAmount(_)));
+            storage.add_transaction(public_transaction(1_000)).unwrap();
+        });
+    }
+    
+    #[test]
+    fn backends_enforce_the_daily_cap() {
+        let policy = TransactionPolicy { daily_limit: Some(1_000), ..TransactionPolicy::default() };
+        for_each_backend_with(&policy, |storage| {
+            for amount in [400, 300, 300] {
+                storage.add_transaction(public_transaction(amount)).unwrap();
+            }
+            let error = storage.add_transaction(public_transaction(1)).unwrap_err();
+            assert!(matches!(error, crate::error::ShieldedError::InvalidAmount(_)));
+            
+            // Another sender has a cap of its own
+            let other = ShieldedTransaction::create_public(&test_wallet('c'), &address('b'), 1_000).unwrap();
+            storage.add_transaction(other).unwrap();
+        });
+    }
+    
+    #[test]
+    fn batch_counts_earlier_transactions_toward_the_daily_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        storage.policy.daily_limit = Some(1_000);
+        
+        assert!(storage.add_transactions(vec![public_transaction(600), public_transaction(600)]).is_err());
+        storage.add_transactions(vec![public_transaction(600), public_transaction(400)]).unwrap();
+    }
+    
+    #[test]
+    fn merge_adds_missing_transactions_and_reports_conflicts() {
+        let (here_dir, there_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let mut here = StorageData::load_from(here_dir.path()).unwrap();
+        let mut there = StorageData::load_from(there_dir.path()).unwrap();
+        let (shared, collided, disjoint) = (public_transaction(1_000), public_transaction(2_000), public_transaction(3_000));
+        let mut spent_here = public_transaction(4_000);
+        spent_here.nullifiers = vec!["nullifier".to_string()];
+        let mut spent_there = public_transaction(5_000);
+        spent_there.nullifiers = vec!["nullifier".to_string()];
+        let mut edited = collided.clone();
+        edited.amount = 2_500;
+        here.add_transactions(vec![shared.clone(), collided.clone(), spent_here]).unwrap();
+        there.add_transactions(vec![shared.clone(), edited, disjoint.clone(), spent_there.clone()]).unwrap();
+        
+        let report = here.merge(there).unwrap();
+        assert_eq!(report.added, vec![disjoint.id.clone()]);
+        assert_eq!(report.conflicts, vec![collided.id.clone()]);
+        assert_eq!(report.nullifier_conflicts, vec![spent_there.id.clone()]);
+        assert_eq!(here.get_transaction(&collided.id).unwrap().amount, 2_000);
+        assert_eq!(here.verify_chain().verified, 4);
+        assert_eq!(report.merkle_root, here.rebuild_merkle_tree().root());
+    }
+    
+    #[test]
+    fn merge_keeps_archived_transactions_archived() {
+        let (here_dir, there_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let mut here = StorageData::load_from(here_dir.path()).unwrap();
+        let mut there = StorageData::load_from(there_dir.path()).unwrap();
+        let archived = public_transaction(1_000);
+        there.add_transaction(archived.clone()).unwrap();
+        there.archive_transaction(&archived.id).unwrap();
+        
+        let report = here.merge(there).unwrap();
+        assert_eq!(report.added, vec![archived.id.clone()]);
+        assert!(here.archived.contains_key(&archived.id));
+        assert!(!here.transactions.contains_key(&archived.id));
+    }
+    
+    #[test]
+    fn merge_runs_the_insert_checks() {
+        let (here_dir, there_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let mut here = StorageData::load_from(here_dir.path()).unwrap();
+        let mut there = StorageData::load_from(there_dir.path()).unwrap();
+        let mut first_here = public_transaction(1_000);
+        first_here.sequence = Some(0);
+        here.add_transaction(first_here).unwrap();
+        
+        let mut first_there = public_transaction(2_000);
+        first_there.sequence = Some(0);
+        there.add_transaction(first_there.clone()).unwrap();
+        let mut anchored = public_transaction(3_000);
+        anchored.anchor = Some(there.current_anchor());
+        there.add_transaction(anchored.clone()).unwrap();
+        // Never logged, so it skipped the other store's checks
+        let mut unanchored = public_transaction(4_000);
+        unanchored.anchor = Some("not a root".to_string());
+        there.transactions.insert(unanchored.id.clone(), unanchored.clone());
+        
+        let report = here.merge(there).unwrap();
+        assert_eq!(report.added, vec![anchored.id.clone()]);
+        let rejected: Vec<&String> = report.rejected.iter().map(|(id, _)| id).collect();
+        assert_eq!(rejected, vec![&first_there.id, &unanchored.id]);
+        assert!(report.rejected[1].1.contains("unknown anchor"));
+    }
+    
+    #[test]
+    fn commitment_lifecycle_traces_creation_and_spend() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let sender = Wallet::new("alice").unwrap();
+        let mut recipient = Wallet::new("bob").unwrap();
+        let created = ShieldedTransaction::create_shielded_to(&sender, &recipient.address, &recipient.viewing_public_key, 500).unwrap();
+        recipient.track(&created);
+        storage.add_transaction(created.clone()).unwrap();
+        let note = recipient.notes[0].commitment.clone();
+        let spend = ShieldedTransaction::create_shielded_from_notes(&recipient, &address('c'), &sender.viewing_public_key, 100).unwrap();
+        storage.add_transaction(spend.clone()).unwrap();
+        
+        let lifecycle = storage.commitment_lifecycle(&note);
+        assert_eq!(lifecycle.created_in.as_ref(), Some(&created.id));
+        assert_eq!(lifecycle.spent_in.as_ref(), Some(&spend.id));
+        assert_eq!(lifecycle.status, CommitmentStatus::Spent);
+        
+        let unspent = storage.commitment_lifecycle(&spend.output_commitments[0]);
+        assert_eq!(unspent.created_in.as_ref(), Some(&spend.id));
+        assert_eq!(unspent.spent_in, None);
+        assert_eq!(unspent.status, CommitmentStatus::Unspent);
+        assert_eq!(storage.commitment_lifecycle("unknown").status, CommitmentStatus::Unknown);
+    }
+    
+    fn shard_contents(dir: &Path) -> HashMap<String, Vec<u8>> {
+        fs::read_dir(dir.join(SHARD_DIR))
+            .unwrap()
+            .flatten()
+            .map(|entry| (entry.file_name().to_string_lossy().into_owned(), fs::read(entry.path()).unwrap()))
+            .collect()
+    }
+    
+    #[test]
+    fn adding_a_transaction_rewrites_only_its_shard() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        for amount in 1..=8 {
+            storage.add_transaction(public_transaction(amount * 1_000)).unwrap();
+        }
+        let before = shard_contents(dir.path());
+        
+        let added = public_transaction(9_000);
+        let shard = format!("{}.json", shard_for(&added.id));
+        storage.add_transaction(added).unwrap();
+        let after = shard_contents(dir.path());
+        
+        assert!(after.len() > 1);
+        for (file, contents) in &after {
+            if *file != shard {
+                assert_eq!(before.get(file), Some(contents), "{} was rewritten", file);
+            }
+        }
+        assert_ne!(before.get(&shard), after.get(&shard));
+    }
+    
+    #[test]
+    fn shards_load_the_same_view_as_a_single_file() {
+        let (sharded_dir, single_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let mut storage = StorageData::load_from(sharded_dir.path()).unwrap();
+        for amount in 1..=5 {
+            storage.add_transaction(public_transaction(amount * 1_000)).unwrap();
+        }
+        let sharded = StorageData::load_from(sharded_dir.path()).unwrap();
+        
+        let file = TransactionsFile { version: CURRENT_VERSION, transactions: &sharded.transactions };
+        fs::write(single_dir.path().join(STORAGE_FILE), serde_json::to_string(&file).unwrap()).unwrap();
+        let log = json!({ "version": CURRENT_VERSION, "transaction_ids": &sharded.merkle_leaves });
+        fs::write(single_dir.path().join(MERKLE_FILE), log.to_string()).unwrap();
+        let single = StorageData::load_from(single_dir.path()).unwrap();
+        
+        assert_eq!(serde_json::to_value(&single.transactions).unwrap(), serde_json::to_value(&sharded.transactions).unwrap());
+        assert_eq!(single.merkle_leaves, sharded.merkle_leaves);
+        assert_eq!(single.merkle_tree().root(), sharded.merkle_tree().root());
+        assert!(single.verify_chain().is_intact());
+    }
+    
+    #[test]
+    fn stats_count_commitment_and_proof_bytes_of_shielded_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let shielded: Vec<ShieldedTransaction> = [1_000, 2_500]
+            .iter()
+            .map(|amount| ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), *amount).unwrap())
+            .collect();
+        storage.add_transaction(public_transaction(3_000)).unwrap();
+        for transaction in &shielded {
+            storage.add_transaction(transaction.clone()).unwrap();
+        }
+        
+        let commitment_bytes: usize = shielded
+            .iter()
+            .flat_map(|transaction| [
+                &transaction.input_commitments,
+                &transaction.output_commitments,
+                &transaction.input_value_commitments,
+                &transaction.output_value_commitments,
+            ])
+            .flatten()
+            .map(String::len)
+            .sum();
+        let proof_bytes: usize = shielded.iter().map(|transaction| transaction.zk_proof.as_ref().unwrap().len()).sum();
+        
+        let stats = storage.stats();
+        assert!(commitment_bytes > 0 && proof_bytes > 0);
+        assert_eq!(stats.commitment_bytes_total, commitment_bytes);
+        assert_eq!(stats.proof_bytes_total, proof_bytes);
+        assert_eq!(stats.commitment_bytes_avg, commitment_bytes as f64 / 2.0);
+        assert_eq!(stats.proof_bytes_avg, proof_bytes as f64 / 2.0);
+    }
+    
+    #[test]
+    fn snapshot_does_not_see_a_later_concurrent_add() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        storage.add_transaction(public_transaction(1_000)).unwrap();
+        let storage = Arc::new(std::sync::RwLock::new(storage));
+        
+        let before = storage.read().unwrap().snapshot();
+        let added = public_transaction(2_000);
+        let added_id = added.id.clone();
+        let writer = {
+            let storage = Arc::clone(&storage);
+            std::thread::spawn(move || storage.write().unwrap().add_transaction(added).unwrap())
+        };
+        // Reads on the snapshot run alongside the writer without taking the lock
+        assert_eq!(before.stats().total_transactions, 1);
+        writer.join().unwrap();
+        
+        assert_eq!(before.stats().total_transactions, 1);
+        assert!(before.get_transaction(&added_id).is_none());
+        let after = storage.read().unwrap().snapshot();
+        assert_eq!(after.stats().total_transactions, 2);
+        assert!(after.get_transaction(&added_id).is_some());
+    }
+    
+    #[test]
+    fn commitment_positions_give_proofs_against_the_current_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        storage.add_transaction(public_transaction(1_000)).unwrap();
+        for amount in [2_000, 3_000] {
+            storage.add_transaction(ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), amount).unwrap()).unwrap();
+        }
+        
+        let commitments = storage.all_commitments();
+        let leaf_data = storage.merkle_leaf_data();
+        let tree = storage.merkle_tree();
+        assert_eq!(commitments.len(), 4);
+        for (commitment, index) in &commitments {
+            let transaction = storage.get_transaction(&storage.merkle_leaves[*index]).unwrap();
+            assert!(transaction.output_commitments.contains(commitment));
+            
+            let proof = tree.generate_proof(*index).unwrap();
+            assert!(tree.verify_proof(&leaf_data[*index], &proof, *index).unwrap());
+        }
+    }
+    
+    #[test]
+    fn compressed_storage_round_trips_and_is_smaller_than_plain_json() {
+        let transactions: Vec<ShieldedTransaction> = (1..=20).map(|amount| public_transaction(amount * 1_000)).collect();
+        let plain_dir = tempfile::tempdir().unwrap();
+        let compressed_dir = tempfile::tempdir().unwrap();
+        let mut plain = StorageData::load_from(plain_dir.path()).unwrap();
+        let mut compressed = StorageData::load_from(compressed_dir.path()).unwrap();
+        compressed.compress = true;
+        for transaction in &transactions {
+            plain.add_transaction(transaction.clone()).unwrap();
+            compressed.add_transaction(transaction.clone()).unwrap();
+        }
+        plain.save().unwrap();
+        compressed.save().unwrap();
+        
+        assert!(fs::read(compressed_dir.path().join(MERKLE_FILE)).unwrap().starts_with(&GZIP_MAGIC));
+        assert!(!fs::read(plain_dir.path().join(MERKLE_FILE)).unwrap().starts_with(&GZIP_MAGIC));
+        assert!(compressed.disk_usage().unwrap() < plain.disk_usage().unwrap());
+        
+        // Both the gzipped files and the plain ones an older build wrote load the same
+        for dir in [&compressed_dir, &plain_dir] {
+            let reloaded = StorageData::load_from(dir.path()).unwrap();
+            assert_eq!(reloaded.merkle_leaves, plain.merkle_leaves);
+            assert_eq!(reloaded.merkle_tree().root(), plain.merkle_tree().root());
+            assert_eq!(reloaded.get_transaction(&transactions[7].id).unwrap().amount, 8_000);
+        }
+    }
+    
+    #[test]
+    fn larger_pools_of_unspent_notes_give_larger_anonymity_sets() {
+        let shielded = |amount| ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), amount).unwrap();
+        let anonymity_after = |pool_size: u64| {
+            let dir = tempfile::tempdir().unwrap();
+            let mut storage = StorageData::load_from(dir.path()).unwrap();
+            for amount in 1..=pool_size {
+                storage.add_transaction(shielded(amount * 1_000)).unwrap();
+            }
+            let spend = shielded(500);
+            let spend_id = spend.id.clone();
+            storage.add_transaction(spend).unwrap();
+            storage.anonymity_set(&spend_id)
+        };
+        
+        let small = anonymity_after(1);
+        let large = anonymity_after(5);
+        assert!(small > 0);
+        assert!(large > small);
+        
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let public = public_transaction(1_000);
+        let public_id = public.id.clone();
+        storage.add_transaction(public).unwrap();
+        assert_eq!(storage.anonymity_set(&public_id), 0);
+    }
+    
+    #[test]
+    fn multi_recipient_transaction_debits_the_total_and_credits_each_recipient() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let outputs = vec![(address('b'), 1_000), (address('c'), 2_000), (address('d'), 3_000)];
+        let transaction = ShieldedTransaction::create_public_multi(&test_wallet('a'), &outputs).unwrap();
+        let fee = transaction.fee;
+        assert_eq!(transaction.amount, 6_000);
+        assert!(transaction.is_balanced());
+        storage.add_transaction(transaction).unwrap();
+        
+        let balances = storage.reconcile();
+        assert_eq!(balances[&address('a')], -(6_000 + fee as i128));
+        for (to, amount) in outputs {
+            assert_eq!(balances[&to], amount as i128);
+        }
+    }
+    
+    #[test]
+    fn nullifier_is_spent_once_its_transaction_is_added() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let spent = Note::new(5_000, &address('a')).unwrap();
+        let unspent = Note::new(5_000, &address('a')).unwrap();
+        let transaction = ShieldedTransaction::create_shielded_multi(&test_wallet('a'), &[(address('b'), 1_000)], std::slice::from_ref(&spent)).unwrap();
+        
+        assert!(!storage.is_spent(&spent.nullifier()));
+        storage.add_transaction(transaction).unwrap();
+        assert!(storage.is_spent(&spent.nullifier()));
+        assert!(!storage.is_spent(&unspent.nullifier()));
+        assert!(!storage.is_spent("not a nullifier"));
+    }
+    
+    #[test]
+    fn archiving_hides_a_transaction_but_keeps_its_leaf_until_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut storage, ids) = chained_store(dir.path());
+        let root = storage.merkle_tree().root();
+        let leaves = storage.merkle_leaves.clone();
+        
+        storage.archive_transaction(&ids[1]).unwrap();
+        assert!(!storage.get_all_transactions().contains_key(ids[1].as_str()));
+        assert_eq!(storage.get_all_transactions().len(), 3);
+        assert_eq!(storage.list_archived().iter().map(|transaction| &transaction.id).collect::<Vec<_>>(), vec![&ids[1]]);
+        assert_eq!(storage.merkle_tree().root(), root);
+        assert_eq!(storage.merkle_leaves, leaves);
+        
+        let reloaded = StorageData::load_from(dir.path()).unwrap();
+        assert_eq!(reloaded.list_archived().len(), 1);
+        assert_eq!(reloaded.merkle_tree().root(), root);
+        
+        storage.restore_archived(&ids[1]).unwrap();
+        assert!(storage.get_all_transactions().contains_key(ids[1].as_str()));
+        assert!(storage.list_archived().is_empty());
+        assert_eq!(storage.merkle_tree().root(), root);
+    }
+    
+    #[test]
+    fn transaction_anchored_to_a_known_root_verifies_and_a_fabricated_one_does_not() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        storage.add_transaction(public_transaction(1_000)).unwrap();
+        
+        let shielded = |amount| ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), amount).unwrap();
+        let anchored = shielded(2_000).with_anchor(storage.current_anchor());
+        assert_eq!(storage.verify_anchor(&anchored), VerifyOutcome::Valid);
+        storage.add_transaction(anchored.clone()).unwrap();
+        // Still a historical root once the tree has moved on
+        assert_eq!(storage.verify_anchor(&anchored), VerifyOutcome::Valid);
+        
+        let fabricated = shielded(3_000).with_anchor("ab".repeat(32));
+        assert!(storage.verify_anchor(&fabricated).is_invalid());
+        assert!(storage.add_transaction(fabricated).is_err());
+    }
+    
+    /// Reader that records the largest read it was asked for
+    struct CappedReader<'a> {
+        inner: &'a [u8],
+        largest_read: usize,
+    }
+    
+    impl Read for CappedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.largest_read = self.largest_read.max(buf.len());
+            self.inner.read(buf)
+        }
+    }
+    
+    #[test]
+    fn streamed_load_matches_a_whole_file_parse_in_bounded_reads() {
+        let transactions: HashMap<String, ShieldedTransaction> = (1..=500)
+            .map(|amount| public_transaction(amount * 1_000))
+            .map(|transaction| (transaction.id.clone(), transaction))
+            .collect();
+        let file = serde_json::to_vec_pretty(&transactions).unwrap();
+        
+        let mut reader = CappedReader { inner: &file, largest_read: 0 };
+        let streamed: HashMap<String, ShieldedTransaction> = read_json_from(&mut reader, "transactions").unwrap();
+        let whole: HashMap<String, ShieldedTransaction> = serde_json::from_slice(&file).unwrap();
+        
+        assert_eq!(streamed.len(), 500);
+        assert_eq!(serde_json::to_value(&streamed).unwrap(), serde_json::to_value(&whole).unwrap());
+        // Reads go through a fixed-size buffer, never the whole file at once
+        assert!(reader.largest_read <= 64 * 1024, "read {} bytes at once", reader.largest_read);
+        assert!(file.len() > 8 * reader.largest_read);
+    }
+    
+    #[test]
+    fn verify_all_fails_an_expired_pending_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let mut live = public_transaction(1_000).with_ttl(chrono::Duration::hours(1));
+        let mut expired = public_transaction(2_000).with_expiry(chrono::Utc::now() + chrono::Duration::milliseconds(50));
+        live.resign(&test_wallet('a')).unwrap();
+        expired.resign(&test_wallet('a')).unwrap();
+        let expired_id = expired.id.clone();
+        storage.add_transaction(live).unwrap();
+        storage.add_transaction(expired).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(60));
+        
+        let summary = storage.verify_all(false);
+        assert_eq!(summary.passed.len(), 1);
+        assert_eq!(summary.failed.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![&expired_id]);
+    }
+    
+    #[test]
+    fn save_to_writes_only_to_the_given_directory() {
+        let cwd_had_files = Path::new(MERKLE_FILE).exists() || Path::new(SHARD_DIR).exists();
+        let first = tempfile::tempdir().unwrap();
+        let (mut storage, ids) = chained_store(first.path());
+        let root = storage.merkle_tree().root();
+        
+        let second = tempfile::tempdir().unwrap();
+        storage.save_to(second.path()).unwrap();
+        assert_eq!(storage.dir(), second.path());
+        for dir in [first.path(), second.path()] {
+            let reloaded = StorageData::load_from(dir).unwrap();
+            assert_eq!(reloaded.merkle_leaves, ids);
+            assert_eq!(reloaded.merkle_tree().root(), root);
+        }
+        
+        // Further writes follow the store to its new directory
+        storage.add_transaction(public_transaction(9_000)).unwrap();
+        assert_eq!(StorageData::load_from(first.path()).unwrap().transactions.len(), 4);
+        assert_eq!(StorageData::load_from(second.path()).unwrap().transactions.len(), 5);
+        if !cwd_had_files {
+            assert!(!Path::new(MERKLE_FILE).exists() && !Path::new(SHARD_DIR).exists());
+        }
+    }
+    
+    /// Five transactions a minute apart, as (from, to, type, status)
+    fn filterable_store() -> (StorageData, Vec<String>) {
+        let start = Utc::now();
+        let rows = [
+            ('a', 'b', TransactionType::Public, TransactionStatus::Pending),
+            ('a', 'c', TransactionType::Shielded, TransactionStatus::Failed),
+            ('b', 'c', TransactionType::Public, TransactionStatus::Confirmed),
+            ('a', 'b', TransactionType::Shielded, TransactionStatus::Failed),
+            ('c', 'a', TransactionType::Public, TransactionStatus::Pending),
+        ];
+        let mut storage = StorageData::new();
+        let mut ids = Vec::new();
+        for (minute, (from, to, transaction_type, status)) in rows.into_iter().enumerate() {
+            let mut transaction = ShieldedTransaction::create_public(&test_wallet(from), &address(to), 1_000).unwrap();
+            transaction.transaction_type = transaction_type;
+            transaction.status = status;
+            transaction.timestamp = start + chrono::Duration::minutes(minute as i64);
+            ids.push(transaction.id.clone());
+            storage.transactions.insert(transaction.id.clone(), transaction);
+        }
+        (storage, ids)
+    }
+    
+    fn query_ids(storage: &StorageData, filter: &TransactionFilter, offset: usize, limit: Option<usize>) -> Vec<String> {
+        storage.query_transactions(filter, offset, limit).into_iter().map(|transaction| transaction.id.clone()).collect()
+    }
+    
+    #[test]
+    fn each_filter_dimension_and_their_combination_select_the_right_transactions() {
+        let (storage, ids) = filterable_store();
+        let pick = |indices: &[usize]| indices.iter().map(|&i| ids[i].clone()).collect::<Vec<_>>();
+        let timestamp = |i: usize| storage.transactions[&ids[i]].timestamp;
+        
+        assert_eq!(query_ids(&storage, &TransactionFilter::default(), 0, None), ids);
+        let by_from = TransactionFilter { from: Some(address('a')), ..TransactionFilter::default() };
+        assert_eq!(query_ids(&storage, &by_from, 0, None), pick(&[0, 1, 3]));
+        let by_to = TransactionFilter { to: Some(address('c')), ..TransactionFilter::default() };
+        assert_eq!(query_ids(&storage, &by_to, 0, None), pick(&[1, 2]));
+        let by_type = TransactionFilter { transaction_type: Some(TransactionType::Shielded), ..TransactionFilter::default() };
+        assert_eq!(query_ids(&storage, &by_type, 0, None), pick(&[1, 3]));
+        let by_status = TransactionFilter { status: Some(TransactionStatus::Pending), ..TransactionFilter::default() };
+        assert_eq!(query_ids(&storage, &by_status, 0, None), pick(&[0, 4]));
+        let by_time = TransactionFilter { since: Some(timestamp(1)), until: Some(timestamp(3)), ..TransactionFilter::default() };
+        assert_eq!(query_ids(&storage, &by_time, 0, None), pick(&[1, 2]));
+        
+        let combined = TransactionFilter {
+            from: Some(address('a')),
+            transaction_type: Some(TransactionType::Shielded),
+            status: Some(TransactionStatus::Failed),
+            since: Some(timestamp(2)),
+            ..TransactionFilter::default()
+        };
+        assert_eq!(query_ids(&storage, &combined, 0, None), pick(&[3]));
+    }
+    
+    #[test]
+    fn offset_and_limit_page_through_the_matches() {
+        let (storage, ids) = filterable_store();
+        let all = TransactionFilter::default();
+        assert_eq!(query_ids(&storage, &all, 1, Some(2)), ids[1..3].to_vec());
+        assert_eq!(query_ids(&storage, &all, 4, Some(10)), ids[4..].to_vec());
+        assert!(query_ids(&storage, &all, 5, None).is_empty());
+        
+        // Paging applies after filtering
+        let by_from = TransactionFilter { from: Some(address('a')), ..TransactionFilter::default() };
+        assert_eq!(query_ids(&storage, &by_from, 1, Some(1)), vec![ids[1].clone()]);
+    }
+    
+    #[test]
+    fn transactions_by_time_is_stable_across_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        // Shared timestamps leave the order to the id tiebreaker
+        let timestamp = Utc::now();
+        let transactions: Vec<ShieldedTransaction> = (1..=6)
+            .map(|amount| {
+                let mut transaction = public_transaction(amount * 1_000);
+                transaction.timestamp = timestamp + chrono::Duration::seconds((amount % 2) as i64);
+                transaction
+            })
+            .collect();
+        for transaction in transactions {
+            storage.add_transaction(transaction).unwrap();
+        }
+        
+        let order = |storage: &StorageData| storage.transactions_by_time().iter().map(|transaction| transaction.id.clone()).collect::<Vec<_>>();
+        let expected = order(&storage);
+        let keys: Vec<_> = storage.transactions_by_time().iter().map(|transaction| (transaction.timestamp, transaction.id.clone())).collect();
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        for _ in 0..3 {
+            let reloaded = StorageData::load_from(dir.path()).unwrap();
+            assert_eq!(order(&reloaded), expected);
+            assert_eq!(reloaded.merkle_leaves, storage.merkle_leaves);
+            assert_eq!(reloaded.merkle_tree().root(), storage.merkle_tree().root());
+        }
+    }
+    
+    #[test]
+    fn anonymity_set_counts_only_outputs_in_the_spent_asset() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let atom = AssetId::new("ATOM");
+        // Two NAM and two ATOM outputs, change included
+        let inputs = vec![
+            Note::new(1_000, &address('a')).unwrap(),
+            Note::with_asset(atom.clone(), 50, &address('a')).unwrap(),
+        ];
+        let outputs = vec![(address('b'), AssetId::native(), 500), (address('c'), atom.clone(), 20)];
+        storage.add_transaction(ShieldedTransaction::create_shielded_multi_asset(&test_wallet('a'), &outputs, &inputs).unwrap()).unwrap();
+        
+        let nam_spend = ShieldedTransaction::create_shielded(&test_wallet('a'), &address('b'), 100).unwrap();
+        let nam_id = nam_spend.id.clone();
+        storage.add_transaction(nam_spend).unwrap();
+        let atom_inputs = vec![Note::with_asset(atom.clone(), 40, &address('c')).unwrap()];
+        let atom_spend = ShieldedTransaction::create_shielded_multi_asset(&test_wallet('c'), &[(address('d'), atom, 10)], &atom_inputs).unwrap();
+        let atom_id = atom_spend.id.clone();
+        storage.add_transaction(atom_spend).unwrap();
+        
+        assert_eq!(storage.anonymity_set(&nam_id), 2);
+        // The NAM spend's own outputs came before it but can't hide an ATOM note
+        assert_eq!(storage.anonymity_set(&atom_id), 2);
+    }
+    
+    #[test]
+    fn stats_keep_amounts_and_fees_of_each_asset_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let atom = AssetId::new("ATOM");
+        let nam = public_transaction(1_000);
+        let nam_fee = nam.fee;
+        storage.add_transaction(nam).unwrap();
+        let inputs = vec![Note::with_asset(atom.clone(), 500, &address('a')).unwrap()];
+        let atom_transfer = ShieldedTransaction::create_shielded_multi_asset(&test_wallet('a'), &[(address('b'), atom.clone(), 300)], &inputs).unwrap();
+        let atom_fee = atom_transfer.fee;
+        storage.add_transaction(atom_transfer).unwrap();
+        
+        let stats = storage.stats();
+        assert_eq!(stats.amount_by_asset, BTreeMap::from([(AssetId::native(), 1_000), (atom.clone(), 300)]));
+        assert_eq!(stats.fees_by_asset, BTreeMap::from([(AssetId::native(), nam_fee), (atom, atom_fee)]));
+    }
+    
+    #[test]
+    fn legacy_signed_store_loads_only_with_the_opt_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = StorageData::load_from(dir.path()).unwrap();
+        let alice = test_wallet('a');
+        let mut transaction = ShieldedTransaction::create_public(&alice, &address('b'), 1_000).unwrap();
+        use sha2::Digest;
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(transaction.signing_message());
+        hasher.update(hex::decode(&alice.public_key).unwrap());
+        transaction.signature = hex::encode(hasher.finalize());
+        transaction.sig_scheme = crate::crypto::KeyScheme::Legacy;
+        storage.add_transaction(transaction).unwrap();
+        storage.save().unwrap();
+        
+        assert!(StorageData::load_from_with(dir.path(), VerifyMode::Eager).is_err());
+        let storage = StorageData::load_from_allowing_legacy(dir.path(), VerifyMode::Eager).unwrap();
+        assert!(storage.verify_all(false).failed.is_empty());
+        assert!(storage.verification_reports().iter().all(|(_, report)| report.signature.is_valid()));
+    }
+}
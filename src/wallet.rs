@@ -1,46 +1,370 @@
-use crate::{error::Result, crypto::generate_keypair};
+use crate::{
+    error::Result,
+    crypto::{derive_public_key_for, derive_viewing_keypair, generate_keypair_for, generate_nonce, hash, sign_with, KeyScheme},
+    note::Note,
+    shielded_transaction::{ShieldedTransaction, TransactionType},
+};
+use aes_gcm::{aead::{Aead, Payload}, Aes256Gcm, KeyInit, Nonce};
+use argon2::Argon2;
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use hex;
 
+/// Prefix of every wallet address, followed by 40 hex characters
+pub const ADDRESS_PREFIX: &str = "namada_";
+
+/// A private key sealed with a password: Argon2 stretches the password into an
+/// AES-256-GCM key, and the wallet address is authenticated alongside the ciphertext
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Wallet {
     pub name: String,
     pub address: String,
     pub public_key: String,
-    pub private_key: String, // In production, this would be encrypted
+    /// Plaintext private key, empty once the wallet is encrypted
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub private_key: String,
+    /// Password-encrypted private key, replacing `private_key` at rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_key: Option<EncryptedKey>,
+    /// Plaintext seed receive addresses are derived from; sealed in `encrypted_key`
+    /// along with the private key once the wallet is encrypted, and empty for
+    /// wallets created before seeds were kept
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub seed: String,
+    /// Receive addresses handed out by `new_receive_address`; address `i` is
+    /// `derive_address(i)`
+    #[serde(default)]
+    pub receive_addresses: Vec<String>,
+    /// Signature scheme of `public_key`/`private_key`
+    #[serde(default = "KeyScheme::legacy")]
+    pub key_scheme: KeyScheme,
     pub balance: u64,
     pub shielded_balance: u64,
+    /// Secret half of the viewing keypair, able to decrypt but not spend notes
+    pub viewing_key: String,
+    pub viewing_public_key: String,
+    /// Shielded notes received by this wallet
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    /// Sequence number the next transaction from this wallet will carry
+    #[serde(default)]
+    pub sequence: u64,
 }
 
 impl Wallet {
     pub fn new(name: &str) -> Result<Self> {
-        let (public_key, private_key) = generate_keypair()?;
+        Self::new_with_scheme(name, KeyScheme::default())
+    }
+    
+    /// Create a wallet whose keys belong to a specific signature scheme
+    pub fn new_with_scheme(name: &str, key_scheme: KeyScheme) -> Result<Self> {
+        let (public_key, private_key) = generate_keypair_for(key_scheme)?;
+        Self::from_keys(name, key_scheme, public_key, private_key, &generate_nonce())
+    }
+    
+    /// Create a wallet whose private key is only stored encrypted under a password
+    pub fn new_encrypted(name: &str, password: &str) -> Result<Self> {
+        let mut wallet = Self::new(name)?;
+        wallet.encrypt_private_key(password)?;
+        Ok(wallet)
+    }
+    
+    /// Encrypt the private key and seed under a password and drop the plaintext copies
+    pub fn encrypt_private_key(&mut self, password: &str) -> Result<()> {
+        // The seed, if any, is sealed with the key as `private_key:seed`
+        let mut private_key = self.spending_key()?.to_string();
+        if !self.seed.is_empty() {
+            private_key = format!("{}:{}", private_key, self.seed);
+        }
+        let random = generate_nonce();
+        let (salt, nonce) = (&random[..16], &random[16..28]);
+        
+        let ciphertext = Self::key_cipher(password, salt)?
+            .encrypt(Nonce::from_slice(nonce), Payload { msg: private_key.as_bytes(), aad: self.address.as_bytes() })
+            .map_err(|_| crate::error::ShieldedError::CryptoError("Failed to encrypt private key".to_string()))?;
+        
+        self.encrypted_key = Some(EncryptedKey {
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        });
+        self.private_key.clear();
+        self.seed.clear();
+        Ok(())
+    }
+    
+    /// Decrypt the private key with the wallet password, without storing it
+    ///
+    /// A wrong password (or a key copied from another wallet) gives a `CryptoError`.
+    pub fn unlock(&self, password: &str) -> Result<String> {
+        self.unseal(password).map(|(private_key, _)| private_key)
+    }
+    
+    /// Decrypt the private key and seed sealed by `encrypt_private_key`
+    fn unseal(&self, password: &str) -> Result<(String, String)> {
+        let encrypted = self.encrypted_key.as_ref().ok_or_else(|| {
+            crate::error::ShieldedError::CryptoError(format!("Wallet '{}' is not encrypted", self.name))
+        })?;
+        let decode = |value: &str| hex::decode(value)
+            .map_err(|_| crate::error::ShieldedError::CryptoError("Malformed encrypted key".to_string()));
+        let (salt, nonce, ciphertext) = (decode(&encrypted.salt)?, decode(&encrypted.nonce)?, decode(&encrypted.ciphertext)?);
+        if nonce.len() != 12 {
+            return Err(crate::error::ShieldedError::CryptoError("Malformed encrypted key".to_string()));
+        }
+        
+        let plaintext = Self::key_cipher(password, &salt)?
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: self.address.as_bytes() })
+            .map_err(|_| crate::error::ShieldedError::CryptoError("Wrong password for wallet key".to_string()))?;
+        let plaintext = String::from_utf8(plaintext)
+            .map_err(|_| crate::error::ShieldedError::CryptoError("Malformed encrypted key".to_string()))?;
+        // Keys sealed before seeds were kept hold the private key alone
+        Ok(match plaintext.split_once(':') {
+            Some((private_key, seed)) => (private_key.to_string(), seed.to_string()),
+            None => (plaintext, String::new()),
+        })
+    }
+    
+    /// An in-memory copy of this wallet holding the decrypted private key, for signing
+    ///
+    /// Never store the copy: it serializes the plaintext key. Unencrypted wallets
+    /// are returned as they are, whatever the password.
+    pub fn unlocked(&self, password: &str) -> Result<Self> {
+        let mut wallet = self.clone();
+        if self.is_encrypted() {
+            (wallet.private_key, wallet.seed) = self.unseal(password)?;
+            wallet.encrypted_key = None;
+        }
+        Ok(wallet)
+    }
+    
+    /// Whether the private key is stored encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted_key.is_some()
+    }
+    
+    /// The plaintext private key, or a `CryptoError` if the wallet is encrypted
+    pub fn spending_key(&self) -> Result<&str> {
+        if self.is_encrypted() {
+            return Err(crate::error::ShieldedError::CryptoError(
+                format!("Wallet '{}' is encrypted; unlock it first", self.name)
+            ));
+        }
+        Ok(&self.private_key)
+    }
+    
+    fn key_cipher(password: &str, salt: &[u8]) -> Result<Aes256Gcm> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .map_err(|e| crate::error::ShieldedError::CryptoError(format!("Failed to derive wallet key: {}", e)))?;
+        Ok(Aes256Gcm::new(&key.into()))
+    }
+    
+    /// Create a wallet whose keys are derived from a hash of its name
+    ///
+    /// INSECURE, demo only: anyone who knows the name can derive the private key.
+    /// Useful for documentation examples that need stable addresses.
+    pub fn new_deterministic(name: &str, key_scheme: KeyScheme) -> Result<Self> {
+        key_scheme.ensure_secure()?;
+        tracing::warn!("Wallet '{}' uses deterministic keys derived from its name; never use it for real funds", name);
+        
+        let mut private_key = hash(format!("deterministic_wallet:{}", name).as_bytes());
+        // Rehash on the rare secp256k1 key outside the group order
+        let public_key = loop {
+            match derive_public_key_for(key_scheme, &private_key) {
+                Ok(public_key) => break public_key,
+                Err(_) => private_key = hash(&private_key),
+            }
+        };
+        
+        let seed = hash(format!("deterministic_seed:{}", name).as_bytes());
+        Self::from_keys(name, key_scheme, public_key, hex::encode(private_key), &seed)
+    }
+    
+    /// Generate a fresh 24-word BIP-39 mnemonic to create a wallet from
+    pub fn generate_mnemonic() -> String {
+        Self::generate_mnemonic_with(24).expect("24 words is a valid mnemonic length")
+    }
+    
+    /// Generate a fresh BIP-39 mnemonic of 12, 15, 18, 21 or 24 words
+    pub fn generate_mnemonic_with(word_count: usize) -> Result<String> {
+        if !matches!(word_count, 12 | 15 | 18 | 21 | 24) {
+            return Err(crate::error::ShieldedError::CryptoError(
+                format!("Mnemonic must have 12, 15, 18, 21 or 24 words, not {}", word_count)
+            ));
+        }
+        let entropy = generate_nonce();
+        let mnemonic = bip39::Mnemonic::from_entropy(&entropy[..word_count / 3 * 4])
+            .map_err(|e| crate::error::ShieldedError::CryptoError(format!("Failed to generate mnemonic: {}", e)))?;
+        Ok(mnemonic.to_string())
+    }
+    
+    /// Create a wallet from a BIP-39 mnemonic without a passphrase
+    ///
+    /// The same phrase always gives the same keys and address, so writing it
+    /// down backs the wallet up; a phrase with a bad checksum is a `CryptoError`.
+    pub fn from_mnemonic(name: &str, mnemonic: &str) -> Result<Self> {
+        Self::recover(name, mnemonic, "")
+    }
+    
+    /// Recover a wallet from its BIP-39 mnemonic and optional passphrase
+    ///
+    /// Keys and address are re-derived from the seed, the spending key along its own
+    /// `spending` path so it never equals raw seed bytes; the note set is empty until
+    /// rebuilt with `StorageData::rescan`.
+    pub fn recover(name: &str, mnemonic: &str, passphrase: &str) -> Result<Self> {
+        let mnemonic = bip39::Mnemonic::parse(mnemonic)
+            .map_err(|e| crate::error::ShieldedError::CryptoError(format!("Invalid mnemonic: {}", e)))?;
+        let seed = mnemonic.to_seed(passphrase);
+        
+        let private_key = seed_key(&seed, "spending");
+        let key_scheme = KeyScheme::default();
+        let public_key = derive_public_key_for(key_scheme, &private_key)?;
+        
+        Self::from_keys(name, key_scheme, public_key, hex::encode(private_key), &seed)
+    }
+    
+    fn from_keys(name: &str, key_scheme: KeyScheme, public_key: String, private_key: String, seed: &[u8]) -> Result<Self> {
         let address = Self::generate_address(&public_key)?;
+        let private_key_bytes: [u8; 32] = hex::decode(&private_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crate::error::ShieldedError::CryptoError("Private key must be 32 bytes".to_string()))?;
+        let (viewing_public_key, viewing_key) = derive_viewing_keypair(&private_key_bytes);
         
         Ok(Self {
             name: name.to_string(),
             address,
             public_key,
             private_key,
+            encrypted_key: None,
+            seed: hex::encode(seed),
+            receive_addresses: Vec::new(),
+            key_scheme,
             balance: 1000, // Starting balance for demo
             shielded_balance: 0,
+            viewing_key,
+            viewing_public_key,
+            notes: Vec::new(),
+            sequence: 0,
         })
     }
     
-    fn generate_address(public_key: &str) -> Result<String> {
+    /// Receive address number `index`, the address of `derive_keypair(index)`
+    pub fn derive_address(&self, index: u32) -> Result<String> {
+        let (public_key, _) = self.derive_keypair(index)?;
+        Self::generate_address(&public_key)
+    }
+    
+    /// Child keypair number `index` as `(public key, private key)`, derived from the seed
+    ///
+    /// Each index gives a child key `SHA256("hd_child" || seed || index)` in the
+    /// wallet's scheme, so the same wallet always derives the same keys, and wallets
+    /// recovered from one mnemonic share them. Errors while the wallet is encrypted
+    /// or if it has no seed.
+    pub fn derive_keypair(&self, index: u32) -> Result<(String, String)> {
+        if self.is_encrypted() {
+            return Err(crate::error::ShieldedError::CryptoError(
+                format!("Wallet '{}' is encrypted; unlock it first", self.name)
+            ));
+        }
+        let seed = hex::decode(&self.seed)
+            .ok()
+            .filter(|seed| !seed.is_empty())
+            .ok_or_else(|| crate::error::ShieldedError::CryptoError(format!("Wallet '{}' has no seed", self.name)))?;
+        
+        let mut hasher = Sha256::new();
+        hasher.update(b"hd_child");
+        hasher.update((seed.len() as u64).to_le_bytes());
+        hasher.update(&seed);
+        hasher.update(index.to_le_bytes());
+        let mut child_key: [u8; 32] = hasher.finalize().into();
+        // Rehash on the rare secp256k1 key outside the group order, as `new_deterministic` does
+        let public_key = loop {
+            match derive_public_key_for(self.key_scheme, &child_key) {
+                Ok(public_key) => break public_key,
+                Err(_) => child_key = hash(&child_key),
+            }
+        };
+        
+        Ok((public_key, hex::encode(child_key)))
+    }
+    
+    /// Derive the next receive address and remember it, so the wallet is found by it
+    pub fn new_receive_address(&mut self) -> Result<String> {
+        let index = u32::try_from(self.receive_addresses.len())
+            .map_err(|_| crate::error::ShieldedError::CryptoError("No receive addresses left".to_string()))?;
+        let address = self.derive_address(index)?;
+        self.receive_addresses.push(address.clone());
+        Ok(address)
+    }
+    
+    /// Whether `address` is this wallet's own address or one of its receive addresses
+    pub fn owns_address(&self, address: &str) -> bool {
+        self.address == address || self.receive_addresses.iter().any(|receive| receive == address)
+    }
+    
+    /// The `(public key, private key)` that sign for `address`: the derived keypair
+    /// for one of the wallet's receive addresses, and its own keys otherwise
+    pub fn signing_keys(&self, address: &str) -> Result<(String, String)> {
+        match self.receive_addresses.iter().position(|receive| receive == address) {
+            Some(index) => self.derive_keypair(index as u32),
+            None => Ok((self.public_key.clone(), self.spending_key()?.to_string())),
+        }
+    }
+    
+    /// Address of a public key: `namada_` and the first 20 bytes of its SHA-256, in hex
+    pub fn generate_address(public_key: &str) -> Result<String> {
         let mut hasher = Sha256::new();
         hasher.update(public_key.as_bytes());
         let result = hasher.finalize();
-        Ok(format!("namada_{}", hex::encode(&result[..20])))
+        Ok(format!("{}{}", ADDRESS_PREFIX, hex::encode(&result[..20])))
+    }
+    
+    /// Check that `address` is `namada_` followed by 40 hex characters, as wallet
+    /// addresses are, returning `InvalidWalletAddress` otherwise
+    pub fn validate_address(address: &str) -> Result<()> {
+        let body = address.strip_prefix(ADDRESS_PREFIX).ok_or_else(|| {
+            crate::error::ShieldedError::InvalidWalletAddress(
+                format!("Address '{}' does not start with {}", address, ADDRESS_PREFIX)
+            )
+        })?;
+        if body.len() != 40 {
+            return Err(crate::error::ShieldedError::InvalidWalletAddress(
+                format!("Address '{}' needs 40 hex characters after {}, got {}", address, ADDRESS_PREFIX, body.len())
+            ));
+        }
+        if !body.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(crate::error::ShieldedError::InvalidWalletAddress(
+                format!("Address '{}' has non-hex characters after {}", address, ADDRESS_PREFIX)
+            ));
+        }
+        Ok(())
     }
     
-    pub fn add_funds(&mut self, amount: u64) {
-        self.balance += amount;
+    /// Credit the transparent balance, failing if it would overflow
+    pub fn add_funds(&mut self, amount: u64) -> Result<()> {
+        self.balance = self
+            .balance
+            .checked_add(amount)
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Transparent balance overflows".to_string()))?;
+        Ok(())
     }
     
-    pub fn add_shielded_funds(&mut self, amount: u64) {
-        self.shielded_balance += amount;
+    /// Credit the shielded balance, failing if it would overflow
+    pub fn add_shielded_funds(&mut self, amount: u64) -> Result<()> {
+        self.shielded_balance = self
+            .shielded_balance
+            .checked_add(amount)
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Shielded balance overflows".to_string()))?;
+        Ok(())
     }
     
     pub fn spend(&mut self, amount: u64) -> Result<()> {
@@ -65,16 +389,367 @@ impl Wallet {
         Ok(())
     }
     
-    pub fn get_total_balance(&self) -> u64 {
-        self.balance + self.shielded_balance
+    /// Debit the sender of a transaction by its amount plus fee
+    ///
+    /// Shielded transactions spend the shielded balance, public ones the transparent
+    /// balance; `InsufficientFunds` leaves the wallet unchanged.
+    pub fn debit_for(&mut self, transaction: &ShieldedTransaction) -> Result<()> {
+        let total = transaction.debit_total()?;
+        match transaction.transaction_type {
+            TransactionType::Shielded => self.spend_shielded(total),
+            TransactionType::Public => self.spend(total),
+        }
+    }
+    
+    /// Credit the recipient of a transaction with its amount, to the balance of its type
+    pub fn credit_for(&mut self, transaction: &ShieldedTransaction) -> Result<()> {
+        match transaction.transaction_type {
+            TransactionType::Shielded => self.add_shielded_funds(transaction.amount),
+            TransactionType::Public => self.add_funds(transaction.amount),
+        }
+    }
+    
+    /// Replace the viewing keypair with a fresh random one
+    ///
+    /// The new key is no longer derived from the spending key, so recovering from
+    /// the mnemonic brings back the original viewing key. Re-encrypt history to the
+    /// new key first with `reencrypt_memos` on a copy of the old wallet.
+    pub fn rotate_viewing_key(&mut self) {
+        let (viewing_public_key, viewing_key) = derive_viewing_keypair(&generate_nonce());
+        self.viewing_public_key = viewing_public_key;
+        self.viewing_key = viewing_key;
+    }
+    
+    /// Re-encrypt every payload addressed to this wallet's viewing key to a new
+    /// viewing public key, returning how many were re-encrypted
+    ///
+    /// Payloads that don't decrypt with this wallet's key are left untouched. Neither
+    /// note openings nor memo ciphertexts are signed, so received transactions are
+    /// re-encrypted as well and every signature stays valid.
+    pub fn reencrypt_memos(&self, txs: &mut [ShieldedTransaction], new_pubkey: &str) -> Result<usize> {
+        let mut reencrypted = 0;
+        for transaction in txs.iter_mut() {
+            for encrypted in transaction.encrypted_notes.iter_mut() {
+                if let Some(note) = encrypted.decrypt(&self.viewing_key) {
+                    *encrypted = note.encrypt(new_pubkey)?;
+                    reencrypted += 1;
+                }
+            }
+            
+            if transaction.reencrypt_memo(&self.viewing_key, new_pubkey)? {
+                reencrypted += 1;
+            }
+        }
+        Ok(reencrypted)
     }
     
+    /// Total value of the notes this wallet holds
+    pub fn note_balance(&self) -> u64 {
+        self.notes.iter().map(|note| note.value).sum()
+    }
+    
+    /// Decrypt the non-empty notes a transaction pays to this wallet's viewing key
+    pub fn scan_outputs(&self, tx: &ShieldedTransaction) -> Vec<Note> {
+        tx.decrypt_notes(&self.viewing_key)
+            .into_iter()
+            .filter(|note| !note.is_empty())
+            .collect()
+    }
+    
+    /// Bring the held notes up to date with a transaction: drop the notes it spends
+    /// and keep the ones it pays to this wallet, returning how many were added
+    pub fn track(&mut self, tx: &ShieldedTransaction) -> usize {
+        self.notes.retain(|note| !tx.nullifiers.contains(&note.nullifier()));
+        
+        let mut added = 0;
+        for note in self.scan_outputs(tx) {
+            if !self.notes.iter().any(|held| held.commitment == note.commitment) {
+                self.notes.push(note);
+                added += 1;
+            }
+        }
+        added
+    }
+    
+    /// Pick native-asset notes covering `amount`, largest first
+    pub fn select_notes(&self, amount: u64) -> Result<Vec<Note>> {
+        let mut candidates: Vec<&Note> = self.notes.iter().filter(|note| note.asset.is_native()).collect();
+        candidates.sort_by_key(|note| std::cmp::Reverse(note.value));
+        
+        let mut selected = Vec::new();
+        let mut total = 0u64;
+        for note in candidates {
+            if total >= amount {
+                break;
+            }
+            total = total.saturating_add(note.value);
+            selected.push(note.clone());
+        }
+        
+        if total < amount || selected.is_empty() {
+            return Err(crate::error::ShieldedError::InsufficientFunds { required: amount, available: total });
+        }
+        Ok(selected)
+    }
+    
+    /// Take the sequence number for this wallet's next transaction, advancing the counter
+    pub fn next_sequence(&mut self) -> u64 {
+        let sequence = self.sequence;
+        self.sequence += 1;
+        sequence
+    }
+    
+    /// Transparent plus shielded balance, failing if the sum overflows
+    pub fn get_total_balance(&self) -> Result<u64> {
+        self.balance
+            .checked_add(self.shielded_balance)
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Total balance overflows".to_string()))
+    }
+    
+    /// Sign a message with this wallet's key scheme
     pub fn sign_message(&self, message: &[u8]) -> Result<String> {
-        // In a real implementation, this would use proper cryptographic signing
-        let mut hasher = Sha256::new();
-        hasher.update(message);
-        hasher.update(self.private_key.as_bytes());
-        let result = hasher.finalize();
-        Ok(hex::encode(result))
+        sign_with(self.key_scheme, message, self.spending_key()?)
+    }
+}
+
+/// Key for one `path` under a wallet seed, domain-separated from every other path
+fn seed_key(seed: &[u8], path: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"seed_key");
+    hasher.update((path.len() as u64).to_le_bytes());
+    hasher.update(path.as_bytes());
+    hasher.update((seed.len() as u64).to_le_bytes());
+    hasher.update(seed);
+    hasher.finalize().into()
+}
+
+/// Fixtures shared by the crate's test modules
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    
+    /// Wallet with deterministic keys, the same on every call with the same `tag`
+    pub(crate) fn test_wallet(tag: char) -> Wallet {
+        Wallet::new_deterministic(&tag.to_string(), KeyScheme::Ed25519).unwrap()
+    }
+    
+    /// Address of `test_wallet(tag)`
+    pub(crate) fn address(tag: char) -> String {
+        test_wallet(tag).address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn unlock_with_the_right_password_recovers_the_key() {
+        let mut wallet = Wallet::new("alice").unwrap();
+        let private_key = wallet.private_key.clone();
+        wallet.encrypt_private_key("hunter2").unwrap();
+        
+        assert!(wallet.is_encrypted());
+        assert!(wallet.spending_key().is_err());
+        assert_eq!(wallet.unlock("hunter2").unwrap(), private_key);
+        
+        let signer = wallet.unlocked("hunter2").unwrap();
+        let message = b"message";
+        let signature = signer.sign_message(message).unwrap();
+        assert!(crate::crypto::verify_signature_with(wallet.key_scheme, message, &signature, &wallet.public_key).unwrap());
+    }
+    
+    #[test]
+    fn unlock_with_a_wrong_password_fails() {
+        let wallet = Wallet::new_encrypted("alice", "hunter2").unwrap();
+        assert!(matches!(wallet.unlock("hunter3"), Err(crate::error::ShieldedError::CryptoError(_))));
+        assert!(wallet.unlocked("hunter3").is_err());
+    }
+    
+    #[test]
+    fn encrypted_wallet_json_has_no_plaintext_key() {
+        let mut wallet = Wallet::new("alice").unwrap();
+        let private_key = wallet.private_key.clone();
+        wallet.encrypt_private_key("hunter2").unwrap();
+        
+        let json = serde_json::to_string(&wallet).unwrap();
+        assert!(!json.contains(&private_key));
+        assert!(!json.contains("\"private_key\""));
+        
+        let restored: Wallet = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.unlock("hunter2").unwrap(), private_key);
+    }
+    
+    #[test]
+    fn derived_addresses_are_distinct_and_stable() {
+        let wallet = Wallet::new("alice").unwrap();
+        let addresses: Vec<String> = (0..3).map(|index| wallet.derive_address(index).unwrap()).collect();
+        
+        assert_ne!(addresses[0], addresses[1]);
+        assert_ne!(addresses[1], addresses[2]);
+        assert_ne!(addresses[0], addresses[2]);
+        assert!(!addresses.contains(&wallet.address));
+        for (index, address) in addresses.iter().enumerate() {
+            Wallet::validate_address(address).unwrap();
+            assert_eq!(&wallet.derive_address(index as u32).unwrap(), address);
+        }
+    }
+    
+    #[test]
+    fn derived_addresses_follow_the_seed() {
+        let mnemonic = Wallet::generate_mnemonic();
+        let original = Wallet::from_mnemonic("alice", &mnemonic).unwrap();
+        let recovered = Wallet::from_mnemonic("alice", &mnemonic).unwrap();
+        let other = Wallet::from_mnemonic("alice", &Wallet::generate_mnemonic()).unwrap();
+        
+        for index in 0..3 {
+            assert_eq!(original.derive_address(index).unwrap(), recovered.derive_address(index).unwrap());
+            assert_ne!(original.derive_address(index).unwrap(), other.derive_address(index).unwrap());
+        }
+    }
+    
+    #[test]
+    fn encrypted_wallet_seals_its_seed() {
+        let mut wallet = Wallet::new("alice").unwrap();
+        let (seed, address) = (wallet.seed.clone(), wallet.derive_address(0).unwrap());
+        wallet.encrypt_private_key("hunter2").unwrap();
+        
+        assert!(!serde_json::to_string(&wallet).unwrap().contains(&seed));
+        assert!(wallet.derive_address(0).is_err());
+        assert_eq!(wallet.unlocked("hunter2").unwrap().derive_address(0).unwrap(), address);
+    }
+    
+    #[test]
+    fn receive_address_signs_with_its_derived_key() {
+        let mut wallet = Wallet::new("alice").unwrap();
+        let address = wallet.new_receive_address().unwrap();
+        assert!(wallet.owns_address(&address));
+        
+        // Only the signer is checked here, so the id made for the main address can stay
+        let mut transaction = ShieldedTransaction::create_public(&wallet, &wallet.address, 100).unwrap();
+        transaction.from = address.clone();
+        transaction.resign(&wallet).unwrap();
+        let signer_key = transaction.signer_public_key.clone().unwrap();
+        assert_eq!(Wallet::generate_address(&signer_key).unwrap(), address);
+        assert_eq!(signer_key, wallet.derive_keypair(0).unwrap().0);
+        assert!(transaction.is_signature_valid());
+    }
+    
+    #[test]
+    fn reencrypted_memo_reads_with_the_new_key_and_stays_signed() {
+        let old = Wallet::new("alice").unwrap();
+        let mut transaction = ShieldedTransaction::create_shielded_with_memo(
+            &old, &old.address, 100, "rent", &old.viewing_public_key,
+        ).unwrap();
+        let notes = transaction.decrypt_notes(&old.viewing_key).len();
+        
+        let mut rotated = old.clone();
+        rotated.rotate_viewing_key();
+        let reencrypted = old.reencrypt_memos(std::slice::from_mut(&mut transaction), &rotated.viewing_public_key).unwrap();
+        
+        assert_eq!(reencrypted, notes + 1);
+        assert_eq!(transaction.decrypt_memo(&rotated.viewing_key).unwrap(), "rent");
+        assert!(transaction.decrypt_memo(&old.viewing_key).is_err());
+        assert_eq!(transaction.decrypt_notes(&rotated.viewing_key).len(), notes);
+        assert!(transaction.decrypt_notes(&old.viewing_key).is_empty());
+        assert!(transaction.is_signature_valid());
+    }
+    
+    #[test]
+    fn memo_from_another_sender_is_reencrypted_and_stays_signed() {
+        let sender = Wallet::new("alice").unwrap();
+        let recipient = Wallet::new("bob").unwrap();
+        let mut transaction = ShieldedTransaction::create_shielded_with_memo(
+            &sender, &recipient.address, 100, "rent", &recipient.viewing_public_key,
+        ).unwrap();
+        
+        let mut rotated = recipient.clone();
+        rotated.rotate_viewing_key();
+        let reencrypted = recipient.reencrypt_memos(std::slice::from_mut(&mut transaction), &rotated.viewing_public_key).unwrap();
+        
+        assert_eq!(reencrypted, 2);
+        assert_eq!(transaction.decrypt_memo(&rotated.viewing_key).unwrap(), "rent");
+        assert!(transaction.decrypt_memo(&recipient.viewing_key).is_err());
+        assert!(transaction.is_signature_valid());
+    }
+    
+    #[test]
+    fn recovered_spending_key_is_derived_not_raw_seed() {
+        let mnemonic = Wallet::generate_mnemonic();
+        let wallet = Wallet::recover("alice", &mnemonic, "").unwrap();
+        let seed = bip39::Mnemonic::parse(&mnemonic).unwrap().to_seed("");
+        
+        assert_ne!(wallet.private_key, hex::encode(&seed[..32]));
+        assert_eq!(wallet.private_key, Wallet::recover("alice", &mnemonic, "").unwrap().private_key);
+        assert_ne!(wallet.private_key, Wallet::recover("alice", &mnemonic, "extra words").unwrap().private_key);
+    }
+    
+    #[test]
+    fn signature_verifies_only_with_the_signers_public_key() {
+        let alice = Wallet::new("alice").unwrap();
+        let bob = Wallet::new("bob").unwrap();
+        assert_eq!(alice.key_scheme, KeyScheme::Ed25519);
+        assert_eq!(hex::decode(&alice.public_key).unwrap().len(), 32);
+        
+        let signature = alice.sign_message(b"pay bob 100").unwrap();
+        assert!(crate::crypto::verify_signature(b"pay bob 100", &signature, &alice.public_key).unwrap());
+        assert!(!crate::crypto::verify_signature(b"pay bob 100", &signature, &bob.public_key).unwrap());
+        assert!(!crate::crypto::verify_signature(b"pay bob 101", &signature, &alice.public_key).unwrap());
+    }
+    
+    #[test]
+    fn mnemonic_recovers_the_same_wallet_and_bad_checksums_are_rejected() {
+        for word_count in [12, 24] {
+            let mnemonic = Wallet::generate_mnemonic_with(word_count).unwrap();
+            assert_eq!(mnemonic.split_whitespace().count(), word_count);
+            let original = Wallet::from_mnemonic("alice", &mnemonic).unwrap();
+            let recovered = Wallet::from_mnemonic("alice", &mnemonic).unwrap();
+            assert_eq!(original.address, recovered.address);
+            assert_eq!(original.public_key, recovered.public_key);
+            assert_eq!(original.private_key, recovered.private_key);
+        }
+        
+        // Twelve "abandon"s are all dictionary words, but the last one fails the checksum
+        let valid = format!("{} about", ["abandon"; 11].join(" "));
+        assert!(Wallet::from_mnemonic("alice", &valid).is_ok());
+        let bad_checksum = ["abandon"; 12].join(" ");
+        assert!(matches!(Wallet::from_mnemonic("alice", &bad_checksum), Err(crate::error::ShieldedError::CryptoError(_))));
+    }
+    
+    #[test]
+    fn validate_address_checks_prefix_length_and_hex() {
+        let body = "0123456789abcdef0123456789abcdef01234567";
+        assert!(Wallet::validate_address(&format!("{}{}", ADDRESS_PREFIX, body)).is_ok());
+        assert!(Wallet::validate_address(&Wallet::new("alice").unwrap().address).is_ok());
+        
+        let malformed = [
+            body.to_string(),
+            format!("{}{}", ADDRESS_PREFIX, &body[..39]),
+            format!("{}{}0", ADDRESS_PREFIX, body),
+            format!("{}{}g", ADDRESS_PREFIX, &body[..39]),
+            String::new(),
+        ];
+        for address in &malformed {
+            assert!(
+                matches!(Wallet::validate_address(address), Err(crate::error::ShieldedError::InvalidWalletAddress(_))),
+                "{:?}",
+                address
+            );
+            let valid = Wallet::new("bob").unwrap();
+            assert!(matches!(
+                ShieldedTransaction::create_public(&valid, address, 100),
+                Err(crate::error::ShieldedError::InvalidWalletAddress(_))
+            ));
+            assert!(matches!(
+                ShieldedTransaction::create_shielded(&valid, address, 100),
+                Err(crate::error::ShieldedError::InvalidWalletAddress(_))
+            ));
+        }
+    }
+    
+    #[test]
+    fn new_wallets_refuse_the_legacy_scheme() {
+        assert!(Wallet::new_with_scheme("alice", KeyScheme::Legacy).is_err());
+        assert!(Wallet::new_deterministic("alice", KeyScheme::Legacy).is_err());
     }
 }
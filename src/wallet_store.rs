@@ -0,0 +1,223 @@
+use crate::{
+    error::Result,
+    shielded_transaction::ShieldedTransaction,
+    storage::{read_json, write_json},
+    wallet::Wallet,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const WALLET_FILE: &str = "wallets.json";
+
+/// Wallets persisted between runs, keyed by name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletStore {
+    wallets: BTreeMap<String, Wallet>,
+    /// Directory the wallet file lives in; empty for the working directory
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl WalletStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Load the wallet file, or start empty if there is none yet
+    pub fn load() -> Result<Self> {
+        Self::load_from(Path::new(""))
+    }
+    
+    /// Load the wallet file from `dir`, where later saves will write it
+    pub fn load_from(dir: &Path) -> Result<Self> {
+        let path = dir.join(WALLET_FILE);
+        let mut store = if path.exists() {
+            read_json(&path, "wallet file")?
+        } else {
+            Self::new()
+        };
+        store.dir = dir.to_path_buf();
+        Ok(store)
+    }
+    
+    /// Save all wallets to disk
+    pub fn save(&self) -> Result<()> {
+        write_json(&self.dir.join(WALLET_FILE), self, "wallet file")
+    }
+    
+    /// Store a new wallet and save, refusing to overwrite an existing name
+    pub fn add_wallet(&mut self, wallet: Wallet) -> Result<()> {
+        if self.wallets.contains_key(&wallet.name) {
+            return Err(crate::error::ShieldedError::InvalidWalletAddress(
+                format!("Wallet '{}' already exists", wallet.name)
+            ));
+        }
+        self.wallets.insert(wallet.name.clone(), wallet);
+        self.save()
+    }
+    
+    /// Replace a stored wallet with an updated copy and save
+    pub fn update_wallet(&mut self, wallet: Wallet) -> Result<()> {
+        if !self.wallets.contains_key(&wallet.name) {
+            return Err(crate::error::ShieldedError::WalletNotFound(wallet.name));
+        }
+        self.wallets.insert(wallet.name.clone(), wallet);
+        self.save()
+    }
+    
+    /// Record a transfer in one save: store the sender, already debited by
+    /// `create_public_from` or `create_shielded_from`, and credit the stored wallet
+    /// at the transaction's `to` address, if any
+    ///
+    /// `sender` is `None` for a sender outside the store, and a sender paying itself
+    /// gets the amount back. Nothing is changed if the sender is not stored or a
+    /// credit fails.
+    pub fn record_transfer(&mut self, mut sender: Option<Wallet>, transaction: &ShieldedTransaction) -> Result<()> {
+        if let Some(sender) = &sender {
+            if !self.wallets.contains_key(&sender.name) {
+                return Err(crate::error::ShieldedError::WalletNotFound(sender.name.clone()));
+            }
+        }
+        let mut recipient = match sender.as_mut() {
+            Some(sender) if sender.owns_address(&transaction.to) => {
+                sender.credit_for(transaction)?;
+                None
+            }
+            _ => self.find(&transaction.to).cloned(),
+        };
+        if let Some(recipient) = recipient.as_mut() {
+            recipient.credit_for(transaction)?;
+        }
+        
+        for wallet in sender.into_iter().chain(recipient) {
+            self.wallets.insert(wallet.name.clone(), wallet);
+        }
+        self.save()
+    }
+    
+    /// Start every wallet's sequence over, for a store whose transactions were cleared
+    pub fn reset_sequences(&mut self) -> Result<()> {
+        for wallet in self.wallets.values_mut() {
+            wallet.sequence = 0;
+        }
+        self.save()
+    }
+    
+    /// Get a wallet by name
+    pub fn get_wallet(&self, name: &str) -> Result<&Wallet> {
+        self.wallets
+            .get(name)
+            .ok_or_else(|| crate::error::ShieldedError::WalletNotFound(name.to_string()))
+    }
+    
+    /// Every stored wallet, ordered by name
+    pub fn list_wallets(&self) -> Vec<&Wallet> {
+        self.wallets.values().collect()
+    }
+    
+    /// Find a wallet by name, address or one of its receive addresses
+    pub fn find(&self, name_or_address: &str) -> Option<&Wallet> {
+        self.wallets.get(name_or_address).or_else(|| {
+            self.wallets.values().find(|wallet| wallet.owns_address(name_or_address))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::TransactionPolicy;
+    
+    fn store_with(dir: &Path, names: &[&str]) -> WalletStore {
+        let mut store = WalletStore::load_from(dir).unwrap();
+        for name in names {
+            store.add_wallet(Wallet::new(name).unwrap()).unwrap();
+        }
+        store
+    }
+    
+    #[test]
+    fn transfer_moves_balances() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with(dir.path(), &["alice", "bob"]);
+        let mut alice = store.get_wallet("alice").unwrap().clone();
+        let bob_address = store.get_wallet("bob").unwrap().address.clone();
+        
+        let transaction = ShieldedTransaction::create_public_from(&mut alice, &bob_address, 100, &TransactionPolicy::default()).unwrap();
+        assert_eq!(alice.balance, 1_000 - 100 - transaction.fee);
+        store.record_transfer(Some(alice), &transaction).unwrap();
+        
+        let reloaded = WalletStore::load_from(dir.path()).unwrap();
+        assert_eq!(reloaded.get_wallet("alice").unwrap().balance, 1_000 - 100 - transaction.fee);
+        assert_eq!(reloaded.get_wallet("bob").unwrap().balance, 1_100);
+    }
+    
+    #[test]
+    fn shielded_transfer_moves_shielded_balances() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with(dir.path(), &["alice", "bob"]);
+        let mut alice = store.get_wallet("alice").unwrap().clone();
+        alice.add_shielded_funds(500).unwrap();
+        let bob = store.get_wallet("bob").unwrap().clone();
+        
+        let transaction = ShieldedTransaction::create_shielded_from(
+            &mut alice,
+            &bob.address,
+            Some(&bob.viewing_public_key),
+            100,
+            None,
+            &TransactionPolicy::default(),
+        ).unwrap();
+        store.record_transfer(Some(alice), &transaction).unwrap();
+        
+        assert_eq!(store.get_wallet("alice").unwrap().shielded_balance, 500 - 100 - transaction.fee);
+        assert_eq!(store.get_wallet("alice").unwrap().balance, 1_000);
+        assert_eq!(store.get_wallet("bob").unwrap().shielded_balance, 100);
+    }
+    
+    #[test]
+    fn sender_paying_itself_only_loses_the_fee() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with(dir.path(), &["alice"]);
+        let mut alice = store.get_wallet("alice").unwrap().clone();
+        let address = alice.address.clone();
+        
+        let transaction = ShieldedTransaction::create_public_from(&mut alice, &address, 100, &TransactionPolicy::default()).unwrap();
+        store.record_transfer(Some(alice), &transaction).unwrap();
+        assert_eq!(store.get_wallet("alice").unwrap().balance, 1_000 - transaction.fee);
+    }
+    
+    #[test]
+    fn over_budget_transfer_is_rejected_before_anything_is_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with(dir.path(), &["alice", "bob"]);
+        let mut alice = store.get_wallet("alice").unwrap().clone();
+        let bob_address = store.get_wallet("bob").unwrap().address.clone();
+        
+        let error = ShieldedTransaction::create_public_from(&mut alice, &bob_address, 1_000, &TransactionPolicy::default())
+            .unwrap_err();
+        assert!(matches!(error, crate::error::ShieldedError::InsufficientFunds { required: 1_001, available: 1_000 }));
+        assert_eq!(alice.balance, 1_000);
+        
+        let error = ShieldedTransaction::create_shielded_from(&mut alice, &bob_address, None, 1, None, &TransactionPolicy::default())
+            .unwrap_err();
+        assert!(matches!(error, crate::error::ShieldedError::InsufficientFunds { .. }));
+        
+        let reloaded = WalletStore::load_from(dir.path()).unwrap();
+        assert_eq!(reloaded.get_wallet("alice").unwrap().balance, 1_000);
+        assert_eq!(reloaded.get_wallet("bob").unwrap().balance, 1_000);
+    }
+    
+    #[test]
+    fn wallet_is_found_by_its_receive_addresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with(dir.path(), &["alice", "bob"]);
+        let mut alice = store.get_wallet("alice").unwrap().clone();
+        let address = alice.new_receive_address().unwrap();
+        store.update_wallet(alice).unwrap();
+        
+        let reloaded = WalletStore::load_from(dir.path()).unwrap();
+        assert_eq!(reloaded.find(&address).unwrap().name, "alice");
+    }
+}
@@ -1,36 +1,75 @@
-use crate::{error::Result, crypto::{hash, generate_nonce}};
+use crate::{error::Result, crypto::generate_nonce, proof_verifier::ProofVerifierRegistry};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use hex;
 use chrono::{DateTime, Utc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct ZeroKnowledgeProof {
     pub proof_id: String,
     pub transaction_id: String,
     pub proof_data: String,
     pub public_inputs: Vec<String>,
+    #[cfg_attr(feature = "borsh", borsh(serialize_with = "crate::borsh_time::serialize", deserialize_with = "crate::borsh_time::deserialize"))]
     pub timestamp: DateTime<Utc>,
     pub proof_type: ProofType,
+    #[serde(default = "ZeroKnowledgeProof::default_version")]
+    pub version: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[cfg_attr(feature = "borsh", borsh(use_discriminant = true))]
 pub enum ProofType {
-    SpendProof,
-    OutputProof,
-    BalanceProof,
-    RangeProof,
+    SpendProof = 0,
+    OutputProof = 1,
+    BalanceProof = 2,
+    RangeProof = 3,
 }
 
 impl ZeroKnowledgeProof {
+    /// Version tag of the proofs produced by this crate
+    pub const DEFAULT_VERSION: u32 = 1;
+    
+    fn default_version() -> u32 {
+        Self::DEFAULT_VERSION
+    }
+    
     /// Generate a zero-knowledge proof for a transaction
-    pub fn generate(transaction_id: &str) -> Result<String> {
-        let proof_id = Self::generate_proof_id(transaction_id)?;
-        let proof_data = Self::create_proof_data(transaction_id)?;
+    ///
+    /// The proof id is fresh randomness and the proof data binds it to the
+    /// transaction id and public inputs, so the proof only verifies for those.
+    pub fn generate(transaction_id: &str, public_inputs: &[String]) -> Result<String> {
+        Self::generate_with(transaction_id, public_inputs, &generate_nonce())
+    }
+    
+    /// Generate the proof for a transaction from given randomness, deterministically
+    pub fn generate_with(transaction_id: &str, public_inputs: &[String], randomness: &[u8; 32]) -> Result<String> {
+        let proof_id = hex::encode(randomness);
+        let proof_data = Self::binding(transaction_id, &proof_id, public_inputs);
         
         Ok(format!("{}:{}", proof_id, proof_data))
     }
     
+    /// Decode a `proof_id:proof_data` string produced by `generate` into a spend proof
+    /// claimed for a transaction and public inputs
+    pub fn decode(transaction_id: &str, public_inputs: &[String], encoded: &str) -> Result<Self> {
+        let (proof_id, proof_data) = encoded.split_once(':').ok_or_else(|| {
+            crate::error::ShieldedError::ZKProofError("Proof must be of the form proof_id:proof_data".to_string())
+        })?;
+        
+        Ok(Self {
+            proof_id: proof_id.to_string(),
+            transaction_id: transaction_id.to_string(),
+            proof_data: proof_data.to_string(),
+            public_inputs: public_inputs.to_vec(),
+            timestamp: Utc::now(),
+            proof_type: ProofType::SpendProof,
+            version: Self::DEFAULT_VERSION,
+        })
+    }
+    
     /// Create a complete ZK proof structure
     pub fn create_spend_proof(
         transaction_id: &str,
@@ -38,47 +77,73 @@ impl ZeroKnowledgeProof {
         output_commitments: &[String],
         balance_proof: &str,
     ) -> Result<Self> {
-        let proof_id = Self::generate_proof_id(transaction_id)?;
-        let proof_data = Self::create_spend_proof_data(input_commitments, output_commitments, balance_proof)?;
+        let proof_id = hex::encode(generate_nonce());
+        let public_inputs = vec![
+            format!("input_count:{}", input_commitments.len()),
+            format!("output_count:{}", output_commitments.len()),
+            format!("statement:{}", Self::create_spend_proof_data(input_commitments, output_commitments, balance_proof)?),
+        ];
+        let proof_data = Self::binding(transaction_id, &proof_id, &public_inputs);
         
         Ok(Self {
             proof_id,
             transaction_id: transaction_id.to_string(),
             proof_data,
-            public_inputs: vec![
-                format!("input_count:{}", input_commitments.len()),
-                format!("output_count:{}", output_commitments.len()),
-            ],
+            public_inputs,
             timestamp: Utc::now(),
             proof_type: ProofType::SpendProof,
+            version: Self::DEFAULT_VERSION,
         })
     }
     
-    /// Verify a zero-knowledge proof
+    /// Tag the proof with a version so it is routed to a matching verifier
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+    
+    /// Verify a zero-knowledge proof through the global verifier registry
     pub fn verify(&self) -> Result<bool> {
-        // In a real implementation, this would verify the actual ZK proof
-        // For this demo, we'll simulate verification
-        Ok(self.proof_data.len() >= 64 && self.proof_id.len() >= 32)
+        crate::proof_verifier::verify_registered(self)
     }
     
-    /// Generate a proof ID based on transaction ID
-    fn generate_proof_id(transaction_id: &str) -> Result<String> {
-        let mut hasher = Sha256::new();
-        hasher.update(transaction_id.as_bytes());
-        hasher.update(b"zk_proof");
-        hasher.update(generate_nonce());
-        
-        Ok(hex::encode(&hasher.finalize()[..16]))
+    /// Verify a proof claimed for a transaction with the given public inputs
+    ///
+    /// Fails when the proof was made for another transaction or other inputs.
+    pub fn verify_for(&self, transaction_id: &str, public_inputs: &[String]) -> Result<bool> {
+        Ok(self.transaction_id == transaction_id
+            && self.public_inputs == public_inputs
+            && self.verify()?)
     }
     
-    /// Create proof data for a transaction
-    fn create_proof_data(transaction_id: &str) -> Result<String> {
-        let mut hasher = Sha256::new();
-        hasher.update(transaction_id.as_bytes());
-        hasher.update(b"proof_data");
-        hasher.update(generate_nonce());
+    /// Verify many proofs through the global verifier registry, one result per proof
+    ///
+    /// Each verifier gets all of its proofs at once. Fails without verifying
+    /// anything if some proof has no registered verifier.
+    pub fn verify_batch(proofs: &[ZeroKnowledgeProof]) -> Result<Vec<bool>> {
+        crate::proof_verifier::verify_batch_registered(proofs)
+    }
+    
+    /// Verify a zero-knowledge proof through a specific registry
+    pub fn verify_with(&self, registry: &ProofVerifierRegistry) -> Result<bool> {
+        registry.verify(self)
+    }
+    
+    /// Proof data binding the committed randomness (the proof id) to a
+    /// transaction and its public inputs
+    pub fn binding(transaction_id: &str, proof_id: &str, public_inputs: &[String]) -> String {
+        Self::binding_with(&mut Sha256::new(), transaction_id, proof_id, public_inputs)
+    }
+    
+    /// `binding` on a caller-owned hasher, which is left reset for the next proof
+    pub(crate) fn binding_with(hasher: &mut Sha256, transaction_id: &str, proof_id: &str, public_inputs: &[String]) -> String {
+        hasher.update(b"zk_binding");
+        for field in [transaction_id, proof_id].into_iter().chain(public_inputs.iter().map(String::as_str)) {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
         
-        Ok(hex::encode(hasher.finalize()))
+        hex::encode(hasher.finalize_reset())
     }
     
     /// Create spend proof data
@@ -126,7 +191,10 @@ impl ZeroKnowledgeProof {
     
     /// Create a balance proof showing total input equals total output
     pub fn create_balance_proof(input_total: u64, output_total: u64, fee: u64) -> Result<String> {
-        if input_total != output_total + fee {
+        let required = output_total
+            .checked_add(fee)
+            .ok_or_else(|| crate::error::ShieldedError::InvalidAmount("Output total plus fee overflows".to_string()))?;
+        if input_total != required {
             return Err(crate::error::ShieldedError::InvalidTransaction(
                 "Input total does not equal output total plus fee".to_string()
             ));